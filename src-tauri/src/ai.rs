@@ -1,5 +1,7 @@
-use tauri::command;
-use crate::database::{Database, DwightMemory};
+use tauri::{command, State};
+use crate::audio_decode;
+use crate::database::DwightMemory;
+use crate::state::AppState;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -183,17 +185,15 @@ impl DwightAI {
 #[command]
 pub async fn chat_with_dwight(
     user_input: String,
-    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
 ) -> Result<DwightResponse, String> {
-    let config = app_handle.config();
-    let db = Database::new(&config).map_err(|e| format!("Database error: {}", e))?;
+    let db = state.db()?;
     
     // Get recent conversation context
     let context = db.get_dwight_memory_context(10).map_err(|e| format!("Database error: {}", e))?;
     
     // Generate AI response
-    let ai = DwightAI::new();
-    let response = ai.generate_response(&user_input, &context);
+    let response = state.dwight.generate_response(&user_input, &context);
     
     // Save this interaction to memory
     let memory = DwightMemory {
@@ -205,6 +205,7 @@ pub async fn chat_with_dwight(
     };
     
     db.save_dwight_memory(&memory).map_err(|e| format!("Database error: {}", e))?;
+    state.request_index_update();
     
     Ok(response)
 }
@@ -225,11 +226,26 @@ pub async fn analyze_audio_intelligence(
     // For now, return some intelligent analysis based on file properties
     let mut analysis = Vec::new();
     
-    if audio_file_path.ends_with(".wav") {
-        analysis.push("WAV format detected - high quality uncompressed audio".to_string());
-    } else if audio_file_path.ends_with(".mp3") {
-        analysis.push("MP3 format detected - compressed audio, may have some quality loss".to_string());
+    let path = audio_file_path.clone();
+    let decoded = tokio::task::spawn_blocking(move || audio_decode::decode_file(&path, None))
+        .await
+        .map_err(|e| format!("Audio decode error: {}", e))?
+        .map_err(|e| format!("Audio decode error: {}", e))?;
+    
+    match decoded.codec.as_str() {
+        "pcm_s16le" | "pcm_s24le" | "pcm_s32le" | "pcm_f32le" | "flac" | "alac" => {
+            analysis.push(format!("{} audio detected - lossless, high quality source", decoded.codec));
+        }
+        codec => {
+            analysis.push(format!("{} audio detected - compressed audio, may have some quality loss", codec));
+        }
     }
+    analysis.push(format!(
+        "{:.1} seconds, {} Hz, {} channel(s)",
+        decoded.duration_seconds(),
+        decoded.source_sample_rate,
+        decoded.channels
+    ));
     
     analysis.push("Audio file ready for transcription and pattern analysis".to_string());
     analysis.push("I can detect speech, identify speakers, and find non-verbal sounds".to_string());
@@ -1,176 +1,536 @@
-use tauri::command;
+use tauri::{command, AppHandle, Manager, State};
+use crate::llm_provider::{self, GenerationOptions, ModelDefaults, ProviderKind, Usage};
+use crate::model_registry::{ModelProbe, ModelRegistry};
+use crate::rag::{self, RagHit};
+use crate::state::AppState;
 use serde::{Deserialize, Serialize};
 use reqwest;
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use std::collections::HashMap;
+use std::sync::{Mutex, RwLock};
+use std::time::{Duration, Instant};
+use tokio::sync::oneshot;
+
+/// Emitted with an [`LlmToken`] for each piece of a streamed response.
+pub const LLM_TOKEN_EVENT: &str = "llm-token";
+/// Emitted with an [`LlmStreamEnd`] once a streamed response finishes, fails
+/// or is cancelled.
+pub const LLM_STREAM_END_EVENT: &str = "llm-stream-end";
+
+/// Used when a command doesn't name a model, if it is enabled.
+const PREFERRED_CHAT_MODEL: &str = "llama3:8b";
+const PREFERRED_ANALYSIS_MODEL: &str = "mixtral:8x7b";
+
+/// How long a probe waits for a model server to list its models.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ModelConfig {
+    /// The model name sent to the server, e.g. `llama3:8b` for Ollama
+    pub id: String,
+    /// Display name
     pub name: String,
     pub model_type: String,
     pub api_endpoint: Option<String>,
     pub local_path: Option<String>,
     pub enabled: bool,
+    /// Which API `api_endpoint` speaks
+    #[serde(default)]
+    pub provider: ProviderKind,
+    /// Sent as a bearer token, for OpenAI-compatible servers that want one
+    #[serde(default)]
+    pub api_key: Option<String>,
+    #[serde(default)]
+    pub defaults: ModelDefaults,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LlamaResponse {
     pub text: String,
-    pub tokens_used: usize,
+    /// Prompt plus answer tokens; `None` if the server didn't report usage
+    pub tokens_used: Option<usize>,
     pub processing_time_ms: u64,
-    pub confidence: f32,
+    /// The canned stand-in given when the model isn't configured, not an
+    /// answer from any model
+    pub simulated: bool,
+    /// Token counts and timings as reported by the server
+    pub usage: Option<Usage>,
+    /// Answer tokens generated per second
+    pub tokens_per_second: Option<f32>,
+}
+
+impl LlamaResponse {
+    /// `generation_time` is how long the answer took to produce, used for
+    /// tokens/sec when the server doesn't time generation itself.
+    fn new(text: String, usage: Option<Usage>, start_time: Instant, generation_time: Duration, simulated: bool) -> Self {
+        let tokens_used = usage.as_ref().and_then(|u| match (u.prompt_tokens, u.completion_tokens) {
+            (None, None) => None,
+            (prompt_tokens, completion_tokens) => Some(prompt_tokens.unwrap_or(0) as usize + completion_tokens.unwrap_or(0) as usize),
+        });
+        let tokens_per_second = usage.as_ref().and_then(|u| {
+            u.tokens_per_second().or_else(|| {
+                let seconds = generation_time.as_secs_f32();
+                u.completion_tokens.filter(|_| seconds > 0.0).map(|tokens| tokens as f32 / seconds)
+            })
+        });
+        LlamaResponse {
+            text,
+            tokens_used,
+            processing_time_ms: start_time.elapsed().as_millis() as u64,
+            simulated,
+            usage,
+            tokens_per_second,
+        }
+    }
 }
 
+/// Retrieved excerpts and, unless only retrieval was asked for, the model's
+/// answer based on them.
 #[derive(Debug, Serialize, Deserialize)]
-pub struct RAGContext {
+pub struct RagSearchResult {
     pub query: String,
-    pub documents: Vec<String>,
-    pub embeddings: Vec<Vec<f32>>,
-    pub similarity_scores: Vec<f32>,
+    pub hits: Vec<RagHit>,
+    pub answer: Option<LlamaResponse>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LlmToken {
+    pub request_id: String,
+    pub token: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LlmStreamEnd {
+    pub request_id: String,
+    /// The whole answer; `None` if the stream failed or was cancelled
+    pub response: Option<LlamaResponse>,
+    pub cancelled: bool,
+    pub error: Option<String>,
 }
 
 pub struct AdvancedAI {
-    models: HashMap<String, ModelConfig>,
+    registry: RwLock<ModelRegistry>,
     client: reqwest::Client,
+    /// Cancel senders for streams still in flight, by request id
+    streams: Mutex<HashMap<String, oneshot::Sender<()>>>,
 }
 
 impl AdvancedAI {
-    pub fn new() -> Self {
-        let mut models = HashMap::new();
-        
-        // Configure Llama 3 models
-        models.insert("llama3-8b".to_string(), ModelConfig {
-            name: "Llama 3 8B".to_string(),
-            model_type: "llama".to_string(),
-            api_endpoint: Some("http://localhost:11434/api/generate".to_string()), // Ollama endpoint
-            local_path: None,
-            enabled: true,
-        });
-        
-        models.insert("llama3-70b".to_string(), ModelConfig {
-            name: "Llama 3 70B".to_string(),
-            model_type: "llama".to_string(),
-            api_endpoint: Some("http://localhost:11434/api/generate".to_string()),
-            local_path: None,
-            enabled: false, // Disabled by default due to resource requirements
-        });
-        
-        // Configure Mixtral models
-        models.insert("mixtral-8x7b".to_string(), ModelConfig {
-            name: "Mixtral 8x7B".to_string(),
-            model_type: "mixtral".to_string(),
-            api_endpoint: Some("http://localhost:11434/api/generate".to_string()),
-            local_path: None,
-            enabled: true,
-        });
-        
-        // Configure Mistral models
-        models.insert("mistral-7b".to_string(), ModelConfig {
-            name: "Mistral 7B".to_string(),
-            model_type: "mistral".to_string(),
-            api_endpoint: Some("http://localhost:11434/api/generate".to_string()),
-            local_path: None,
-            enabled: true,
-        });
-        
+    pub fn new(app_config: &tauri::Config) -> Self {
+        let registry = ModelRegistry::load(app_config);
         let client = reqwest::Client::new();
         
-        AdvancedAI { models, client }
+        AdvancedAI { registry: RwLock::new(registry), client, streams: Mutex::new(HashMap::new()) }
+    }
+    
+    /// A snapshot of the configured models.
+    pub fn registry(&self) -> ModelRegistry {
+        match self.registry.read() {
+            Ok(registry) => registry.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        }
+    }
+    
+    fn model(&self, id: &str) -> Option<ModelConfig> {
+        self.registry().get(id).cloned()
+    }
+    
+    /// Runs `edit` on a copy of the registry and keeps the copy only if it
+    /// succeeds. Edits are serialised, so none is lost to a concurrent one.
+    pub fn edit_registry<T>(&self, edit: impl FnOnce(&mut ModelRegistry) -> Result<T, String>) -> Result<T, String> {
+        let mut registry = match self.registry.write() {
+            Ok(registry) => registry,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let mut edited = registry.clone();
+        let result = edit(&mut edited)?;
+        *registry = edited;
+        Ok(result)
+    }
+    
+    /// `requested` if given, otherwise `preferred` when it is enabled, or
+    /// else the first enabled model.
+    pub fn resolve_model(&self, requested: Option<String>, preferred: &str) -> String {
+        if let Some(requested) = requested {
+            return requested;
+        }
+        let registry = self.registry();
+        if registry.get(preferred).is_some_and(|m| m.enabled) {
+            return preferred.to_string();
+        }
+        registry
+            .models
+            .iter()
+            .find(|m| m.enabled)
+            .map_or_else(|| preferred.to_string(), |m| m.id.clone())
     }
     
-    pub async fn query_llama(&self, prompt: &str, model: &str) -> Result<LlamaResponse> {
-        let start_time = std::time::Instant::now();
+    /// Answers `prompt` with `model`, applying `options` over the model's
+    /// defaults. Unknown models and models without an endpoint get a
+    /// simulated answer; a server that fails or refuses is an error.
+    pub async fn query_llama(&self, prompt: &str, model: &str, options: &GenerationOptions) -> Result<LlamaResponse> {
+        let start_time = Instant::now();
         
-        if let Some(config) = self.models.get(model) {
+        if let Some(config) = self.model(model) {
+            if config.provider == ProviderKind::Stub {
+                let text = llm_provider::stub_reply(model, prompt);
+                return Ok(LlamaResponse::new(text, None, start_time, start_time.elapsed(), false));
+            }
+            
             if let Some(endpoint) = &config.api_endpoint {
-                let payload = serde_json::json!({
-                    "model": model,
-                    "prompt": prompt,
-                    "stream": false,
-                    "options": {
-                        "temperature": 0.7,
-                        "top_p": 0.9,
-                        "max_tokens": 512,
-                    }
-                });
+                let options = options.or_defaults(&config.defaults);
+                let payload = llm_provider::request_body(config.provider, model, prompt, false, &options);
                 
-                let response = self.client
-                    .post(endpoint)
+                let response = self.post(&config, endpoint)
                     .json(&payload)
                     .send()
                     .await?;
                 
-                if response.status().is_success() {
-                    let result: serde_json::Value = response.json().await?;
-                    let text = result["response"].as_str().unwrap_or("No response").to_string();
-                    
-                    return Ok(LlamaResponse {
-                        text,
-                        tokens_used: prompt.split_whitespace().count(),
-                        processing_time_ms: start_time.elapsed().as_millis() as u64,
-                        confidence: 0.85,
-                    });
+                let status = response.status();
+                if !status.is_success() {
+                    let body = response.text().await.unwrap_or_default();
+                    return Err(anyhow!("Model server returned {}: {}", status, body.trim()));
                 }
+                let result: serde_json::Value = response.json().await?;
+                let text = llm_provider::response_text(config.provider, &result)?;
+                let usage = llm_provider::usage(config.provider, &result);
+                
+                return Ok(LlamaResponse::new(text, usage, start_time, start_time.elapsed(), false));
             }
         }
         
         // Fallback to local implementation
-        Ok(LlamaResponse {
-            text: format!("Fallback response for: {}\n\nThis is a simulated response from {}. In a full implementation, this would be processed by the actual AI model.", prompt, model),
-            tokens_used: prompt.split_whitespace().count(),
-            processing_time_ms: start_time.elapsed().as_millis() as u64,
-            confidence: 0.6,
-        })
+        let text = format!("Fallback response for: {}\n\nThis is a simulated response from {}. In a full implementation, this would be processed by the actual AI model.", prompt, model);
+        Ok(LlamaResponse::new(text, None, start_time, start_time.elapsed(), true))
+    }
+    
+    fn post(&self, config: &ModelConfig, endpoint: &str) -> reqwest::RequestBuilder {
+        let request = self.client.post(endpoint);
+        match &config.api_key {
+            Some(key) => request.bearer_auth(key),
+            None => request,
+        }
     }
     
-    pub async fn rag_query(&self, query: &str, context_docs: Vec<String>) -> Result<LlamaResponse> {
-        // Simplified RAG implementation
-        let mut enriched_prompt = format!("Context documents:\n");
+    /// Like [`AdvancedAI::query_llama`] but reads the server's stream,
+    /// passing each piece of text to `on_token` as it arrives. Returns `None`
+    /// if `cancel` fires first; dropping the response aborts the request.
+    pub async fn stream_llama(
+        &self,
+        prompt: &str,
+        model: &str,
+        options: &GenerationOptions,
+        mut cancel: oneshot::Receiver<()>,
+        mut on_token: impl FnMut(&str),
+    ) -> Result<Option<LlamaResponse>> {
+        let start_time = Instant::now();
+        let config = self.model(model).ok_or_else(|| anyhow!("Unknown model {}", model))?;
         
-        for (i, doc) in context_docs.iter().enumerate() {
-            enriched_prompt.push_str(&format!("Document {}: {}\n", i + 1, doc));
+        if config.provider == ProviderKind::Stub {
+            let text = llm_provider::stub_reply(model, prompt);
+            for word in text.split_inclusive(' ') {
+                if cancel.try_recv().is_ok() {
+                    return Ok(None);
+                }
+                on_token(word);
+                tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+            }
+            return Ok(Some(LlamaResponse::new(text, None, start_time, start_time.elapsed(), false)));
         }
         
-        enriched_prompt.push_str(&format!("\nQuery: {}\n\nPlease answer the query based on the provided context.", query));
+        let endpoint = config
+            .api_endpoint
+            .as_ref()
+            .ok_or_else(|| anyhow!("Model {} has no API endpoint", model))?;
+        let options = options.or_defaults(&config.defaults);
+        let payload = llm_provider::request_body(config.provider, model, prompt, true, &options);
+        
+        let mut response = tokio::select! {
+            response = self.post(&config, endpoint).json(&payload).send() => response?,
+            _ = &mut cancel => return Ok(None),
+        };
+        let status = response.status();
+        if !status.is_success() {
+            let body = tokio::select! {
+                body = response.text() => body.unwrap_or_default(),
+                _ = &mut cancel => return Ok(None),
+            };
+            return Err(anyhow!("Model server returned {}: {}", status, body.trim()));
+        }
         
-        // Use the best available model for RAG
-        self.query_llama(&enriched_prompt, "llama3-8b").await
+        let mut text = String::new();
+        let mut usage = None;
+        // Generation speed is timed from the first token, after the prompt is read
+        let mut first_token_at = None;
+        let mut pending: Vec<u8> = Vec::new();
+        loop {
+            let chunk = tokio::select! {
+                chunk = response.chunk() => chunk?,
+                _ = &mut cancel => return Ok(None),
+            };
+            let ended = match chunk {
+                Some(chunk) => {
+                    pending.extend_from_slice(&chunk);
+                    false
+                }
+                // The last line may not end in a newline
+                None => {
+                    pending.push(b'\n');
+                    true
+                }
+            };
+            
+            // Parse complete lines; keep any partial line for the next chunk
+            while let Some(newline) = pending.iter().position(|&b| b == b'\n') {
+                let line: Vec<u8> = pending.drain(..=newline).collect();
+                let parsed = llm_provider::parse_stream_line(config.provider, &String::from_utf8_lossy(&line))?;
+                if let Some(token) = parsed.token {
+                    first_token_at.get_or_insert_with(Instant::now);
+                    text.push_str(&token);
+                    on_token(&token);
+                }
+                usage = parsed.usage.or(usage);
+                if parsed.done {
+                    let generation_time = first_token_at.map_or(Duration::ZERO, |t| t.elapsed());
+                    return Ok(Some(LlamaResponse::new(text, usage, start_time, generation_time, false)));
+                }
+            }
+            if ended {
+                return Err(anyhow!("Stream ended before the model finished"));
+            }
+        }
+    }
+    
+    /// Registers a stream so [`AdvancedAI::cancel_stream`] can stop it.
+    fn start_stream(&self, request_id: &str) -> Result<oneshot::Receiver<()>> {
+        let mut streams = self.streams.lock().map_err(|_| anyhow!("Stream registry poisoned"))?;
+        if streams.contains_key(request_id) {
+            return Err(anyhow!("Request {} is already streaming", request_id));
+        }
+        let (cancel_tx, cancel_rx) = oneshot::channel();
+        streams.insert(request_id.to_string(), cancel_tx);
+        Ok(cancel_rx)
+    }
+    
+    fn finish_stream(&self, request_id: &str) {
+        if let Ok(mut streams) = self.streams.lock() {
+            streams.remove(request_id);
+        }
     }
     
-    pub fn get_available_models(&self) -> Vec<&ModelConfig> {
-        self.models.values().filter(|config| config.enabled).collect()
+    /// Stops a stream. Returns false if it had already finished.
+    pub fn cancel_stream(&self, request_id: &str) -> bool {
+        let sender = match self.streams.lock() {
+            Ok(mut streams) => streams.remove(request_id),
+            Err(_) => None,
+        };
+        sender.map(|tx| tx.send(()).is_ok()).unwrap_or(false)
+    }
+    
+    /// Answers `query` from retrieved excerpts, numbered so the model can
+    /// cite them, followed by any extra documents the caller supplied.
+    pub async fn rag_query(
+        &self,
+        query: &str,
+        hits: &[RagHit],
+        extra_documents: &[String],
+        model: &str,
+        options: &GenerationOptions,
+    ) -> Result<LlamaResponse> {
+        let mut enriched_prompt = String::from("Excerpts from the user's recordings and conversations:\n");
+        
+        for (i, hit) in hits.iter().enumerate() {
+            enriched_prompt.push_str(&format!("[{}] {}: {}\n", i + 1, hit.citation, hit.text));
+        }
+        for (i, doc) in extra_documents.iter().enumerate() {
+            enriched_prompt.push_str(&format!("[{}] Provided document: {}\n", hits.len() + i + 1, doc));
+        }
+        
+        enriched_prompt.push_str(&format!(
+            "\nQuery: {}\n\nAnswer the query using only these excerpts and cite them by number, like [1]. \
+            If they don't contain the answer, say so.",
+            query
+        ));
+        
+        self.query_llama(&enriched_prompt, model, options).await
+    }
+    
+    /// Asks the model's server which models it has installed.
+    pub async fn probe(&self, config: &ModelConfig) -> ModelProbe {
+        let mut probe = ModelProbe {
+            id: config.id.clone(),
+            endpoint: config.api_endpoint.clone(),
+            reachable: false,
+            installed_models: Vec::new(),
+            model_installed: None,
+            error: None,
+        };
+        if config.provider == ProviderKind::Stub {
+            probe.reachable = true;
+            probe.installed_models = vec![config.id.clone()];
+            probe.model_installed = Some(true);
+            return probe;
+        }
+        let Some(url) = config
+            .api_endpoint
+            .as_deref()
+            .and_then(|endpoint| llm_provider::models_url(config.provider, endpoint))
+        else {
+            probe.error = Some("No API endpoint configured".to_string());
+            return probe;
+        };
+        
+        let mut request = self.client.get(&url).timeout(PROBE_TIMEOUT);
+        if let Some(key) = &config.api_key {
+            request = request.bearer_auth(key);
+        }
+        let body: Result<serde_json::Value> = async {
+            let response = request.send().await?;
+            if !response.status().is_success() {
+                return Err(anyhow!("{} returned {}", url, response.status()));
+            }
+            Ok(response.json().await?)
+        }
+        .await;
+        
+        match body {
+            Ok(body) => {
+                probe.reachable = true;
+                probe.installed_models = llm_provider::installed_models(config.provider, &body);
+                // Ollama lists untagged pulls as `name:latest`
+                let latest = format!("{}:latest", config.id);
+                probe.model_installed = Some(probe.installed_models.iter().any(|m| *m == config.id || *m == latest));
+            }
+            Err(e) => probe.error = Some(e.to_string()),
+        }
+        probe
+    }
+    
+    pub fn get_available_models(&self) -> Vec<ModelConfig> {
+        self.registry().models.into_iter().filter(|config| config.enabled).collect()
     }
 }
 
+/// Checks per-request options, which default to the model's settings.
+fn generation_options(options: Option<GenerationOptions>) -> Result<GenerationOptions, String> {
+    let options = options.unwrap_or_default();
+    let errors = options.validate();
+    if !errors.is_empty() {
+        return Err(format!("Invalid generation options: {}", errors.join("; ")));
+    }
+    Ok(options)
+}
+
 #[command]
 pub async fn chat_with_llama(
     prompt: String,
     model: Option<String>,
+    options: Option<GenerationOptions>,
+    state: State<'_, AppState>,
 ) -> Result<LlamaResponse, String> {
-    let ai = AdvancedAI::new();
-    let model_name = model.unwrap_or_else(|| "llama3-8b".to_string());
+    let ai = &state.advanced_ai;
+    let model_name = ai.resolve_model(model, PREFERRED_CHAT_MODEL);
+    let options = generation_options(options)?;
     
-    ai.query_llama(&prompt, &model_name)
+    ai.query_llama(&prompt, &model_name, &options)
         .await
         .map_err(|e| format!("AI error: {}", e))
 }
 
+/// Starts streaming a response and returns its request id straight away.
+/// Text arrives as `llm-token` events and the end as one `llm-stream-end`,
+/// both carrying the request id. Pass `request_id` to choose the id.
+#[command]
+pub async fn stream_llama(
+    prompt: String,
+    model: Option<String>,
+    request_id: Option<String>,
+    options: Option<GenerationOptions>,
+    app_handle: AppHandle,
+) -> Result<String, String> {
+    let options = generation_options(options)?;
+    let model_name = app_handle.state::<AppState>().advanced_ai.resolve_model(model, PREFERRED_CHAT_MODEL);
+    let request_id = request_id.unwrap_or_else(|| {
+        format!("llm-{}", chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default())
+    });
+    let cancel = app_handle
+        .state::<AppState>()
+        .advanced_ai
+        .start_stream(&request_id)
+        .map_err(|e| format!("AI error: {}", e))?;
+    
+    let id = request_id.clone();
+    tauri::async_runtime::spawn(async move {
+        let state = app_handle.state::<AppState>();
+        let ai = &state.advanced_ai;
+        let result = ai
+            .stream_llama(&prompt, &model_name, &options, cancel, |token| {
+                let event = LlmToken { request_id: id.clone(), token: token.to_string() };
+                if let Err(e) = app_handle.emit_all(LLM_TOKEN_EVENT, event) {
+                    eprintln!("Failed to emit LLM token: {}", e);
+                }
+            })
+            .await;
+        ai.finish_stream(&id);
+        
+        let end = match result {
+            Ok(Some(response)) => LlmStreamEnd { request_id: id, response: Some(response), cancelled: false, error: None },
+            Ok(None) => LlmStreamEnd { request_id: id, response: None, cancelled: true, error: None },
+            Err(e) => LlmStreamEnd { request_id: id, response: None, cancelled: false, error: Some(e.to_string()) },
+        };
+        if let Err(e) = app_handle.emit_all(LLM_STREAM_END_EVENT, end) {
+            eprintln!("Failed to emit LLM stream end: {}", e);
+        }
+    });
+    
+    Ok(request_id)
+}
+
+/// Aborts a streaming response. Returns false if it had already finished.
+#[command]
+pub async fn cancel_llm_stream(request_id: String, state: State<'_, AppState>) -> Result<bool, String> {
+    Ok(state.advanced_ai.cancel_stream(&request_id))
+}
+
+/// Finds the `top_k` excerpts of recordings and past conversations closest
+/// to `query` and, unless `generate` is false, has the model answer from them.
 #[command]
 pub async fn rag_search(
     query: String,
-    context_documents: Vec<String>,
-) -> Result<LlamaResponse, String> {
-    let ai = AdvancedAI::new();
-    
-    ai.rag_query(&query, context_documents)
+    top_k: Option<u32>,
+    generate: Option<bool>,
+    model: Option<String>,
+    options: Option<GenerationOptions>,
+    app_handle: AppHandle,
+) -> Result<RagSearchResult, String> {
+    let options = generation_options(options)?;
+    let top_k = top_k.map(|k| k as usize).unwrap_or(rag::DEFAULT_TOP_K);
+    let handle = app_handle.clone();
+    let search_query = query.clone();
+    let hits = tokio::task::spawn_blocking(move || rag::retrieve(&handle.state::<AppState>(), &search_query, top_k))
         .await
-        .map_err(|e| format!("RAG error: {}", e))
+        .map_err(|e| format!("RAG error: {}", e))?
+        .map_err(|e| format!("RAG error: {}", e))?;
+    
+    let answer = if generate.unwrap_or(true) && !hits.is_empty() {
+        let ai = &app_handle.state::<AppState>().advanced_ai;
+        let model_name = ai.resolve_model(model, PREFERRED_CHAT_MODEL);
+        Some(ai.rag_query(&query, &hits, &[], &model_name, &options).await.map_err(|e| format!("RAG error: {}", e))?)
+    } else {
+        None
+    };
+    
+    Ok(RagSearchResult { query, hits, answer })
 }
 
+/// Enabled models, or every configured model with `include_disabled`.
 #[command]
-pub async fn get_ai_models() -> Result<Vec<ModelConfig>, String> {
-    let ai = AdvancedAI::new();
-    Ok(ai.get_available_models().into_iter().cloned().collect())
+pub async fn get_ai_models(include_disabled: Option<bool>, state: State<'_, AppState>) -> Result<Vec<ModelConfig>, String> {
+    if include_disabled.unwrap_or(false) {
+        Ok(state.advanced_ai.registry().models)
+    } else {
+        Ok(state.advanced_ai.get_available_models())
+    }
 }
 
 #[command]
@@ -178,8 +538,12 @@ pub async fn enhanced_dwight_chat(
     user_input: String,
     use_advanced_model: Option<bool>,
     context_documents: Option<Vec<String>>,
+    options: Option<GenerationOptions>,
+    app_handle: AppHandle,
 ) -> Result<LlamaResponse, String> {
-    let ai = AdvancedAI::new();
+    let options = generation_options(options)?;
+    let state = app_handle.state::<AppState>();
+    let ai = &state.advanced_ai;
     
     // Enhanced Dwight prompt with personality and capabilities
     let dwight_prompt = format!(
@@ -195,12 +559,26 @@ pub async fn enhanced_dwight_chat(
         user_input
     );
     
-    if use_advanced_model.unwrap_or(false) && context_documents.is_some() {
+    let mut hits = Vec::new();
+    if use_advanced_model.unwrap_or(false) {
+        // Ground the answer in the user's own recordings when an embedding model is installed
+        let handle = app_handle.clone();
+        let query = user_input.clone();
+        match tokio::task::spawn_blocking(move || rag::retrieve(&handle.state::<AppState>(), &query, rag::DEFAULT_TOP_K)).await {
+            Ok(Ok(found)) => hits = found,
+            Ok(Err(e)) => eprintln!("Retrieval skipped: {}", e),
+            Err(e) => eprintln!("Retrieval failed: {}", e),
+        }
+    }
+    let context_documents = context_documents.unwrap_or_default();
+    let model_name = ai.resolve_model(None, PREFERRED_CHAT_MODEL);
+    
+    if !hits.is_empty() || !context_documents.is_empty() {
         // Use RAG for context-aware responses
-        ai.rag_query(&dwight_prompt, context_documents.unwrap()).await
+        ai.rag_query(&dwight_prompt, &hits, &context_documents, &model_name, &options).await
     } else {
         // Use standard model
-        ai.query_llama(&dwight_prompt, "llama3-8b").await
+        ai.query_llama(&dwight_prompt, &model_name, &options).await
     }
     .map_err(|e| format!("Enhanced chat error: {}", e))
 }
@@ -210,8 +588,9 @@ pub async fn enhanced_dwight_chat(
 pub async fn ai_audio_analysis(
     audio_features: Vec<f32>,
     audio_metadata: serde_json::Value,
+    state: State<'_, AppState>,
 ) -> Result<serde_json::Value, String> {
-    let ai = AdvancedAI::new();
+    let ai = &state.advanced_ai;
     
     // Convert audio features to a descriptive prompt
     let avg_amplitude = audio_features.iter().sum::<f32>() / audio_features.len() as f32;
@@ -230,13 +609,15 @@ pub async fn ai_audio_analysis(
         avg_amplitude, max_amplitude, zero_crossings, audio_features.len(), audio_metadata
     );
     
-    let response = ai.query_llama(&analysis_prompt, "mixtral-8x7b").await
+    let model_name = ai.resolve_model(None, PREFERRED_ANALYSIS_MODEL);
+    let response = ai.query_llama(&analysis_prompt, &model_name, &GenerationOptions::default()).await
         .map_err(|e| format!("Audio analysis error: {}", e))?;
     
     Ok(serde_json::json!({
         "analysis": response.text,
-        "confidence": response.confidence,
+        "simulated": response.simulated,
         "processing_time_ms": response.processing_time_ms,
+        "tokens_per_second": response.tokens_per_second,
         "audio_features": {
             "avg_amplitude": avg_amplitude,
             "peak_amplitude": max_amplitude,
@@ -249,4 +630,345 @@ pub async fn ai_audio_analysis(
             "Enable continuous recording for security applications"
         ]
     }))
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+
+    /// What the stub server sends for one path: the body in pieces, written
+    /// with a pause between them so the client sees separate chunks.
+    #[derive(Clone)]
+    struct Reply {
+        status: u16,
+        parts: Vec<String>,
+        /// Keep the connection open after the last part instead of closing it
+        hold_open: bool,
+    }
+
+    impl Reply {
+        fn json(body: serde_json::Value) -> Self {
+            Reply { status: 200, parts: vec![body.to_string()], hold_open: false }
+        }
+
+        fn stream(parts: &[&str]) -> Self {
+            Reply { status: 200, parts: parts.iter().map(|p| p.to_string()).collect(), hold_open: false }
+        }
+    }
+
+    /// A throwaway HTTP server on a free local port, answering the Ollama and
+    /// OpenAI paths with canned replies and recording what it was sent.
+    struct StubServer {
+        base: String,
+        requests: Arc<Mutex<Vec<(String, serde_json::Value)>>>,
+    }
+
+    impl StubServer {
+        async fn start(routes: Vec<(&'static str, Reply)>) -> Self {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let base = format!("http://{}", listener.local_addr().unwrap());
+            let routes: Arc<HashMap<&'static str, Reply>> = Arc::new(routes.into_iter().collect());
+            let requests = Arc::new(Mutex::new(Vec::new()));
+            let recorded = requests.clone();
+            tokio::spawn(async move {
+                while let Ok((socket, _)) = listener.accept().await {
+                    tokio::spawn(serve(socket, routes.clone(), recorded.clone()));
+                }
+            });
+            StubServer { base, requests }
+        }
+
+        fn url(&self, path: &str) -> String {
+            format!("{}{}", self.base, path)
+        }
+
+        fn requests(&self) -> Vec<(String, serde_json::Value)> {
+            self.requests.lock().unwrap().clone()
+        }
+    }
+
+    async fn serve(
+        mut socket: TcpStream,
+        routes: Arc<HashMap<&'static str, Reply>>,
+        requests: Arc<Mutex<Vec<(String, serde_json::Value)>>>,
+    ) {
+        let mut received = Vec::new();
+        let mut buf = [0u8; 4096];
+        let header_end = loop {
+            if let Some(i) = received.windows(4).position(|w| w == b"\r\n\r\n") {
+                break i + 4;
+            }
+            match socket.read(&mut buf).await {
+                Ok(0) | Err(_) => return,
+                Ok(n) => received.extend_from_slice(&buf[..n]),
+            }
+        };
+        let head = String::from_utf8_lossy(&received[..header_end]).to_string();
+        let path = head.split_whitespace().nth(1).unwrap_or("/").to_string();
+        let content_length: usize = head
+            .lines()
+            .find_map(|line| line.to_ascii_lowercase().strip_prefix("content-length:").map(|v| v.trim().to_string()))
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(0);
+        while received.len() < header_end + content_length {
+            match socket.read(&mut buf).await {
+                Ok(0) | Err(_) => return,
+                Ok(n) => received.extend_from_slice(&buf[..n]),
+            }
+        }
+        let body = serde_json::from_slice(&received[header_end..]).unwrap_or(serde_json::Value::Null);
+        requests.lock().unwrap().push((path.clone(), body));
+
+        let reply = routes.get(path.as_str()).cloned().unwrap_or(Reply {
+            status: 404,
+            parts: vec![r#"{"error":"not found"}"#.to_string()],
+            hold_open: false,
+        });
+        let head = format!(
+            "HTTP/1.1 {} Stub\r\nContent-Type: application/json\r\nConnection: close\r\n\r\n",
+            reply.status
+        );
+        if socket.write_all(head.as_bytes()).await.is_err() {
+            return;
+        }
+        for part in &reply.parts {
+            if socket.write_all(part.as_bytes()).await.is_err() || socket.flush().await.is_err() {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        if reply.hold_open {
+            tokio::time::sleep(Duration::from_secs(30)).await;
+        }
+    }
+
+    fn model(id: &str, provider: ProviderKind, endpoint: String) -> ModelConfig {
+        ModelConfig {
+            id: id.to_string(),
+            name: id.to_string(),
+            model_type: "llama".to_string(),
+            api_endpoint: Some(endpoint),
+            local_path: None,
+            enabled: true,
+            provider,
+            api_key: None,
+            defaults: ModelDefaults::default(),
+        }
+    }
+
+    fn ai_with(models: Vec<ModelConfig>) -> AdvancedAI {
+        AdvancedAI {
+            registry: RwLock::new(ModelRegistry { models }),
+            client: reqwest::Client::new(),
+            streams: Mutex::new(HashMap::new()),
+        }
+    }
+
+    const FORMATS: [(ProviderKind, &str); 3] = [
+        (ProviderKind::OllamaGenerate, "/api/generate"),
+        (ProviderKind::OllamaChat, "/api/chat"),
+        (ProviderKind::OpenAiChat, "/v1/chat/completions"),
+    ];
+
+    #[tokio::test]
+    async fn query_llama_speaks_each_wire_format() {
+        let server = StubServer::start(vec![
+            ("/api/generate", Reply::json(serde_json::json!({
+                "response": "Bears eat beets.", "done": true,
+                "prompt_eval_count": 5, "eval_count": 4, "eval_duration": 500_000_000u64,
+            }))),
+            ("/api/chat", Reply::json(serde_json::json!({
+                "message": { "role": "assistant", "content": "Bears eat beets." }, "done": true,
+                "prompt_eval_count": 5, "eval_count": 4, "eval_duration": 500_000_000u64,
+            }))),
+            ("/v1/chat/completions", Reply::json(serde_json::json!({
+                "choices": [{ "message": { "role": "assistant", "content": "Bears eat beets." } }],
+                "usage": { "prompt_tokens": 5, "completion_tokens": 4 },
+            }))),
+        ])
+        .await;
+
+        for (kind, path) in FORMATS {
+            let ai = ai_with(vec![model("llama3:8b", kind, server.url(path))]);
+            let response = ai.query_llama("Which bear is best?", "llama3:8b", &GenerationOptions::default()).await.unwrap();
+            assert_eq!(response.text, "Bears eat beets.", "{:?}", kind);
+            assert_eq!(response.tokens_used, Some(9), "{:?}", kind);
+            assert!(!response.simulated);
+            let usage = response.usage.unwrap();
+            assert_eq!((usage.prompt_tokens, usage.completion_tokens), (Some(5), Some(4)));
+            if kind != ProviderKind::OpenAiChat {
+                assert_eq!(response.tokens_per_second, Some(8.0));
+            }
+
+            let (sent_to, body) = server.requests().pop().unwrap();
+            assert_eq!(sent_to, path);
+            assert_eq!(body["model"], "llama3:8b");
+            assert_eq!(body["stream"], false);
+            match kind {
+                ProviderKind::OllamaGenerate => assert_eq!(body["prompt"], "Which bear is best?"),
+                _ => assert_eq!(body["messages"][0]["content"], "Which bear is best?"),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn query_llama_marks_the_offline_stand_in_as_simulated() {
+        let ai = ai_with(Vec::new());
+        let response = ai.query_llama("Hello", "llama3:8b", &GenerationOptions::default()).await.unwrap();
+        assert!(response.simulated);
+    }
+
+    #[tokio::test]
+    async fn query_llama_reports_server_errors() {
+        let server = StubServer::start(vec![(
+            "/api/generate",
+            Reply {
+                status: 404,
+                parts: vec![r#"{"error":"model 'llama3:8b' not found, try pulling it first"}"#.to_string()],
+                hold_open: false,
+            },
+        )])
+        .await;
+        let ai = ai_with(vec![model("llama3:8b", ProviderKind::OllamaGenerate, server.url("/api/generate"))]);
+        let error = ai.query_llama("Hello", "llama3:8b", &GenerationOptions::default()).await.unwrap_err();
+        assert!(error.to_string().contains("not found"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn stream_llama_streams_each_wire_format() {
+        let server = StubServer::start(vec![
+            ("/api/generate", Reply::stream(&[
+                "{\"response\":\"Bears \",\"done\":false}\n{\"respon",
+                "se\":\"eat beets.\",\"done\":false}\n",
+                "{\"response\":\"\",\"done\":true,\"prompt_eval_count\":5,\"eval_count\":4}\n",
+            ])),
+            ("/api/chat", Reply::stream(&[
+                "{\"message\":{\"role\":\"assistant\",\"content\":\"Bears \"},\"done\":false}\n",
+                "{\"message\":{\"role\":\"assistant\",\"content\":\"eat beets.\"},\"done\":false}\n",
+                "{\"message\":{\"role\":\"assistant\",\"content\":\"\"},\"done\":true,\"prompt_eval_count\":5,\"eval_count\":4}\n",
+            ])),
+            ("/v1/chat/completions", Reply::stream(&[
+                "data: {\"choices\":[{\"delta\":{\"role\":\"assistant\"}}]}\n\n",
+                ": keep-alive\n\ndata: {\"choices\":[{\"delta\":{\"content\":\"Bears \"}}]}\n\n",
+                "data: {\"choices\":[{\"delta\":{\"content\":\"eat beets.\"}}]}\n\n",
+                "data: {\"choices\":[],\"usage\":{\"prompt_tokens\":5,\"completion_tokens\":4}}\n\n",
+                "data: [DONE]\n\n",
+            ])),
+        ])
+        .await;
+
+        for (kind, path) in FORMATS {
+            let ai = ai_with(vec![model("llama3:8b", kind, server.url(path))]);
+            let (_cancel_tx, cancel_rx) = oneshot::channel();
+            let mut tokens = Vec::new();
+            let response = ai
+                .stream_llama("Which bear is best?", "llama3:8b", &GenerationOptions::default(), cancel_rx, |t| {
+                    tokens.push(t.to_string())
+                })
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(tokens, ["Bears ", "eat beets."], "{:?}", kind);
+            assert_eq!(response.text, "Bears eat beets.");
+            assert_eq!(response.tokens_used, Some(9), "{:?}", kind);
+            assert_eq!(server.requests().pop().unwrap().1["stream"], true);
+        }
+    }
+
+    #[tokio::test]
+    async fn stream_llama_reads_a_final_line_without_a_newline() {
+        let server = StubServer::start(vec![(
+            "/api/generate",
+            Reply::stream(&["{\"response\":\"Beets.\",\"done\":false}\n", "{\"response\":\"\",\"done\":true}"]),
+        )])
+        .await;
+        let ai = ai_with(vec![model("llama3:8b", ProviderKind::OllamaGenerate, server.url("/api/generate"))]);
+        let (_cancel_tx, cancel_rx) = oneshot::channel();
+        let response = ai
+            .stream_llama("Favourite vegetable?", "llama3:8b", &GenerationOptions::default(), cancel_rx, |_| {})
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(response.text, "Beets.");
+    }
+
+    #[tokio::test]
+    async fn stream_llama_reports_server_errors() {
+        let server = StubServer::start(vec![(
+            "/api/generate",
+            Reply {
+                status: 404,
+                parts: vec![r#"{"error":"model 'llama3:8b' not found, try pulling it first"}"#.to_string()],
+                hold_open: false,
+            },
+        )])
+        .await;
+        let ai = ai_with(vec![model("llama3:8b", ProviderKind::OllamaGenerate, server.url("/api/generate"))]);
+        let (_cancel_tx, cancel_rx) = oneshot::channel();
+        let error = ai
+            .stream_llama("Hello", "llama3:8b", &GenerationOptions::default(), cancel_rx, |_| {})
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("404"), "{}", error);
+        assert!(error.to_string().contains("not found, try pulling it first"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn stream_llama_fails_if_the_stream_ends_early() {
+        let server = StubServer::start(vec![(
+            "/api/generate",
+            Reply::stream(&["{\"response\":\"Bears \",\"done\":false}\n"]),
+        )])
+        .await;
+        let ai = ai_with(vec![model("llama3:8b", ProviderKind::OllamaGenerate, server.url("/api/generate"))]);
+        let (_cancel_tx, cancel_rx) = oneshot::channel();
+        let result = ai.stream_llama("Hello", "llama3:8b", &GenerationOptions::default(), cancel_rx, |_| {}).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn stream_llama_stops_when_cancelled() {
+        let server = StubServer::start(vec![(
+            "/api/generate",
+            Reply { hold_open: true, ..Reply::stream(&["{\"response\":\"Bears \",\"done\":false}\n"]) },
+        )])
+        .await;
+        let ai = ai_with(vec![model("llama3:8b", ProviderKind::OllamaGenerate, server.url("/api/generate"))]);
+        let (cancel_tx, cancel_rx) = oneshot::channel();
+        let mut cancel_tx = Some(cancel_tx);
+        let mut tokens = Vec::new();
+        let options = GenerationOptions::default();
+        let streamed = ai.stream_llama("Hello", "llama3:8b", &options, cancel_rx, |t| {
+            tokens.push(t.to_string());
+            if let Some(tx) = cancel_tx.take() {
+                let _ = tx.send(());
+            }
+        });
+        let result = tokio::time::timeout(Duration::from_secs(5), streamed).await.expect("cancel was ignored");
+        assert!(result.unwrap().is_none());
+        assert_eq!(tokens, ["Bears "]);
+    }
+
+    #[tokio::test]
+    async fn probe_lists_installed_ollama_models() {
+        let server = StubServer::start(vec![(
+            "/api/tags",
+            Reply::json(serde_json::json!({ "models": [{ "name": "llama3:8b" }, { "name": "mistral:latest" }] })),
+        )])
+        .await;
+        let ai = ai_with(Vec::new());
+
+        let probe = ai.probe(&model("llama3:8b", ProviderKind::OllamaGenerate, server.url("/api/generate"))).await;
+        assert!(probe.reachable);
+        assert_eq!(probe.installed_models, ["llama3:8b", "mistral:latest"]);
+        assert_eq!(probe.model_installed, Some(true));
+
+        let probe = ai.probe(&model("mistral", ProviderKind::OllamaChat, server.url("/api/chat"))).await;
+        assert_eq!(probe.model_installed, Some(true));
+        let probe = ai.probe(&model("mixtral:8x7b", ProviderKind::OllamaChat, server.url("/api/chat"))).await;
+        assert_eq!(probe.model_installed, Some(false));
+    }
+}
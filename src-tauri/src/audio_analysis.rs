@@ -0,0 +1,283 @@
+use rustfft::{num_complex::Complex, FftPlanner};
+use crate::vad;
+use crate::whisper::{AudioAnalysis, AudioQuality, EmotionalTone, NonSpeechEvent, SpeechSegment};
+
+pub const FRAME_SECONDS: f64 = 0.025;
+pub const HOP_SECONDS: f64 = 0.010;
+const SILENCE_DB: f32 = -100.0;
+const MIN_EVENT_SECONDS: f64 = 0.05;
+
+/// Short-time features for one analysis frame.
+#[derive(Debug, Clone)]
+pub struct FrameFeatures {
+    pub start: f64,
+    pub db: f32,
+    pub zero_crossing_rate: f32,
+    pub spectral_flatness: f32,
+    /// Fraction of spectral energy in the 300–3400 Hz speech band
+    pub speech_band_ratio: f32,
+}
+
+fn to_db(value: f32) -> f32 {
+    if value <= 0.0 {
+        SILENCE_DB
+    } else {
+        (20.0 * value.log10()).max(SILENCE_DB)
+    }
+}
+
+fn percentile(values: &[f32], pct: f32) -> f32 {
+    if values.is_empty() {
+        return SILENCE_DB;
+    }
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let idx = ((sorted.len() - 1) as f32 * pct).round() as usize;
+    sorted[idx]
+}
+
+/// Computes overlapping 25 ms frame features every 10 ms.
+pub fn frame_features(samples: &[f32], sample_rate: u32) -> Vec<FrameFeatures> {
+    let frame_len = ((sample_rate as f64 * FRAME_SECONDS) as usize).max(1);
+    let hop = ((sample_rate as f64 * HOP_SECONDS) as usize).max(1);
+    if samples.len() < frame_len {
+        return Vec::new();
+    }
+
+    let fft_len = frame_len.next_power_of_two();
+    let fft = FftPlanner::<f32>::new().plan_fft_forward(fft_len);
+    let window: Vec<f32> = (0..frame_len)
+        .map(|i| 0.5 - 0.5 * (2.0 * std::f32::consts::PI * i as f32 / frame_len as f32).cos())
+        .collect();
+    let bin_hz = sample_rate as f32 / fft_len as f32;
+
+    let mut buffer = vec![Complex::new(0.0f32, 0.0); fft_len];
+    let mut frames = Vec::with_capacity(samples.len() / hop);
+
+    let mut start = 0;
+    while start + frame_len <= samples.len() {
+        let frame = &samples[start..start + frame_len];
+
+        let rms = (frame.iter().map(|s| s * s).sum::<f32>() / frame_len as f32).sqrt();
+        let crossings = frame.windows(2).filter(|w| (w[0] >= 0.0) != (w[1] >= 0.0)).count();
+
+        for (i, slot) in buffer.iter_mut().enumerate() {
+            *slot = Complex::new(if i < frame_len { frame[i] * window[i] } else { 0.0 }, 0.0);
+        }
+        fft.process(&mut buffer);
+
+        let mut power_sum = 0.0f64;
+        let mut log_sum = 0.0f64;
+        let mut speech_band = 0.0f64;
+        let bins = fft_len / 2;
+        for (k, bin) in buffer.iter().enumerate().take(bins).skip(1) {
+            let power = bin.norm_sqr() as f64 + 1e-12;
+            let freq = k as f32 * bin_hz;
+            power_sum += power;
+            log_sum += power.ln();
+            if (300.0..=3400.0).contains(&freq) {
+                speech_band += power;
+            }
+        }
+        let n = (bins - 1).max(1) as f64;
+        let spectral_flatness = ((log_sum / n).exp() / (power_sum / n)) as f32;
+
+        frames.push(FrameFeatures {
+            start: start as f64 / sample_rate as f64,
+            db: to_db(rms),
+            zero_crossing_rate: crossings as f32 / frame_len as f32,
+            spectral_flatness,
+            speech_band_ratio: (speech_band / power_sum) as f32,
+        });
+
+        start += hop;
+    }
+
+    frames
+}
+
+/// Estimates the noise floor as the 10th percentile of frame levels.
+pub fn noise_floor_db(frames: &[FrameFeatures]) -> f32 {
+    let levels: Vec<f32> = frames.iter().map(|f| f.db).collect();
+    percentile(&levels, 0.10)
+}
+
+/// Labels a loud non-speech region from its frame features.
+pub fn classify_sound(frames: &[FrameFeatures], noise_floor_db: f32) -> (String, f32) {
+    let duration = frames.len() as f64 * HOP_SECONDS;
+    let mean_flatness = frames.iter().map(|f| f.spectral_flatness).sum::<f32>() / frames.len().max(1) as f32;
+    let max_db = frames.iter().map(|f| f.db).fold(SILENCE_DB, f32::max);
+    let first_db = frames.first().map(|f| f.db).unwrap_or(SILENCE_DB);
+    let margin = ((max_db - noise_floor_db) / 30.0).clamp(0.0, 1.0);
+
+    // Sharp onset that dies away quickly: knocks, slams, bangs
+    if duration <= 0.3 && max_db - first_db < 6.0 && max_db - noise_floor_db > 15.0 {
+        return ("impulse".to_string(), 0.5 + 0.5 * margin);
+    }
+    if mean_flatness < 0.1 {
+        return ("tonal".to_string(), 0.5 + 0.5 * (1.0 - mean_flatness * 10.0) * margin);
+    }
+    if mean_flatness > 0.4 {
+        return ("noise".to_string(), 0.4 + 0.5 * margin);
+    }
+    ("sound".to_string(), 0.3 + 0.4 * margin)
+}
+
+/// Groups per-frame flags into `(start_frame, end_frame)` runs, bridging short
+/// gaps and dropping runs shorter than `min_seconds`.
+pub fn group_runs(flags: &[bool], min_seconds: f64, max_gap_seconds: f64) -> Vec<(usize, usize)> {
+    let max_gap = (max_gap_seconds / HOP_SECONDS).round() as usize;
+    let min_len = (min_seconds / HOP_SECONDS).round() as usize;
+
+    let mut runs: Vec<(usize, usize)> = Vec::new();
+    for (i, &flag) in flags.iter().enumerate() {
+        if !flag {
+            continue;
+        }
+        match runs.last_mut() {
+            Some((_, end)) if i - *end <= max_gap + 1 => *end = i,
+            _ => runs.push((i, i)),
+        }
+    }
+
+    runs.into_iter()
+        .filter(|(start, end)| end + 1 - start >= min_len.max(1))
+        .collect()
+}
+
+fn frame_span(frames: &[FrameFeatures], start: usize, end: usize) -> (f64, f64) {
+    (frames[start].start, frames[end].start + FRAME_SECONDS)
+}
+
+fn speech_mask(len: usize, runs: &[(usize, usize)]) -> Vec<bool> {
+    let mut mask = vec![false; len];
+    for &(start, end) in runs {
+        mask[start..=end].iter_mut().for_each(|f| *f = true);
+    }
+    mask
+}
+
+/// Flags frames well above the floor that aren't speech, and groups them into
+/// classified events.
+fn find_events(frames: &[FrameFeatures], noise_db: f32, in_speech: &[bool]) -> (Vec<bool>, Vec<NonSpeechEvent>) {
+    let event_flags: Vec<bool> = frames
+        .iter()
+        .enumerate()
+        .map(|(i, f)| !in_speech[i] && f.db > noise_db + 10.0 && f.db > -60.0)
+        .collect();
+    let events = group_runs(&event_flags, MIN_EVENT_SECONDS, 0.05)
+        .into_iter()
+        .map(|(start, end)| {
+            let (event_type, confidence) = classify_sound(&frames[start..=end], noise_db);
+            let (start_s, end_s) = frame_span(frames, start, end);
+            NonSpeechEvent {
+                start: start_s,
+                end: end_s,
+                event_type,
+                confidence,
+            }
+        })
+        .collect();
+    (event_flags, events)
+}
+
+/// The non-speech events [`analyze_samples`] would report, for frames that
+/// have already been computed. Event times come from the frames' `start`.
+pub fn non_speech_events(frames: &[FrameFeatures]) -> Vec<NonSpeechEvent> {
+    let noise_db = noise_floor_db(frames);
+    let (_, runs) = vad::speech_runs(frames, noise_db);
+    find_events(frames, noise_db, &speech_mask(frames.len(), &runs)).1
+}
+
+/// Measures a decoded recording: duration, quality, and speech vs non-speech regions.
+pub fn analyze_samples(samples: &[f32], sample_rate: u32, channels: u16) -> AudioAnalysis {
+    let duration_seconds = samples.len() as f64 / sample_rate.max(1) as f64;
+    let frames = frame_features(samples, sample_rate);
+    let noise_db = noise_floor_db(&frames);
+
+    let (speech_flags, speech_runs) = vad::speech_runs(&frames, noise_db);
+
+    let in_speech = speech_mask(frames.len(), &speech_runs);
+    let speech_segments: Vec<SpeechSegment> = speech_runs
+        .iter()
+        .map(|&(start, end)| {
+            let voiced = speech_flags[start..=end].iter().filter(|&&f| f).count();
+            let (start_s, end_s) = frame_span(&frames, start, end);
+            SpeechSegment {
+                start: start_s,
+                end: end_s,
+                confidence: voiced as f32 / (end + 1 - start) as f32,
+                speaker: "unknown".to_string(),
+                language: "und".to_string(),
+            }
+        })
+        .collect();
+
+    let (event_flags, non_speech_events) = find_events(&frames, noise_db, &in_speech);
+
+    // Signal level from active frames, falling back to the loud end of the distribution
+    let active_levels: Vec<f32> = frames
+        .iter()
+        .zip(in_speech.iter().zip(event_flags.iter()))
+        .filter(|(_, (&speech, &event))| speech || event)
+        .map(|(f, _)| f.db)
+        .collect();
+    let signal_db = if active_levels.is_empty() {
+        percentile(&frames.iter().map(|f| f.db).collect::<Vec<_>>(), 0.90)
+    } else {
+        percentile(&active_levels, 0.50)
+    };
+    let snr_db = (signal_db - noise_db).max(0.0);
+
+    let clipped = samples.iter().filter(|s| s.abs() >= 0.999).count();
+    let clip_ratio = clipped as f32 / samples.len().max(1) as f32;
+    let clarity_score = ((snr_db / 30.0).clamp(0.0, 1.0) * (1.0 - (clip_ratio * 100.0).min(1.0))).clamp(0.0, 1.0);
+    let background_noise_level = if frames.is_empty() { 0.0 } else { 10f32.powf(noise_db / 20.0) };
+
+    let speech_seconds: f64 = speech_segments.iter().map(|s| s.end - s.start).sum();
+    let emotional_tone = estimate_tone(&frames, &in_speech, speech_seconds, duration_seconds);
+
+    AudioAnalysis {
+        duration_seconds,
+        sample_rate,
+        channels,
+        speech_segments,
+        non_speech_events,
+        audio_quality: AudioQuality {
+            snr_db,
+            clarity_score,
+            background_noise_level,
+        },
+        keywords_detected: Vec::new(),
+        emotional_tone,
+    }
+}
+
+/// Acoustic proxies only: loudness and its variability within speech, and how
+/// much of the recording is speech. Confidence grows with the amount of speech.
+fn estimate_tone(frames: &[FrameFeatures], in_speech: &[bool], speech_seconds: f64, duration: f64) -> EmotionalTone {
+    let levels: Vec<f32> = frames
+        .iter()
+        .zip(in_speech)
+        .filter(|(_, &speech)| speech)
+        .map(|(f, _)| f.db)
+        .collect();
+
+    if levels.is_empty() || duration <= 0.0 {
+        return EmotionalTone {
+            urgency: 0.0,
+            stress: 0.0,
+            confidence: 0.0,
+        };
+    }
+
+    let mean = levels.iter().sum::<f32>() / levels.len() as f32;
+    let variance = levels.iter().map(|l| (l - mean).powi(2)).sum::<f32>() / levels.len() as f32;
+    let speech_density = (speech_seconds / duration) as f32;
+
+    EmotionalTone {
+        urgency: (((mean + 40.0) / 30.0).clamp(0.0, 1.0) * 0.6 + speech_density.clamp(0.0, 1.0) * 0.4).clamp(0.0, 1.0),
+        stress: (variance.sqrt() / 12.0).clamp(0.0, 1.0),
+        confidence: ((speech_seconds / 30.0) as f32).clamp(0.0, 1.0) * 0.5,
+    }
+}
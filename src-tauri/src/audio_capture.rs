@@ -0,0 +1,554 @@
+use tauri::{command, AppHandle, Manager, State};
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{FromSample, SampleFormat, SizedSample};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+use anyhow::{anyhow, Result};
+use crate::database::{self, AudioRecord};
+use crate::integrity;
+use crate::state::AppState;
+use crate::vad::{StreamingVad, VadEvent};
+use crate::vault;
+
+const DEFAULT_BUFFER_MINUTES: u32 = 5;
+const MAX_BUFFER_MINUTES: u32 = 60;
+/// How often the capture thread runs VAD over newly captured audio.
+const VAD_POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// Audio awaiting VAD is capped so a stalled thread can't grow it unbounded.
+const MAX_PENDING_SECONDS: usize = 10;
+/// Tauri event carrying each [`VadEvent`] during capture.
+pub const VAD_EVENT: &str = "vad-event";
+
+/// Fixed-size circular buffer of mono samples. Once full, the oldest samples
+/// are overwritten so memory use stays bounded no matter how long capture runs.
+pub struct RingBuffer {
+    data: Vec<f32>,
+    write_pos: usize,
+    filled: usize,
+    /// Total samples ever pushed, used as an absolute stream position
+    written: u64,
+}
+
+impl RingBuffer {
+    pub fn with_capacity(capacity: usize) -> Self {
+        RingBuffer {
+            data: vec![0.0; capacity.max(1)],
+            write_pos: 0,
+            filled: 0,
+            written: 0,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn len(&self) -> usize {
+        self.filled
+    }
+
+    pub fn push_slice(&mut self, samples: &[f32]) {
+        let capacity = self.data.len();
+        self.written += samples.len() as u64;
+
+        // Only the tail of an oversized write can survive anyway
+        let samples = if samples.len() > capacity {
+            &samples[samples.len() - capacity..]
+        } else {
+            samples
+        };
+
+        let first = (capacity - self.write_pos).min(samples.len());
+        self.data[self.write_pos..self.write_pos + first].copy_from_slice(&samples[..first]);
+        let rest = samples.len() - first;
+        self.data[..rest].copy_from_slice(&samples[first..]);
+
+        self.write_pos = (self.write_pos + samples.len()) % capacity;
+        self.filled = (self.filled + samples.len()).min(capacity);
+    }
+
+    /// Copies out the most recent `count` samples in chronological order.
+    pub fn latest(&self, count: usize) -> Vec<f32> {
+        let count = count.min(self.filled);
+        let capacity = self.data.len();
+        let start = (self.write_pos + capacity - count) % capacity;
+
+        let mut out = Vec::with_capacity(count);
+        if start + count <= capacity {
+            out.extend_from_slice(&self.data[start..start + count]);
+        } else {
+            out.extend_from_slice(&self.data[start..]);
+            out.extend_from_slice(&self.data[..count - (capacity - start)]);
+        }
+        out
+    }
+
+    /// Samples from absolute position `from` up to now, along with the
+    /// position actually returned from (older audio has been overwritten).
+    pub fn since(&self, from: u64) -> (u64, Vec<f32>) {
+        let oldest = self.written - self.filled as u64;
+        let start = from.clamp(oldest, self.written);
+        (start, self.latest((self.written - start) as usize))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CaptureStatus {
+    pub is_capturing: bool,
+    pub device_name: Option<String>,
+    pub sample_rate: u32,
+    pub buffer_capacity_seconds: f64,
+    pub buffered_seconds: f64,
+    pub started_at: Option<String>,
+    pub speech_active: bool,
+}
+
+/// A span of the capture stream. `start` is an absolute sample position that
+/// restarts from zero with each capture session, identified by `started_at`.
+pub struct CapturedAudio {
+    pub samples: Vec<f32>,
+    pub start: u64,
+    pub sample_rate: u32,
+    pub started_at: String,
+    pub device_name: String,
+}
+
+impl CapturedAudio {
+    pub fn end(&self) -> u64 {
+        self.start + self.samples.len() as u64
+    }
+}
+
+struct CaptureShared {
+    buffer: RingBuffer,
+    sample_rate: u32,
+    /// Samples captured since the VAD last ran
+    pending: Vec<f32>,
+    speech_active: bool,
+}
+
+struct CaptureSession {
+    shared: Arc<Mutex<CaptureShared>>,
+    device_name: String,
+    started_at: String,
+    stop_tx: mpsc::Sender<()>,
+    thread: JoinHandle<()>,
+}
+
+impl CaptureSession {
+    fn stop(self) {
+        // The stream lives on its own thread because cpal streams are not Send
+        let _ = self.stop_tx.send(());
+        let _ = self.thread.join();
+    }
+}
+
+/// Always-on microphone capture that keeps the last few minutes of audio in
+/// memory, like a DVR. Managed as Tauri state so commands share one session.
+#[derive(Default)]
+pub struct AudioCapture {
+    session: Mutex<Option<CaptureSession>>,
+}
+
+impl AudioCapture {
+    /// Starts capturing. `on_vad` is called from the capture thread as speech
+    /// starts and stops.
+    pub fn start<F>(&self, buffer_minutes: u32, on_vad: F) -> Result<CaptureStatus>
+    where
+        F: Fn(VadEvent) + Send + 'static,
+    {
+        let mut session = self.session.lock().map_err(|_| anyhow!("Capture state poisoned"))?;
+        if session.is_some() {
+            return Err(anyhow!("Audio capture is already running"));
+        }
+
+        let buffer_minutes = buffer_minutes.clamp(1, MAX_BUFFER_MINUTES);
+        let (stop_tx, stop_rx) = mpsc::channel::<()>();
+        let (ready_tx, ready_rx) = mpsc::channel::<Result<(String, Arc<Mutex<CaptureShared>>)>>();
+
+        let thread = std::thread::Builder::new()
+            .name("dwight-audio-capture".to_string())
+            .spawn(move || {
+                let (stream, shared) = match open_input_stream(buffer_minutes) {
+                    Ok((stream, device_name, shared)) => {
+                        let _ = ready_tx.send(Ok((device_name, shared.clone())));
+                        (stream, shared)
+                    }
+                    Err(e) => {
+                        let _ = ready_tx.send(Err(e));
+                        return;
+                    }
+                };
+
+                let sample_rate = shared.lock().map(|s| s.sample_rate).unwrap_or(0);
+                let mut vad = StreamingVad::new(sample_rate);
+                let handle = |event: VadEvent| {
+                    if let Ok(mut shared) = shared.lock() {
+                        shared.speech_active = matches!(event, VadEvent::SpeechStart { .. });
+                    }
+                    on_vad(event);
+                };
+
+                // Run VAD until asked to stop (or the owning session is dropped)
+                while let Err(RecvTimeoutError::Timeout) = stop_rx.recv_timeout(VAD_POLL_INTERVAL) {
+                    let pending = match shared.lock() {
+                        Ok(mut shared) => std::mem::take(&mut shared.pending),
+                        Err(_) => break,
+                    };
+                    for event in vad.push(&pending) {
+                        handle(event);
+                    }
+                }
+                if let Some(event) = vad.finish() {
+                    handle(event);
+                }
+                drop(stream);
+            })?;
+
+        let (device_name, shared) = match ready_rx.recv() {
+            Ok(Ok(opened)) => opened,
+            Ok(Err(e)) => {
+                let _ = thread.join();
+                return Err(e);
+            }
+            Err(_) => {
+                let _ = thread.join();
+                return Err(anyhow!("Audio capture thread exited unexpectedly"));
+            }
+        };
+
+        *session = Some(CaptureSession {
+            shared,
+            device_name,
+            started_at: chrono::Utc::now().to_rfc3339(),
+            stop_tx,
+            thread,
+        });
+
+        Ok(Self::status_of(session.as_ref()))
+    }
+
+    pub fn stop(&self) -> Result<CaptureStatus> {
+        let session = self.session.lock().map_err(|_| anyhow!("Capture state poisoned"))?.take();
+        if let Some(session) = session {
+            session.stop();
+        }
+        Ok(Self::status_of(None))
+    }
+
+    pub fn status(&self) -> Result<CaptureStatus> {
+        let session = self.session.lock().map_err(|_| anyhow!("Capture state poisoned"))?;
+        Ok(Self::status_of(session.as_ref()))
+    }
+
+    /// Returns the last `seconds` of buffered audio and its sample rate.
+    pub fn snapshot(&self, seconds: f64) -> Result<(Vec<f32>, u32)> {
+        let session = self.session.lock().map_err(|_| anyhow!("Capture state poisoned"))?;
+        let session = session.as_ref().ok_or_else(|| anyhow!("Audio capture is not running"))?;
+        let shared = session.shared.lock().map_err(|_| anyhow!("Capture buffer poisoned"))?;
+
+        let count = (seconds.max(0.0) * shared.sample_rate as f64).round() as usize;
+        Ok((shared.buffer.latest(count), shared.sample_rate))
+    }
+
+    /// Audio captured since absolute position `from`; see [`RingBuffer::since`].
+    pub fn read_since(&self, from: u64) -> Result<CapturedAudio> {
+        let session = self.session.lock().map_err(|_| anyhow!("Capture state poisoned"))?;
+        let session = session.as_ref().ok_or_else(|| anyhow!("Audio capture is not running"))?;
+        let shared = session.shared.lock().map_err(|_| anyhow!("Capture buffer poisoned"))?;
+
+        let (start, samples) = shared.buffer.since(from);
+        Ok(CapturedAudio {
+            samples,
+            start,
+            sample_rate: shared.sample_rate,
+            started_at: session.started_at.clone(),
+            device_name: session.device_name.clone(),
+        })
+    }
+
+    fn status_of(session: Option<&CaptureSession>) -> CaptureStatus {
+        match session {
+            Some(session) => {
+                let (sample_rate, capacity, buffered, speech_active) = match session.shared.lock() {
+                    Ok(shared) => (
+                        shared.sample_rate,
+                        shared.buffer.capacity(),
+                        shared.buffer.len(),
+                        shared.speech_active,
+                    ),
+                    Err(_) => (0, 0, 0, false),
+                };
+                let rate = sample_rate.max(1) as f64;
+                CaptureStatus {
+                    is_capturing: true,
+                    device_name: Some(session.device_name.clone()),
+                    sample_rate,
+                    buffer_capacity_seconds: capacity as f64 / rate,
+                    buffered_seconds: buffered as f64 / rate,
+                    started_at: Some(session.started_at.clone()),
+                    speech_active,
+                }
+            }
+            None => CaptureStatus {
+                is_capturing: false,
+                device_name: None,
+                sample_rate: 0,
+                buffer_capacity_seconds: 0.0,
+                buffered_seconds: 0.0,
+                started_at: None,
+                speech_active: false,
+            },
+        }
+    }
+}
+
+fn open_input_stream(buffer_minutes: u32) -> Result<(cpal::Stream, String, Arc<Mutex<CaptureShared>>)> {
+    let host = cpal::default_host();
+    let device = host
+        .default_input_device()
+        .ok_or_else(|| anyhow!("No input device available"))?;
+    let device_name = device.name().unwrap_or_else(|_| "Unknown input device".to_string());
+
+    let supported = device.default_input_config()?;
+    let sample_format = supported.sample_format();
+    let config: cpal::StreamConfig = supported.config();
+    let sample_rate = config.sample_rate.0;
+    let channels = config.channels as usize;
+
+    let capacity = sample_rate as usize * 60 * buffer_minutes as usize;
+    let shared = Arc::new(Mutex::new(CaptureShared {
+        buffer: RingBuffer::with_capacity(capacity),
+        sample_rate,
+        pending: Vec::new(),
+        speech_active: false,
+    }));
+
+    let stream = match sample_format {
+        SampleFormat::I8 => build_stream::<i8>(&device, &config, channels, shared.clone())?,
+        SampleFormat::I16 => build_stream::<i16>(&device, &config, channels, shared.clone())?,
+        SampleFormat::I32 => build_stream::<i32>(&device, &config, channels, shared.clone())?,
+        SampleFormat::U8 => build_stream::<u8>(&device, &config, channels, shared.clone())?,
+        SampleFormat::U16 => build_stream::<u16>(&device, &config, channels, shared.clone())?,
+        SampleFormat::F32 => build_stream::<f32>(&device, &config, channels, shared.clone())?,
+        SampleFormat::F64 => build_stream::<f64>(&device, &config, channels, shared.clone())?,
+        other => return Err(anyhow!("Unsupported input sample format: {}", other)),
+    };
+    stream.play()?;
+
+    Ok((stream, device_name, shared))
+}
+
+fn build_stream<T>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    channels: usize,
+    shared: Arc<Mutex<CaptureShared>>,
+) -> Result<cpal::Stream>
+where
+    T: SizedSample,
+    f32: FromSample<T>,
+{
+    let mut mono = Vec::new();
+    let max_pending = config.sample_rate.0 as usize * MAX_PENDING_SECONDS;
+
+    let stream = device.build_input_stream(
+        config,
+        move |data: &[T], _: &cpal::InputCallbackInfo| {
+            // Downmix interleaved frames to mono before buffering
+            mono.clear();
+            mono.extend(data.chunks(channels.max(1)).map(|frame| {
+                frame.iter().map(|s| s.to_sample::<f32>()).sum::<f32>() / frame.len() as f32
+            }));
+
+            if let Ok(mut shared) = shared.lock() {
+                shared.buffer.push_slice(&mono);
+                if shared.pending.len() + mono.len() <= max_pending {
+                    shared.pending.extend_from_slice(&mono);
+                }
+            }
+        },
+        |err| eprintln!("Audio capture stream error: {}", err),
+        None,
+    )?;
+
+    Ok(stream)
+}
+
+fn wav_spec(sample_rate: u32) -> hound::WavSpec {
+    hound::WavSpec {
+        channels: 1,
+        sample_rate,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    }
+}
+
+fn write_samples<W: std::io::Write + std::io::Seek>(mut writer: hound::WavWriter<W>, samples: &[f32]) -> Result<()> {
+    for &sample in samples {
+        writer.write_sample((sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16)?;
+    }
+    writer.finalize()?;
+    Ok(())
+}
+
+/// Writes mono f32 samples as a plain 16-bit PCM WAV file. Only for
+/// short-lived temp files handed to external tools; recordings that are kept
+/// go through [`save_wav`].
+pub fn write_wav(path: &Path, samples: &[f32], sample_rate: u32) -> Result<()> {
+    write_samples(hound::WavWriter::create(path, wav_spec(sample_rate))?, samples)
+}
+
+/// Writes a recording as an encrypted 16-bit PCM WAV file and returns the
+/// SHA-256 of the WAV data, for the record's chain of custody.
+pub fn save_wav(path: &Path, samples: &[f32], sample_rate: u32) -> Result<String> {
+    let mut bytes = std::io::Cursor::new(Vec::new());
+    write_samples(hound::WavWriter::new(&mut bytes, wav_spec(sample_rate))?, samples)?;
+    vault::write_file(path, bytes.get_ref())?;
+    Ok(integrity::sha256_hex(bytes.get_ref()))
+}
+
+pub fn recordings_dir(config: &tauri::Config) -> PathBuf {
+    database::app_data_path(config).join("recordings")
+}
+
+#[command]
+pub async fn start_audio_capture(
+    buffer_minutes: Option<u32>,
+    capture: State<'_, AudioCapture>,
+    app_handle: AppHandle,
+) -> Result<CaptureStatus, String> {
+    let on_vad = move |event: VadEvent| {
+        if let Err(e) = app_handle.emit_all(VAD_EVENT, event) {
+            eprintln!("Failed to emit VAD event: {}", e);
+        }
+    };
+    capture.start(buffer_minutes.unwrap_or(DEFAULT_BUFFER_MINUTES), on_vad)
+        .map_err(|e| format!("Capture error: {}", e))
+}
+
+#[command]
+pub async fn stop_audio_capture(capture: State<'_, AudioCapture>) -> Result<CaptureStatus, String> {
+    capture.stop().map_err(|e| format!("Capture error: {}", e))
+}
+
+#[command]
+pub async fn get_capture_status(capture: State<'_, AudioCapture>) -> Result<CaptureStatus, String> {
+    capture.status().map_err(|e| format!("Capture error: {}", e))
+}
+
+/// Saves the last `seconds` of buffered audio to a WAV file and records it in the database.
+#[command]
+pub async fn save_audio_clip(
+    seconds: f64,
+    title: Option<String>,
+    capture: State<'_, AudioCapture>,
+    app_handle: tauri::AppHandle,
+) -> Result<AudioRecord, String> {
+    if seconds <= 0.0 {
+        return Err("Clip length must be greater than zero".to_string());
+    }
+
+    let (samples, sample_rate) = capture.snapshot(seconds).map_err(|e| format!("Capture error: {}", e))?;
+    if samples.is_empty() {
+        return Err("No audio has been buffered yet".to_string());
+    }
+    let device_name = capture.status().ok().and_then(|status| status.device_name);
+
+    let config = app_handle.config();
+    let dir = recordings_dir(&config);
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create recordings directory: {}", e))?;
+
+    let now = chrono::Local::now();
+    let file_path = dir.join(format!("clip_{}.wav", now.format("%Y%m%d_%H%M%S_%3f")));
+    let duration = samples.len() as f64 / sample_rate as f64;
+
+    let wav_path = file_path.clone();
+    let sha256 = tokio::task::spawn_blocking(move || save_wav(&wav_path, &samples, sample_rate))
+        .await
+        .map_err(|e| format!("Capture error: {}", e))?
+        .map_err(|e| format!("Failed to write clip: {}", e))?;
+
+    let mut record = AudioRecord {
+        id: None,
+        title: title.unwrap_or_else(|| format!("Clip {}", now.format("%Y-%m-%d %H:%M:%S"))),
+        file_path: file_path.to_string_lossy().to_string(),
+        transcript: None,
+        duration,
+        created_at: String::new(),
+        triggers: None,
+        tags: Vec::new(),
+        starred: false,
+        sha256: Some(sha256),
+        capture_device: device_name,
+        captured_at: Some((now - chrono::Duration::milliseconds((duration * 1000.0) as i64)).to_rfc3339()),
+    };
+
+    let db = app_handle.state::<AppState>().db()?;
+    let id = db.save_audio_record(&record).map_err(|e| format!("Database error: {}", e))?;
+    record.id = Some(id as i32);
+
+    Ok(record)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Samples numbered from `from`, so positions can be read back from values.
+    fn numbered(from: usize, count: usize) -> Vec<f32> {
+        (from..from + count).map(|i| i as f32).collect()
+    }
+
+    #[test]
+    fn ring_buffer_overwrites_the_oldest_samples_once_full() {
+        let mut buffer = RingBuffer::with_capacity(8);
+        buffer.push_slice(&numbered(0, 5));
+        assert_eq!(buffer.len(), 5);
+        assert_eq!(buffer.latest(5), numbered(0, 5));
+
+        // Wraps past the end
+        buffer.push_slice(&numbered(5, 6));
+        assert_eq!(buffer.len(), 8);
+        assert_eq!(buffer.latest(8), numbered(3, 8));
+
+        // A write bigger than the buffer keeps only its tail
+        buffer.push_slice(&numbered(11, 20));
+        assert_eq!(buffer.len(), 8);
+        assert_eq!(buffer.latest(8), numbered(23, 8));
+        assert_eq!(buffer.since(0), (23, numbered(23, 8)));
+    }
+
+    #[test]
+    fn ring_buffer_latest_returns_at_most_what_it_holds() {
+        let mut buffer = RingBuffer::with_capacity(8);
+        assert!(buffer.latest(4).is_empty());
+
+        buffer.push_slice(&numbered(0, 3));
+        assert_eq!(buffer.latest(100), numbered(0, 3));
+        assert_eq!(buffer.latest(2), numbered(1, 2));
+        assert!(buffer.latest(0).is_empty());
+
+        buffer.push_slice(&numbered(3, 10));
+        assert_eq!(buffer.latest(100), numbered(5, 8));
+    }
+
+    #[test]
+    fn ring_buffer_since_reads_across_the_wrap() {
+        let mut buffer = RingBuffer::with_capacity(8);
+        buffer.push_slice(&numbered(0, 6));
+        buffer.push_slice(&numbered(6, 5));
+        // Holds positions 3..11, stored wrapped around the end of `data`
+        assert_eq!(buffer.since(5), (5, numbered(5, 6)));
+        assert_eq!(buffer.since(7), (7, numbered(7, 4)));
+        // Overwritten audio starts from the oldest still held
+        assert_eq!(buffer.since(1), (3, numbered(3, 8)));
+        // Nothing new yet, or a position from the future
+        assert_eq!(buffer.since(11), (11, Vec::new()));
+        assert_eq!(buffer.since(50), (11, Vec::new()));
+    }
+}
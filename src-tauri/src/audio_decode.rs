@@ -0,0 +1,206 @@
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{DecoderOptions, CODEC_TYPE_NULL, CODEC_TYPE_OPUS};
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+use std::io::Cursor;
+use std::path::Path;
+use std::sync::Arc;
+use anyhow::{anyhow, Context, Result};
+use crate::resample;
+use crate::vault;
+
+/// Audio decoded to mono f32 PCM, plus details of the original stream.
+pub struct DecodedAudio {
+    pub samples: Vec<f32>,
+    pub sample_rate: u32,
+    pub source_sample_rate: u32,
+    pub channels: u16,
+    pub codec: String,
+}
+
+impl DecodedAudio {
+    pub fn duration_seconds(&self) -> f64 {
+        self.samples.len() as f64 / self.sample_rate.max(1) as f64
+    }
+}
+
+/// Decodes WAV, MP3, FLAC, Ogg Vorbis, Ogg Opus (with the `opus` feature) and
+/// M4A/AAC files to mono PCM, resampling to `target_rate` when given.
+/// Recordings encrypted by the vault are decrypted on the way in.
+pub fn decode_file(file_path: &str, target_rate: Option<u32>) -> Result<DecodedAudio> {
+    let bytes = vault::read_file(Path::new(file_path))
+        .with_context(|| format!("Failed to read audio file: {}", file_path))?;
+    let extension = Path::new(file_path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase());
+
+    decode_bytes(bytes, extension.as_deref(), target_rate)
+        .with_context(|| format!("Failed to decode {}", file_path))
+}
+
+/// Same as [`decode_file`] for audio already held in memory.
+pub fn decode_bytes(bytes: Vec<u8>, extension: Option<&str>, target_rate: Option<u32>) -> Result<DecodedAudio> {
+    // Shared so the Opus fallback can re-read the stream without another copy
+    let bytes: Arc<[u8]> = Arc::from(bytes);
+    let (interleaved, sample_rate, channels, codec) = match decode_with_symphonia(bytes.clone(), extension) {
+        Ok(decoded) => decoded,
+        Err(DecodeFailure::Opus) => decode_ogg_opus(&bytes)?,
+        Err(DecodeFailure::Other(e)) => return Err(e),
+    };
+
+    let mono = resample::downmix(&interleaved, channels);
+    let output_rate = target_rate.unwrap_or(sample_rate);
+
+    Ok(DecodedAudio {
+        samples: resample::resample(&mono, sample_rate, output_rate)?,
+        sample_rate: output_rate,
+        source_sample_rate: sample_rate,
+        channels,
+        codec,
+    })
+}
+
+enum DecodeFailure {
+    /// Symphonia can demux Ogg Opus but has no Opus decoder
+    Opus,
+    Other(anyhow::Error),
+}
+
+impl From<SymphoniaError> for DecodeFailure {
+    fn from(e: SymphoniaError) -> Self {
+        DecodeFailure::Other(e.into())
+    }
+}
+
+fn decode_with_symphonia(bytes: Arc<[u8]>, extension: Option<&str>) -> Result<(Vec<f32>, u32, u16, String), DecodeFailure> {
+    let mss = MediaSourceStream::new(Box::new(Cursor::new(bytes)), Default::default());
+    let mut hint = Hint::new();
+    if let Some(extension) = extension {
+        hint.with_extension(extension);
+    }
+
+    let probed = symphonia::default::get_probe().format(
+        &hint,
+        mss,
+        &FormatOptions::default(),
+        &MetadataOptions::default(),
+    )?;
+    let mut format = probed.format;
+
+    let track = format
+        .tracks()
+        .iter()
+        .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+        .ok_or_else(|| DecodeFailure::Other(anyhow!("No audio track found")))?;
+    if track.codec_params.codec == CODEC_TYPE_OPUS {
+        return Err(DecodeFailure::Opus);
+    }
+
+    let track_id = track.id;
+    let codec = symphonia::default::get_codecs()
+        .get_codec(track.codec_params.codec)
+        .map(|descriptor| descriptor.short_name.to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+    let mut sample_rate = track.codec_params.sample_rate.unwrap_or(0);
+    let mut channels = track.codec_params.channels.map(|c| c.count() as u16).unwrap_or(0);
+
+    let mut decoder = symphonia::default::get_codecs().make(&track.codec_params, &DecoderOptions::default())?;
+    let mut interleaved = Vec::new();
+    let mut sample_buf: Option<SampleBuffer<f32>> = None;
+
+    loop {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            Err(SymphoniaError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+            Err(SymphoniaError::ResetRequired) => break,
+            Err(e) => return Err(e.into()),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+
+        match decoder.decode(&packet) {
+            Ok(decoded) => {
+                let spec = *decoded.spec();
+                sample_rate = spec.rate;
+                channels = spec.channels.count() as u16;
+
+                let needed = decoded.capacity() * spec.channels.count();
+                let buf = match &mut sample_buf {
+                    Some(buf) if buf.capacity() >= needed => buf,
+                    _ => sample_buf.insert(SampleBuffer::new(decoded.capacity() as u64, spec)),
+                };
+                buf.copy_interleaved_ref(decoded);
+                interleaved.extend_from_slice(buf.samples());
+            }
+            // Corrupt frames are skipped rather than failing the whole file
+            Err(SymphoniaError::DecodeError(e)) => eprintln!("Skipping undecodable audio frame: {}", e),
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    if sample_rate == 0 || channels == 0 {
+        return Err(DecodeFailure::Other(anyhow!("Could not determine sample rate or channel count")));
+    }
+
+    Ok((interleaved, sample_rate, channels, codec))
+}
+
+#[cfg(feature = "opus")]
+fn decode_ogg_opus(bytes: &[u8]) -> Result<(Vec<f32>, u32, u16, String)> {
+    use audiopus::{coder::Decoder, packet::Packet, Channels, MutSignals, SampleRate};
+
+    // Opus always decodes at 48 kHz
+    const OPUS_RATE: u32 = 48000;
+    const MAX_FRAME_SAMPLES: usize = 5760;
+
+    let mut reader = ogg::PacketReader::new(Cursor::new(bytes));
+
+    let head = reader.read_packet()?.ok_or_else(|| anyhow!("Empty Ogg stream"))?;
+    if head.data.len() < 19 || &head.data[..8] != b"OpusHead" {
+        return Err(anyhow!("Ogg stream is not Opus"));
+    }
+    let channel_count = head.data[9];
+    let pre_skip = u16::from_le_bytes([head.data[10], head.data[11]]) as usize;
+    let opus_channels = match channel_count {
+        1 => Channels::Mono,
+        2 => Channels::Stereo,
+        n => return Err(anyhow!("Unsupported Opus channel count: {}", n)),
+    };
+    let serial = head.stream_serial();
+
+    let mut decoder = Decoder::new(SampleRate::Hz48000, opus_channels)?;
+    let mut frame = vec![0.0f32; MAX_FRAME_SAMPLES * channel_count as usize];
+    let mut interleaved = Vec::new();
+    let mut seen_tags = false;
+
+    while let Some(packet) = reader.read_packet()? {
+        if packet.stream_serial() != serial {
+            continue;
+        }
+        // Second packet is OpusTags metadata
+        if !seen_tags {
+            seen_tags = true;
+            continue;
+        }
+
+        let input = Packet::try_from(packet.data.as_slice())?;
+        let output = MutSignals::try_from(frame.as_mut_slice())?;
+        let decoded = decoder.decode_float(Some(input), output, false)?;
+        interleaved.extend_from_slice(&frame[..decoded * channel_count as usize]);
+    }
+
+    let skip = (pre_skip * channel_count as usize).min(interleaved.len());
+    interleaved.drain(..skip);
+
+    Ok((interleaved, OPUS_RATE, channel_count as u16, "opus".to_string()))
+}
+
+#[cfg(not(feature = "opus"))]
+fn decode_ogg_opus(_bytes: &[u8]) -> Result<(Vec<f32>, u32, u16, String)> {
+    Err(anyhow!("Opus decoding requires building with the `opus` feature"))
+}
@@ -0,0 +1,80 @@
+// Checks a Dwight evidence bundle offline, without the app or its library.
+//
+//     verify-evidence <bundle.zip> [--key <public key hex>]
+//
+// Exits 0 if the bundle verifies against the trusted key, 1 if it doesn't,
+// 2 if it can't be read, and 3 if its hashes are consistent but there was no
+// --key to check the signer against.
+
+// The export side of the module is unused here
+#[allow(dead_code)]
+#[path = "../evidence_bundle.rs"]
+mod evidence_bundle;
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+const USAGE: &str = "Usage: verify-evidence <bundle.zip> [--key <public key hex>]";
+
+fn main() -> ExitCode {
+    let mut bundle: Option<PathBuf> = None;
+    let mut trusted_key: Option<String> = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--key" => match args.next() {
+                Some(key) => trusted_key = Some(key),
+                None => return usage(),
+            },
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return ExitCode::SUCCESS;
+            }
+            _ if bundle.is_none() => bundle = Some(PathBuf::from(arg)),
+            _ => return usage(),
+        }
+    }
+    let Some(bundle) = bundle else { return usage() };
+
+    let report = match evidence_bundle::verify_bundle(&bundle, trusted_key.as_deref()) {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("Could not verify {}: {:#}", bundle.display(), e);
+            return ExitCode::from(2);
+        }
+    };
+
+    println!("Bundle:     {}", bundle.display());
+    println!("Created:    {}", report.created_at.as_deref().unwrap_or("unknown"));
+    println!("Signed by:  {}", report.public_key.as_deref().unwrap_or("unknown"));
+    match report.trusted_key {
+        Some(true) => println!("Signer:     matches the trusted key"),
+        Some(false) => println!("Signer:     DOES NOT match the trusted key"),
+        None => println!("Signer:     not checked (pass --key to require a specific signer)"),
+    }
+    println!("Signature:  {}", if report.signature_valid { "valid" } else { "INVALID" });
+    println!("Files:      {} checked", report.files_checked);
+    println!(
+        "Audio:      {} match their capture hash, {} have none recorded",
+        report.audio_matching_capture, report.audio_unhashed
+    );
+    for error in &report.errors {
+        println!("  - {}", error);
+    }
+
+    if report.valid {
+        println!("VERIFIED");
+        ExitCode::SUCCESS
+    } else if report.consistent {
+        println!("HASHES CONSISTENT, SIGNER UNVERIFIED (pass --key to verify)");
+        ExitCode::from(3)
+    } else {
+        println!("NOT VERIFIED");
+        ExitCode::from(1)
+    }
+}
+
+fn usage() -> ExitCode {
+    eprintln!("{}", USAGE);
+    ExitCode::from(2)
+}
@@ -1,9 +1,17 @@
-use rusqlite::{Connection, Result};
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::{OptionalExtension, Result};
+use crate::embeddings;
+use crate::migrations;
+use crate::vault::{self, Key};
+use crate::whisper::{NonSpeechEvent, TranscriptionSegment};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use tauri::api::path::app_data_dir;
+use zeroize::Zeroizing;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AudioRecord {
     pub id: Option<i32>,
     pub title: String,
@@ -12,6 +20,18 @@ pub struct AudioRecord {
     pub duration: f64,
     pub created_at: String,
     pub triggers: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub starred: bool,
+    /// SHA-256 of the audio as captured, hex encoded
+    #[serde(default)]
+    pub sha256: Option<String>,
+    #[serde(default)]
+    pub capture_device: Option<String>,
+    /// When the audio itself started, as opposed to when it was saved
+    #[serde(default)]
+    pub captured_at: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -23,130 +43,397 @@ pub struct DwightMemory {
     pub user_input: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// The kinds of trigger the trigger engine acts on.
+pub const TRIGGER_TYPES: [&str; 2] = ["sound", "speech"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SoundTrigger {
     pub id: Option<i32>,
-    pub trigger_type: String, // "sound" or "speech"
+    pub trigger_type: String, // One of TRIGGER_TYPES
     pub trigger_value: String,
     pub is_active: bool,
     pub created_at: String,
 }
 
+/// Filters for [`Database::list_audio_records`]. Dates are RFC 3339 or
+/// `YYYY-MM-DD`; every bound is inclusive and optional, and a bare
+/// `created_before` date includes the whole of that day.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RecordFilter {
+    pub offset: u32,
+    pub limit: u32,
+    pub created_after: Option<String>,
+    pub created_before: Option<String>,
+    pub min_duration: Option<f64>,
+    pub max_duration: Option<f64>,
+    pub has_transcript: Option<bool>,
+    pub tag: Option<String>,
+}
+
+/// One page of records plus the number matching the filter overall.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RecordPage {
+    pub records: Vec<AudioRecord>,
+    pub total: i64,
+    pub offset: u32,
+    pub limit: u32,
+}
+
+/// A transcript segment stored against its record. Times are seconds from
+/// the start of the recording.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredSegment {
+    pub id: i64,
+    pub record_id: i64,
+    pub start: f64,
+    pub end: f64,
+    pub text: String,
+    pub confidence: f64,
+}
+
+/// A non-speech event found by audio analysis of a record.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AudioEvent {
+    pub id: i64,
+    pub record_id: i64,
+    pub start: f64,
+    pub end: f64,
+    pub event_type: String,
+    pub confidence: f64,
+}
+
+/// One entry in the append-only custody log. `hash` covers the entry and
+/// the previous entry's hash, so altering or removing any entry breaks the
+/// chain from that point on.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub id: i64,
+    /// Kept after the record itself is deleted
+    pub record_id: Option<i64>,
+    pub action: String,
+    /// JSON describing the change, or empty
+    pub details: String,
+    pub created_at: String,
+    pub prev_hash: String,
+    pub hash: String,
+}
+
+/// A place where the audit chain doesn't hold.
+#[derive(Debug, Clone)]
+pub struct ChainBreak {
+    /// Record the entry belongs to, as the row reads now
+    pub record_id: Option<i64>,
+    pub message: String,
+}
+
+/// What an embedding was made from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "type", content = "id", rename_all = "snake_case")]
+pub enum EmbeddingSource {
+    Recording(i64),
+    Memory(i64),
+}
+
+impl EmbeddingSource {
+    fn ids(self) -> (Option<i64>, Option<i64>) {
+        match self {
+            EmbeddingSource::Recording(id) => (Some(id), None),
+            EmbeddingSource::Memory(id) => (None, Some(id)),
+        }
+    }
+}
+
+/// A stretch of text to embed. Times are seconds into the recording, when known.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbeddingChunk {
+    pub start: Option<f64>,
+    pub end: Option<f64>,
+    pub text: String,
+}
+
+/// An embedded chunk with what's needed to cite it.
+#[derive(Debug, Clone)]
+pub struct StoredEmbedding {
+    pub source: EmbeddingSource,
+    pub chunk: EmbeddingChunk,
+    /// Title of the recording; `None` for memories
+    pub title: Option<String>,
+    pub created_at: String,
+    pub vector: Vec<f32>,
+}
+
+/// `prev_hash` of the first audit entry.
+pub const AUDIT_GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// A record matching a transcript search, best match first. `snippet` is
+/// the matching excerpt with hits wrapped in `<mark>` tags.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SearchHit {
+    pub record_id: i64,
+    pub title: String,
+    pub file_path: String,
+    pub created_at: String,
+    pub snippet: Option<String>,
+    pub score: f64,
+    pub segments: Vec<SegmentHit>,
+}
+
+/// A matching transcript segment, so the UI can seek straight to it.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SegmentHit {
+    pub segment_id: i64,
+    pub start: f64,
+    pub end: f64,
+    pub snippet: String,
+}
+
+const SNIPPET_TOKENS: i64 = 12;
+const MAX_SEGMENTS_PER_HIT: usize = 5;
+
+/// Pool of connections to `dwight.db`, shared through `AppState`.
+pub type DbPool = r2d2::Pool<SqliteConnectionManager>;
+
+const MAX_CONNECTIONS: u32 = 8;
+
+const DEFAULT_PAGE_SIZE: u32 = 50;
+const MAX_PAGE_SIZE: u32 = 500;
+
+const RECORD_COLUMNS: &str =
+    "id, title, file_path, transcript, duration, created_at, triggers, tags, starred, sha256, capture_device, captured_at";
+
+const RECORD_FILTER: &str = "(?1 IS NULL OR julianday(created_at) >= julianday(?1))
+    AND (?2 IS NULL OR CASE WHEN length(?2) = 10
+        THEN julianday(created_at) < julianday(?2, '+1 day')
+        ELSE julianday(created_at) <= julianday(?2) END)
+    AND (?3 IS NULL OR duration >= ?3)
+    AND (?4 IS NULL OR duration <= ?4)
+    AND (?5 IS NULL OR (COALESCE(transcript, '') != '') = ?5)
+    AND (?6 IS NULL OR EXISTS (SELECT 1 FROM json_each(tags) WHERE value = ?6))";
+
+fn record_from_row(row: &rusqlite::Row) -> Result<AudioRecord> {
+    let tags: String = row.get(7)?;
+    Ok(AudioRecord {
+        id: Some(row.get(0)?),
+        title: row.get(1)?,
+        file_path: row.get(2)?,
+        transcript: row.get::<_, Option<String>>(3)?,
+        duration: row.get(4)?,
+        created_at: row.get(5)?,
+        triggers: row.get::<_, Option<String>>(6)?,
+        tags: serde_json::from_str(&tags).unwrap_or_default(),
+        starred: row.get(8)?,
+        sha256: row.get(9)?,
+        capture_device: row.get(10)?,
+        captured_at: row.get(11)?,
+    })
+}
+
+/// Trims tags and drops empty and duplicate ones, keeping their order.
+fn normalize_tags(tags: &[String]) -> Vec<String> {
+    let mut normalized: Vec<String> = Vec::new();
+    for tag in tags.iter().map(|t| t.trim()).filter(|t| !t.is_empty()) {
+        if !normalized.iter().any(|t| t == tag) {
+            normalized.push(tag.to_string());
+        }
+    }
+    normalized
+}
+
+fn tags_json(tags: &[String]) -> String {
+    serde_json::to_string(&normalize_tags(tags)).unwrap_or_else(|_| "[]".to_string())
+}
+
+/// A connection checked out of the pool; it is returned when dropped.
 pub struct Database {
-    connection: Connection,
+    connection: r2d2::PooledConnection<SqliteConnectionManager>,
+}
+
+/// Directory holding `dwight.db` and everything Dwight writes to disk.
+pub fn app_data_path(config: &tauri::Config) -> PathBuf {
+    app_data_dir(config).unwrap_or_else(|| PathBuf::from("."))
+}
+
+/// Whether `path` is a recording the app stored itself in `recordings_dir`
+/// (see [`crate::audio_capture::recordings_dir`]), as opposed to a file the
+/// user picked from elsewhere or one of the app's own databases and keys.
+/// Only these are ever encrypted or deleted. Both paths are resolved first,
+/// so `..` and symlinks can't point outside; a file that doesn't exist is
+/// never a recording.
+pub fn is_recording_file(path: &Path, recordings_dir: &Path) -> bool {
+    match (path.canonicalize(), recordings_dir.canonicalize()) {
+        (Ok(path), Ok(dir)) => path.starts_with(dir) && path.is_file(),
+        _ => false,
+    }
 }
 
 impl Database {
-    pub fn new(config: &tauri::Config) -> Result<Self> {
-        let app_data_path = app_data_dir(config).unwrap_or_else(|| PathBuf::from("."));
-        std::fs::create_dir_all(&app_data_path).map_err(|e| rusqlite::Error::SqliteFailure(
-            rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_CANTOPEN),
-            Some(format!("Failed to create app data directory: {}", e))
-        ))?;
+    /// Opens the pool on the SQLCipher-encrypted database and migrates the
+    /// schema to the latest version. Called whenever storage is unlocked.
+    pub fn open_pool(config: &tauri::Config, key: &Key) -> anyhow::Result<DbPool> {
+        let app_data_path = app_data_path(config);
+        std::fs::create_dir_all(&app_data_path)
+            .map_err(|e| anyhow::anyhow!("Failed to create app data directory: {}", e))?;
         
         let db_path = app_data_path.join("dwight.db");
-        let connection = Connection::open(db_path)?;
+        let key_hex = Zeroizing::new(hex::encode(key.as_ref()));
+        let encrypted_existing = db_path.exists() && encrypt_plaintext_db(&db_path, &key_hex)?;
+        encrypt_plaintext_backups(&db_path, &key_hex)?;
+        let pool = build_pool(&db_path, key, MAX_CONNECTIONS)?;
+        
+        let mut connection = pool.get()?;
+        migrations::run(&mut connection, &db_path)?;
         
-        let db = Database { connection };
-        db.initialize_tables()?;
-        Ok(db)
+        if encrypted_existing {
+            let database = Database { connection };
+            database.encrypt_recordings(&crate::audio_capture::recordings_dir(config))?;
+        }
+        Ok(pool)
     }
 
-    fn initialize_tables(&self) -> Result<()> {
-        // Audio records table
-        self.connection.execute(
-            "CREATE TABLE IF NOT EXISTS audio_records (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                title TEXT NOT NULL,
-                file_path TEXT NOT NULL,
-                transcript TEXT,
-                duration REAL NOT NULL,
-                created_at TEXT NOT NULL,
-                triggers TEXT
-            )",
-            [],
-        )?;
+    /// Opens a database snapshot taken from another library (see
+    /// [`Database::snapshot_to`]), bringing its schema up to date so it can
+    /// be read with the usual queries.
+    pub fn open_snapshot(path: &Path, key: &Key) -> anyhow::Result<DbPool> {
+        let pool = build_pool(path, key, 1)?;
+        let mut connection = pool.get()?;
+        migrations::run(&mut connection, path)?;
+        drop(connection);
+        Ok(pool)
+    }
 
-        // Dwight's memory/conversation history
-        self.connection.execute(
-            "CREATE TABLE IF NOT EXISTS dwight_memory (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                context TEXT NOT NULL,
-                response TEXT NOT NULL,
-                created_at TEXT NOT NULL,
-                user_input TEXT NOT NULL
-            )",
-            [],
-        )?;
+    /// Writes a consistent copy of the whole database to `dest` using the
+    /// online backup API, so recording can carry on meanwhile. The copy ends
+    /// up encrypted with `dest_key` rather than the library key.
+    pub fn snapshot_to(&self, dest: &Path, key: &Key, dest_key: &Key) -> anyhow::Result<()> {
+        let mut target = rusqlite::Connection::open(dest)?;
+        target.execute_batch(&key_pragma(&hex::encode(key.as_ref())))?;
+        {
+            let backup = rusqlite::backup::Backup::new(&self.connection, &mut target)?;
+            backup.run_to_completion(256, std::time::Duration::from_millis(10), None)?;
+        }
+        // Rekeying needs a rollback journal; the copied header says WAL
+        let rekey = Zeroizing::new(format!(
+            "PRAGMA journal_mode = DELETE; PRAGMA rekey = \"x'{}'\";",
+            hex::encode(dest_key.as_ref())
+        ));
+        target.execute_batch(&rekey)?;
+        Ok(())
+    }
 
-        // Sound and speech triggers
-        self.connection.execute(
-            "CREATE TABLE IF NOT EXISTS sound_triggers (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                trigger_type TEXT NOT NULL,
-                trigger_value TEXT NOT NULL,
-                is_active BOOLEAN NOT NULL DEFAULT 1,
-                created_at TEXT NOT NULL
-            )",
-            [],
-        )?;
+    pub fn schema_version(&self) -> anyhow::Result<i64> {
+        migrations::current_version(&self.connection)
+    }
 
+    /// Encrypts audio files this app wrote before storage was encrypted.
+    /// Files that live outside the recordings folder belong to the user and
+    /// are left alone.
+    fn encrypt_recordings(&self, recordings_dir: &Path) -> anyhow::Result<()> {
+        let mut encrypted = 0;
+        for record in self.get_all_audio_records()? {
+            let path = Path::new(&record.file_path);
+            if !is_recording_file(path, recordings_dir) {
+                continue;
+            }
+            match vault::encrypt_in_place(path) {
+                Ok(true) => encrypted += 1,
+                Ok(false) => {}
+                Err(e) => eprintln!("Failed to encrypt {}: {}", path.display(), e),
+            }
+        }
+        println!("Encrypted {} existing recordings", encrypted);
         Ok(())
     }
+    
+    pub fn from_pool(pool: &DbPool) -> std::result::Result<Self, r2d2::Error> {
+        Ok(Database { connection: pool.get()? })
+    }
 
     pub fn save_audio_record(&self, record: &AudioRecord) -> Result<i64> {
         let now = chrono::Utc::now().to_rfc3339();
-        self.connection.execute(
-            "INSERT INTO audio_records (title, file_path, transcript, duration, created_at, triggers)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
-            [
-                &record.title,
-                &record.file_path,
+        self.insert_audio_record(record, &now, "created")
+    }
+
+    /// Inserts a record brought in from another library, keeping its
+    /// original creation time.
+    pub fn import_audio_record(&self, record: &AudioRecord) -> Result<i64> {
+        self.insert_audio_record(record, &record.created_at, "imported")
+    }
+
+    fn insert_audio_record(&self, record: &AudioRecord, created_at: &str, action: &str) -> Result<i64> {
+        let tx = self.write_transaction()?;
+        tx.execute(
+            "INSERT INTO audio_records (title, file_path, transcript, duration, created_at, triggers, tags, starred,
+                                        sha256, capture_device, captured_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            rusqlite::params![
+                record.title,
+                record.file_path,
                 record.transcript.as_deref().unwrap_or(""),
-                &record.duration.to_string(),
-                &now,
+                record.duration,
+                created_at,
                 record.triggers.as_deref().unwrap_or(""),
+                tags_json(&record.tags),
+                record.starred,
+                record.sha256,
+                record.capture_device,
+                record.captured_at,
             ],
         )?;
-        Ok(self.connection.last_insert_rowid())
+        let id = tx.last_insert_rowid();
+        let details = serde_json::json!({
+            "file_path": record.file_path,
+            "sha256": record.sha256,
+            "capture_device": record.capture_device,
+            "captured_at": record.captured_at,
+        });
+        append_audit(&tx, Some(id), action, &details.to_string())?;
+        tx.commit()?;
+        Ok(id)
     }
 
     pub fn save_dwight_memory(&self, memory: &DwightMemory) -> Result<i64> {
         let now = chrono::Utc::now().to_rfc3339();
+        self.insert_dwight_memory(memory, &now)
+    }
+
+    pub fn import_dwight_memory(&self, memory: &DwightMemory) -> Result<i64> {
+        self.insert_dwight_memory(memory, &memory.created_at)
+    }
+
+    fn insert_dwight_memory(&self, memory: &DwightMemory, created_at: &str) -> Result<i64> {
         self.connection.execute(
             "INSERT INTO dwight_memory (context, response, created_at, user_input)
              VALUES (?1, ?2, ?3, ?4)",
-            [&memory.context, &memory.response, &now, &memory.user_input],
+            [&memory.context, &memory.response, created_at, &memory.user_input],
         )?;
         Ok(self.connection.last_insert_rowid())
     }
 
-    pub fn save_trigger(&self, trigger: &SoundTrigger) -> Result<i64> {
+    /// Triggers are audited along with recordings, since they decide what
+    /// gets captured.
+    pub fn save_trigger(&self, trigger: &SoundTrigger) -> anyhow::Result<i64> {
+        check_trigger_type(&trigger.trigger_type)?;
         let now = chrono::Utc::now().to_rfc3339();
-        self.connection.execute(
+        let tx = self.write_transaction()?;
+        tx.execute(
             "INSERT INTO sound_triggers (trigger_type, trigger_value, is_active, created_at)
              VALUES (?1, ?2, ?3, ?4)",
-            [&trigger.trigger_type, &trigger.trigger_value, &trigger.is_active.to_string(), &now],
+            rusqlite::params![trigger.trigger_type, trigger.trigger_value, trigger.is_active, now],
         )?;
-        Ok(self.connection.last_insert_rowid())
+        let id = tx.last_insert_rowid();
+        audit_trigger(&tx, id, "trigger_created")?;
+        tx.commit()?;
+        Ok(id)
     }
 
     pub fn get_all_audio_records(&self) -> Result<Vec<AudioRecord>> {
-        let mut stmt = self.connection.prepare(
-            "SELECT id, title, file_path, transcript, duration, created_at, triggers FROM audio_records ORDER BY created_at DESC"
-        )?;
+        let mut stmt = self.connection.prepare(&format!(
+            "SELECT {} FROM audio_records ORDER BY created_at DESC",
+            RECORD_COLUMNS
+        ))?;
         
-        let record_iter = stmt.query_map([], |row| {
-            Ok(AudioRecord {
-                id: Some(row.get(0)?),
-                title: row.get(1)?,
-                file_path: row.get(2)?,
-                transcript: row.get::<_, Option<String>>(3)?,
-                duration: row.get(4)?,
-                created_at: row.get(5)?,
-                triggers: row.get::<_, Option<String>>(6)?,
-            })
-        })?;
+        let record_iter = stmt.query_map([], record_from_row)?;
 
         let mut records = Vec::new();
         for record in record_iter {
@@ -155,6 +442,118 @@ impl Database {
         Ok(records)
     }
 
+    pub fn get_audio_record(&self, id: i64) -> Result<Option<AudioRecord>> {
+        self.connection
+            .query_row(
+                &format!("SELECT {} FROM audio_records WHERE id = ?1", RECORD_COLUMNS),
+                [id],
+                record_from_row,
+            )
+            .optional()
+    }
+
+    /// Newest records first, one page at a time.
+    pub fn list_audio_records(&self, filter: &RecordFilter) -> Result<RecordPage> {
+        let limit = match filter.limit {
+            0 => DEFAULT_PAGE_SIZE,
+            limit => limit.min(MAX_PAGE_SIZE),
+        };
+        let params = rusqlite::params![
+            filter.created_after,
+            filter.created_before,
+            filter.min_duration,
+            filter.max_duration,
+            filter.has_transcript,
+            filter.tag,
+            limit,
+            filter.offset,
+        ];
+
+        let total: i64 = self.connection.query_row(
+            &format!("SELECT COUNT(*) FROM audio_records WHERE {}", RECORD_FILTER),
+            &params[..6],
+            |row| row.get(0),
+        )?;
+
+        let mut stmt = self.connection.prepare(&format!(
+            "SELECT {} FROM audio_records WHERE {} ORDER BY created_at DESC, id DESC LIMIT ?7 OFFSET ?8",
+            RECORD_COLUMNS, RECORD_FILTER
+        ))?;
+        let record_iter = stmt.query_map(params, record_from_row)?;
+
+        let mut records = Vec::new();
+        for record in record_iter {
+            records.push(record?);
+        }
+        Ok(RecordPage { records, total, offset: filter.offset, limit })
+    }
+
+    /// Renames and/or retags a record. Returns false if it doesn't exist.
+    pub fn update_audio_record(&self, id: i64, title: Option<&str>, tags: Option<&[String]>) -> Result<bool> {
+        let tx = self.write_transaction()?;
+        let updated = tx.execute(
+            "UPDATE audio_records SET title = COALESCE(?2, title), tags = COALESCE(?3, tags) WHERE id = ?1",
+            rusqlite::params![id, title, tags.map(tags_json)],
+        )?;
+        if updated > 0 {
+            let details = serde_json::json!({ "title": title, "tags": tags.map(normalize_tags) });
+            append_audit(&tx, Some(id), "updated", &details.to_string())?;
+        }
+        tx.commit()?;
+        Ok(updated > 0)
+    }
+
+    pub fn set_record_starred(&self, id: i64, starred: bool) -> Result<bool> {
+        let tx = self.write_transaction()?;
+        let updated = tx.execute(
+            "UPDATE audio_records SET starred = ?2 WHERE id = ?1",
+            rusqlite::params![id, starred],
+        )?;
+        if updated > 0 {
+            append_audit(&tx, Some(id), if starred { "starred" } else { "unstarred" }, "")?;
+        }
+        tx.commit()?;
+        Ok(updated > 0)
+    }
+
+    /// Deletes a record along with its segments and events. The audio file
+    /// is left to the caller; see [`Database::delete_audio_record_and_file`].
+    pub fn delete_audio_record(&self, id: i64) -> Result<bool> {
+        let tx = self.write_transaction()?;
+        let removed: Option<(String, String, Option<String>)> = tx
+            .query_row(
+                "SELECT title, file_path, sha256 FROM audio_records WHERE id = ?1",
+                [id],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .optional()?;
+        let Some((title, file_path, sha256)) = removed else {
+            return Ok(false);
+        };
+        tx.execute("DELETE FROM audio_records WHERE id = ?1", [id])?;
+        let details = serde_json::json!({ "title": title, "file_path": file_path, "sha256": sha256 });
+        append_audit(&tx, Some(id), "deleted", &details.to_string())?;
+        tx.commit()?;
+        Ok(true)
+    }
+
+    /// Deletes a record and, if the app stored it in `recordings_dir`, its
+    /// audio file. Anything else belongs to the user and is left alone. The
+    /// file goes first, so a file that can't be removed keeps its record.
+    pub fn delete_audio_record_and_file(&self, record: &AudioRecord, recordings_dir: &Path) -> anyhow::Result<()> {
+        if is_recording_file(Path::new(&record.file_path), recordings_dir) {
+            match std::fs::remove_file(&record.file_path) {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(anyhow::anyhow!("Failed to delete {}: {}", record.file_path, e)),
+            }
+        }
+        if let Some(id) = record.id {
+            self.delete_audio_record(id as i64)?;
+        }
+        Ok(())
+    }
+
     pub fn get_dwight_memory_context(&self, limit: usize) -> Result<Vec<DwightMemory>> {
         let mut stmt = self.connection.prepare(
             "SELECT id, context, response, created_at, user_input FROM dwight_memory ORDER BY created_at DESC LIMIT ?1"
@@ -178,9 +577,18 @@ impl Database {
     }
 
     pub fn get_active_triggers(&self) -> Result<Vec<SoundTrigger>> {
-        let mut stmt = self.connection.prepare(
-            "SELECT id, trigger_type, trigger_value, is_active, created_at FROM sound_triggers WHERE is_active = 1"
-        )?;
+        self.query_triggers("WHERE is_active = 1")
+    }
+
+    pub fn get_all_triggers(&self) -> Result<Vec<SoundTrigger>> {
+        self.query_triggers("ORDER BY created_at DESC")
+    }
+
+    fn query_triggers(&self, clause: &str) -> Result<Vec<SoundTrigger>> {
+        let mut stmt = self.connection.prepare(&format!(
+            "SELECT id, trigger_type, trigger_value, is_active, created_at FROM sound_triggers {}",
+            clause
+        ))?;
         
         let trigger_iter = stmt.query_map([], |row| {
             Ok(SoundTrigger {
@@ -198,4 +606,772 @@ impl Database {
         }
         Ok(triggers)
     }
-}
\ No newline at end of file
+
+    /// Changes any of a trigger's fields. Returns false if it doesn't exist.
+    pub fn update_trigger(
+        &self,
+        id: i64,
+        trigger_type: Option<&str>,
+        trigger_value: Option<&str>,
+        is_active: Option<bool>,
+    ) -> anyhow::Result<bool> {
+        if let Some(trigger_type) = trigger_type {
+            check_trigger_type(trigger_type)?;
+        }
+        let tx = self.write_transaction()?;
+        let updated = tx.execute(
+            "UPDATE sound_triggers SET
+                trigger_type = COALESCE(?2, trigger_type),
+                trigger_value = COALESCE(?3, trigger_value),
+                is_active = COALESCE(?4, is_active)
+             WHERE id = ?1",
+            rusqlite::params![id, trigger_type, trigger_value, is_active],
+        )?;
+        if updated > 0 {
+            audit_trigger(&tx, id, "trigger_updated")?;
+        }
+        tx.commit()?;
+        Ok(updated > 0)
+    }
+
+    pub fn delete_trigger(&self, id: i64) -> Result<bool> {
+        let tx = self.write_transaction()?;
+        // Logged first, while the row is still there to describe
+        let found = audit_trigger(&tx, id, "trigger_deleted")?;
+        if found {
+            tx.execute("DELETE FROM sound_triggers WHERE id = ?1", [id])?;
+        }
+        tx.commit()?;
+        Ok(found)
+    }
+
+    pub fn find_record_by_path(&self, file_path: &str) -> Result<Option<i64>> {
+        self.connection
+            .query_row(
+                "SELECT id FROM audio_records WHERE file_path = ?1 ORDER BY id DESC LIMIT 1",
+                [file_path],
+                |row| row.get(0),
+            )
+            .optional()
+    }
+
+    /// Replaces a record's transcript and its timed segments in one go.
+    pub fn save_transcription(&self, record_id: i64, text: &str, segments: &[TranscriptionSegment]) -> Result<()> {
+        let tx = self.write_transaction()?;
+        tx.execute(
+            "UPDATE audio_records SET transcript = ?1 WHERE id = ?2",
+            rusqlite::params![text, record_id],
+        )?;
+        tx.execute("DELETE FROM segments WHERE record_id = ?1", [record_id])?;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT INTO segments (record_id, start, \"end\", text, confidence) VALUES (?1, ?2, ?3, ?4, ?5)"
+            )?;
+            for segment in segments {
+                stmt.execute(rusqlite::params![
+                    record_id,
+                    segment.start,
+                    segment.end,
+                    segment.text.trim(),
+                    segment.confidence,
+                ])?;
+            }
+        }
+        let details = serde_json::json!({ "segments": segments.len() });
+        append_audit(&tx, Some(record_id), "transcribed", &details.to_string())?;
+        tx.commit()
+    }
+
+    /// Ranked full-text search over record titles, transcripts and segments.
+    /// Records are ordered by bm25 (lower is better), taking whichever of the
+    /// record or its best segment matched more strongly.
+    pub fn search_transcripts(&self, query: &str, limit: usize) -> Result<Vec<SearchHit>> {
+        let Some(fts_query) = fts_query(query) else {
+            return Ok(Vec::new());
+        };
+
+        let mut hits: Vec<SearchHit> = Vec::new();
+        let mut stmt = self.connection.prepare(
+            "SELECT r.id, r.title, r.file_path, r.created_at,
+                    snippet(records_fts, -1, '<mark>', '</mark>', '…', ?2),
+                    bm25(records_fts, 2.0, 1.0)
+             FROM records_fts JOIN audio_records r ON r.id = records_fts.rowid
+             WHERE records_fts MATCH ?1
+             ORDER BY bm25(records_fts, 2.0, 1.0)
+             LIMIT ?3"
+        )?;
+        let rows = stmt.query_map(rusqlite::params![fts_query, SNIPPET_TOKENS, limit as i64], |row| {
+            Ok(SearchHit {
+                record_id: row.get(0)?,
+                title: row.get(1)?,
+                file_path: row.get(2)?,
+                created_at: row.get(3)?,
+                snippet: row.get(4)?,
+                score: row.get(5)?,
+                segments: Vec::new(),
+            })
+        })?;
+        for hit in rows {
+            hits.push(hit?);
+        }
+
+        let mut stmt = self.connection.prepare(
+            "SELECT s.record_id, r.title, r.file_path, r.created_at, s.id, s.start, s.\"end\",
+                    snippet(segments_fts, 0, '<mark>', '</mark>', '…', ?2),
+                    bm25(segments_fts)
+             FROM segments_fts
+             JOIN segments s ON s.id = segments_fts.rowid
+             JOIN audio_records r ON r.id = s.record_id
+             WHERE segments_fts MATCH ?1
+             ORDER BY bm25(segments_fts)
+             LIMIT ?3"
+        )?;
+        let rows = stmt.query_map(
+            rusqlite::params![fts_query, SNIPPET_TOKENS, (limit * MAX_SEGMENTS_PER_HIT) as i64],
+            |row| {
+                let hit = SearchHit {
+                    record_id: row.get(0)?,
+                    title: row.get(1)?,
+                    file_path: row.get(2)?,
+                    created_at: row.get(3)?,
+                    snippet: None,
+                    score: row.get(8)?,
+                    segments: Vec::new(),
+                };
+                let segment = SegmentHit {
+                    segment_id: row.get(4)?,
+                    start: row.get(5)?,
+                    end: row.get(6)?,
+                    snippet: row.get(7)?,
+                };
+                Ok((hit, segment))
+            },
+        )?;
+        for row in rows {
+            let (hit, segment) = row?;
+            match hits.iter_mut().find(|h| h.record_id == hit.record_id) {
+                Some(existing) => {
+                    existing.score = existing.score.min(hit.score);
+                    if existing.segments.len() < MAX_SEGMENTS_PER_HIT {
+                        existing.segments.push(segment);
+                    }
+                }
+                None => hits.push(SearchHit { segments: vec![segment], ..hit }),
+            }
+        }
+
+        hits.sort_by(|a, b| a.score.total_cmp(&b.score));
+        hits.truncate(limit);
+        for hit in &mut hits {
+            hit.segments.sort_by(|a, b| a.start.total_cmp(&b.start));
+        }
+        Ok(hits)
+    }
+
+    /// Replaces the analysis events stored for a record.
+    pub fn save_audio_events(&self, record_id: i64, events: &[NonSpeechEvent]) -> Result<()> {
+        let tx = self.write_transaction()?;
+        tx.execute("DELETE FROM audio_events WHERE record_id = ?1", [record_id])?;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT INTO audio_events (record_id, start, \"end\", event_type, confidence) VALUES (?1, ?2, ?3, ?4, ?5)"
+            )?;
+            for event in events {
+                stmt.execute(rusqlite::params![
+                    record_id,
+                    event.start,
+                    event.end,
+                    event.event_type,
+                    event.confidence,
+                ])?;
+            }
+        }
+        let details = serde_json::json!({ "events": events.len() });
+        append_audit(&tx, Some(record_id), "analyzed", &details.to_string())?;
+        tx.commit()
+    }
+
+    /// Segments in time order, optionally limited to one record and to those
+    /// overlapping `[start, end]` seconds.
+    pub fn get_segments(&self, record_id: Option<i64>, start: Option<f64>, end: Option<f64>) -> Result<Vec<StoredSegment>> {
+        let mut stmt = self.connection.prepare(
+            "SELECT id, record_id, start, \"end\", text, confidence FROM segments
+             WHERE (?1 IS NULL OR record_id = ?1)
+               AND (?2 IS NULL OR \"end\" >= ?2)
+               AND (?3 IS NULL OR start <= ?3)
+             ORDER BY record_id, start"
+        )?;
+        
+        let segment_iter = stmt.query_map(rusqlite::params![record_id, start, end], |row| {
+            Ok(StoredSegment {
+                id: row.get(0)?,
+                record_id: row.get(1)?,
+                start: row.get(2)?,
+                end: row.get(3)?,
+                text: row.get(4)?,
+                confidence: row.get(5)?,
+            })
+        })?;
+
+        let mut segments = Vec::new();
+        for segment in segment_iter {
+            segments.push(segment?);
+        }
+        Ok(segments)
+    }
+
+    /// Events in time order, filtered like [`Database::get_segments`] and
+    /// optionally by event type.
+    pub fn get_audio_events(
+        &self,
+        record_id: Option<i64>,
+        event_type: Option<&str>,
+        start: Option<f64>,
+        end: Option<f64>,
+    ) -> Result<Vec<AudioEvent>> {
+        let mut stmt = self.connection.prepare(
+            "SELECT id, record_id, start, \"end\", event_type, confidence FROM audio_events
+             WHERE (?1 IS NULL OR record_id = ?1)
+               AND (?2 IS NULL OR event_type = ?2)
+               AND (?3 IS NULL OR \"end\" >= ?3)
+               AND (?4 IS NULL OR start <= ?4)
+             ORDER BY record_id, start"
+        )?;
+        
+        let event_iter = stmt.query_map(rusqlite::params![record_id, event_type, start, end], |row| {
+            Ok(AudioEvent {
+                id: row.get(0)?,
+                record_id: row.get(1)?,
+                start: row.get(2)?,
+                end: row.get(3)?,
+                event_type: row.get(4)?,
+                confidence: row.get(5)?,
+            })
+        })?;
+
+        let mut events = Vec::new();
+        for event in event_iter {
+            events.push(event?);
+        }
+        Ok(events)
+    }
+
+    /// Hash stored with each source's embeddings, to tell which are stale.
+    pub fn embedding_hashes(&self) -> Result<HashMap<EmbeddingSource, String>> {
+        let mut stmt = self.connection.prepare(
+            "SELECT record_id, memory_id, source_hash FROM embeddings GROUP BY record_id, memory_id"
+        )?;
+        let rows = stmt.query_map([], |row| {
+            let source = match row.get::<_, Option<i64>>(0)? {
+                Some(record_id) => EmbeddingSource::Recording(record_id),
+                None => EmbeddingSource::Memory(row.get(1)?),
+            };
+            Ok((source, row.get(2)?))
+        })?;
+        rows.collect()
+    }
+
+    /// Replaces a source's embeddings. `vectors` pairs up with `chunks`.
+    pub fn replace_embeddings(
+        &self,
+        source: EmbeddingSource,
+        source_hash: &str,
+        model: &str,
+        chunks: &[EmbeddingChunk],
+        vectors: &[Vec<f32>],
+    ) -> Result<()> {
+        let (record_id, memory_id) = source.ids();
+        let tx = self.write_transaction()?;
+        tx.execute(
+            "DELETE FROM embeddings WHERE record_id IS ?1 AND memory_id IS ?2",
+            rusqlite::params![record_id, memory_id],
+        )?;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT INTO embeddings (record_id, memory_id, chunk, start, \"end\", text, source_hash, model, vector)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)"
+            )?;
+            for (i, (chunk, vector)) in chunks.iter().zip(vectors).enumerate() {
+                stmt.execute(rusqlite::params![
+                    record_id,
+                    memory_id,
+                    i as i64,
+                    chunk.start,
+                    chunk.end,
+                    chunk.text,
+                    source_hash,
+                    model,
+                    embeddings::vector_to_blob(vector),
+                ])?;
+            }
+        }
+        tx.commit()
+    }
+
+    /// Every stored embedding made with `model`, for a similarity scan.
+    pub fn load_embeddings(&self, model: &str) -> Result<Vec<StoredEmbedding>> {
+        let mut stmt = self.connection.prepare(
+            "SELECT e.record_id, e.memory_id, e.start, e.\"end\", e.text, r.title,
+                    COALESCE(r.captured_at, r.created_at, m.created_at), e.vector
+             FROM embeddings e
+             LEFT JOIN audio_records r ON r.id = e.record_id
+             LEFT JOIN dwight_memory m ON m.id = e.memory_id
+             WHERE e.model = ?1"
+        )?;
+        let rows = stmt.query_map([model], |row| {
+            let source = match row.get::<_, Option<i64>>(0)? {
+                Some(record_id) => EmbeddingSource::Recording(record_id),
+                None => EmbeddingSource::Memory(row.get(1)?),
+            };
+            let vector: Vec<u8> = row.get(7)?;
+            Ok(StoredEmbedding {
+                source,
+                chunk: EmbeddingChunk { start: row.get(2)?, end: row.get(3)?, text: row.get(4)? },
+                title: row.get(5)?,
+                created_at: row.get::<_, Option<String>>(6)?.unwrap_or_default(),
+                vector: embeddings::blob_to_vector(&vector),
+            })
+        })?;
+        rows.collect()
+    }
+
+    /// Number of embedded chunks and of sources they came from.
+    pub fn embedding_counts(&self) -> Result<(i64, i64)> {
+        self.connection.query_row(
+            "SELECT COUNT(*), COUNT(DISTINCT COALESCE('r' || record_id, 'm' || memory_id)) FROM embeddings",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+    }
+
+    /// Starts a transaction that takes the write lock up front, so two
+    /// connections appending to the audit log can't both read the same tail.
+    fn write_transaction(&self) -> Result<rusqlite::Transaction<'_>> {
+        rusqlite::Transaction::new_unchecked(&self.connection, rusqlite::TransactionBehavior::Immediate)
+    }
+
+    /// Records an access that doesn't change anything, such as viewing or
+    /// exporting a record.
+    pub fn log_access(&self, record_id: Option<i64>, action: &str, details: &str) -> Result<()> {
+        let tx = self.write_transaction()?;
+        append_audit(&tx, record_id, action, details)?;
+        tx.commit()
+    }
+
+    /// Audit entries in the order they were written, optionally for one
+    /// record only. With a limit, the most recent `limit` entries.
+    pub fn get_audit_log(&self, record_id: Option<i64>, limit: Option<usize>) -> Result<Vec<AuditEntry>> {
+        let mut stmt = self.connection.prepare(
+            "SELECT id, record_id, action, details, created_at, prev_hash, hash FROM audit_log
+             WHERE ?1 IS NULL OR record_id = ?1
+             ORDER BY id DESC LIMIT ?2",
+        )?;
+        let limit = limit.map(|l| l as i64).unwrap_or(-1);
+        let entry_iter = stmt.query_map(rusqlite::params![record_id, limit], audit_entry_from_row)?;
+
+        let mut entries = Vec::new();
+        for entry in entry_iter {
+            entries.push(entry?);
+        }
+        entries.reverse();
+        Ok(entries)
+    }
+
+    /// Walks the whole audit log and describes every place the chain doesn't
+    /// hold: an entry whose hash doesn't match its contents, or one that
+    /// doesn't follow on from the entry before it. Empty means intact.
+    pub fn verify_audit_chain(&self) -> Result<Vec<String>> {
+        Ok(self.audit_chain_breaks()?.into_iter().map(|b| b.message).collect())
+    }
+
+    /// [`Database::verify_audit_chain`], keeping which record each break was
+    /// found at. A removed entry shows up at the one after it.
+    pub fn audit_chain_breaks(&self) -> Result<Vec<ChainBreak>> {
+        let mut stmt = self.connection.prepare(
+            "SELECT id, record_id, action, details, created_at, prev_hash, hash FROM audit_log ORDER BY id",
+        )?;
+        let entry_iter = stmt.query_map([], audit_entry_from_row)?;
+
+        let mut breaks = Vec::new();
+        let mut expected_prev = AUDIT_GENESIS_HASH.to_string();
+        for entry in entry_iter {
+            let entry = entry?;
+            let mut broken = |message: String| breaks.push(ChainBreak { record_id: entry.record_id, message });
+            if entry.prev_hash != expected_prev {
+                broken(format!("Audit entry {} does not follow the entry before it", entry.id));
+            }
+            let hash = audit_hash(&entry.prev_hash, entry.record_id, &entry.action, &entry.details, &entry.created_at);
+            if hash != entry.hash {
+                broken(format!("Audit entry {} has been altered", entry.id));
+            }
+            expected_prev = entry.hash;
+        }
+        Ok(breaks)
+    }
+}
+
+fn audit_entry_from_row(row: &rusqlite::Row) -> Result<AuditEntry> {
+    Ok(AuditEntry {
+        id: row.get(0)?,
+        record_id: row.get(1)?,
+        action: row.get(2)?,
+        details: row.get(3)?,
+        created_at: row.get(4)?,
+        prev_hash: row.get(5)?,
+        hash: row.get(6)?,
+    })
+}
+
+/// SHA-256 over a JSON array of the entry's fields, so no choice of field
+/// values can make two different entries serialize the same way.
+fn audit_hash(prev_hash: &str, record_id: Option<i64>, action: &str, details: &str, created_at: &str) -> String {
+    let canonical = serde_json::json!([prev_hash, record_id, action, details, created_at]).to_string();
+    hex::encode(Sha256::digest(canonical.as_bytes()))
+}
+
+/// Appends to the audit log, chaining onto the newest entry. Call inside a
+/// [`Database::write_transaction`] so the entry commits with the change it
+/// describes.
+fn append_audit(conn: &rusqlite::Connection, record_id: Option<i64>, action: &str, details: &str) -> Result<()> {
+    let prev_hash: String = conn
+        .query_row("SELECT hash FROM audit_log ORDER BY id DESC LIMIT 1", [], |row| row.get(0))
+        .optional()?
+        .unwrap_or_else(|| AUDIT_GENESIS_HASH.to_string());
+    let created_at = chrono::Utc::now().to_rfc3339();
+    let hash = audit_hash(&prev_hash, record_id, action, details, &created_at);
+    conn.execute(
+        "INSERT INTO audit_log (record_id, action, details, created_at, prev_hash, hash) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        rusqlite::params![record_id, action, details, created_at, prev_hash, hash],
+    )?;
+    Ok(())
+}
+
+fn check_trigger_type(trigger_type: &str) -> anyhow::Result<()> {
+    if TRIGGER_TYPES.contains(&trigger_type) {
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!(
+            "Unknown trigger type '{}'; expected one of {}",
+            trigger_type,
+            TRIGGER_TYPES.join(", ")
+        ))
+    }
+}
+
+/// Logs trigger `id` as it currently stands. Triggers aren't tied to a
+/// record, so the entry has none. Returns false if there is no such trigger.
+fn audit_trigger(conn: &rusqlite::Connection, id: i64, action: &str) -> Result<bool> {
+    let trigger: Option<(String, String, bool)> = conn
+        .query_row(
+            "SELECT trigger_type, trigger_value, is_active FROM sound_triggers WHERE id = ?1",
+            [id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .optional()?;
+    let Some((trigger_type, trigger_value, is_active)) = trigger else {
+        return Ok(false);
+    };
+    let details = serde_json::json!({
+        "trigger_id": id,
+        "trigger_type": trigger_type,
+        "trigger_value": trigger_value,
+        "is_active": is_active,
+    });
+    append_audit(conn, None, action, &details.to_string())?;
+    Ok(true)
+}
+
+/// Pool on an SQLCipher database. The key must be the first statement on
+/// every connection. WAL lets readers proceed while another connection writes.
+fn build_pool(db_path: &Path, key: &Key, max_size: u32) -> anyhow::Result<DbPool> {
+    let key_pragma = key_pragma(&hex::encode(key.as_ref()));
+    let manager = SqliteConnectionManager::file(db_path).with_init(move |c| {
+        c.execute_batch(&key_pragma)?;
+        c.execute_batch("PRAGMA journal_mode = WAL; PRAGMA busy_timeout = 5000; PRAGMA foreign_keys = ON;")
+    });
+    Ok(r2d2::Pool::builder().max_size(max_size).build(manager)?)
+}
+
+/// SQLCipher raw-key form, which skips its own passphrase KDF since the
+/// vault key is already random.
+fn key_pragma(key_hex: &str) -> Zeroizing<String> {
+    Zeroizing::new(format!("PRAGMA key = \"x'{}'\";", key_hex))
+}
+
+fn readable(conn: &rusqlite::Connection) -> bool {
+    conn.query_row("SELECT COUNT(*) FROM sqlite_master", [], |row| row.get::<_, i64>(0)).is_ok()
+}
+
+/// Replaces the plaintext database at `path` with an encrypted copy made by
+/// `sqlcipher_export`.
+fn export_encrypted(plain: rusqlite::Connection, path: &Path, key_hex: &str) -> anyhow::Result<()> {
+    plain.execute_batch("PRAGMA wal_checkpoint(TRUNCATE);")?;
+
+    let export_path = path.with_extension("encrypting");
+    if export_path.exists() {
+        std::fs::remove_file(&export_path)?;
+    }
+    let attach = Zeroizing::new(format!("ATTACH DATABASE ?1 AS encrypted KEY \"x'{}'\"", key_hex));
+    plain.execute(&attach, [export_path.to_string_lossy()])?;
+    plain.query_row("SELECT sqlcipher_export('encrypted')", [], |_| Ok(()))?;
+    plain.execute_batch("DETACH DATABASE encrypted;")?;
+    drop(plain);
+
+    for suffix in ["-wal", "-shm"] {
+        let _ = std::fs::remove_file(format!("{}{}", path.display(), suffix));
+    }
+    std::fs::rename(&export_path, path)?;
+    Ok(())
+}
+
+/// Converts a database written before encryption was added. Returns false if
+/// `db_path` already opens with the key. A plaintext database is exported into
+/// an encrypted copy that then replaces it; anything else that doesn't open
+/// with the key is an error rather than something to overwrite.
+fn encrypt_plaintext_db(db_path: &Path, key_hex: &str) -> anyhow::Result<bool> {
+    let encrypted = rusqlite::Connection::open(db_path)?;
+    encrypted.execute_batch(&key_pragma(key_hex))?;
+    if readable(&encrypted) {
+        return Ok(false);
+    }
+    drop(encrypted);
+
+    let plain = rusqlite::Connection::open(db_path)?;
+    if !readable(&plain) {
+        return Err(anyhow::anyhow!(
+            "{} cannot be opened with the storage key",
+            db_path.display()
+        ));
+    }
+    export_encrypted(plain, db_path, key_hex)?;
+    println!("Encrypted existing database {}", db_path.display());
+    Ok(true)
+}
+
+/// Encrypts migration backups (`dwight.db.*.bak`) taken while the database
+/// was still plaintext, so no unencrypted copy of the library stays on disk.
+/// Backups that are already encrypted are left as they are.
+fn encrypt_plaintext_backups(db_path: &Path, key_hex: &str) -> anyhow::Result<()> {
+    let (Some(dir), Some(file_name)) = (db_path.parent(), db_path.file_name().and_then(|n| n.to_str())) else {
+        return Ok(());
+    };
+    let prefix = format!("{}.", file_name);
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let is_backup = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .is_some_and(|name| name.starts_with(&prefix) && name.ends_with(".bak"));
+        if !is_backup {
+            continue;
+        }
+        let plain = rusqlite::Connection::open(&path)?;
+        if readable(&plain) {
+            export_encrypted(plain, &path, key_hex)
+                .map_err(|e| anyhow::anyhow!("Failed to encrypt backup {}: {}", path.display(), e))?;
+            println!("Encrypted database backup {}", path.display());
+        }
+    }
+    Ok(())
+}
+
+/// Turns free text into an FTS5 query: every word must appear, and the last
+/// one may be a prefix so results update while the user is still typing.
+/// Terms are quoted so punctuation and FTS operators in the input are literal.
+fn fts_query(input: &str) -> Option<String> {
+    let terms: Vec<String> = input
+        .split_whitespace()
+        .map(|term| term.replace('"', ""))
+        .filter(|term| !term.is_empty())
+        .map(|term| format!("\"{}\"", term))
+        .collect();
+    let (last, rest) = terms.split_last()?;
+    let mut query = rest.to_vec();
+    query.push(format!("{}*", last));
+    Some(query.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A migrated, encrypted database in its own directory, removed on drop.
+    struct TestDb {
+        dir: PathBuf,
+        pool: DbPool,
+    }
+
+    impl TestDb {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!("dwight-database-{}-{}", std::process::id(), name));
+            let _ = std::fs::remove_dir_all(&dir);
+            std::fs::create_dir_all(dir.join("recordings")).unwrap();
+            let pool = Database::open_snapshot(&dir.join("dwight.db"), &Zeroizing::new([7u8; 32])).unwrap();
+            TestDb { dir, pool }
+        }
+
+        fn db(&self) -> Database {
+            Database::from_pool(&self.pool).unwrap()
+        }
+
+        fn recordings_dir(&self) -> PathBuf {
+            self.dir.join("recordings")
+        }
+    }
+
+    impl Drop for TestDb {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    fn record(title: &str, file_path: &Path, created_at: &str) -> AudioRecord {
+        AudioRecord {
+            id: None,
+            title: title.to_string(),
+            file_path: file_path.to_string_lossy().into_owned(),
+            transcript: None,
+            duration: 1.0,
+            created_at: created_at.to_string(),
+            triggers: None,
+            tags: Vec::new(),
+            starred: false,
+            sha256: None,
+            capture_device: None,
+            captured_at: None,
+        }
+    }
+
+    #[test]
+    fn date_bounds_include_the_whole_bound_day() {
+        let test = TestDb::new("date-bounds");
+        let db = test.db();
+        for created_at in ["2024-04-30T23:59:00Z", "2024-05-01T00:00:00Z", "2024-05-01T18:30:00Z", "2024-05-02T00:00:00Z"] {
+            db.import_audio_record(&record(created_at, Path::new(""), created_at)).unwrap();
+        }
+        let titles = |after: Option<&str>, before: Option<&str>| -> Vec<String> {
+            let filter = RecordFilter {
+                created_after: after.map(str::to_string),
+                created_before: before.map(str::to_string),
+                ..Default::default()
+            };
+            db.list_audio_records(&filter).unwrap().records.into_iter().map(|r| r.title).collect()
+        };
+
+        assert_eq!(titles(Some("2024-05-01"), Some("2024-05-01")), ["2024-05-01T18:30:00Z", "2024-05-01T00:00:00Z"]);
+        assert_eq!(titles(None, Some("2024-04-30")), ["2024-04-30T23:59:00Z"]);
+        // A full timestamp is still an exact bound
+        assert_eq!(titles(None, Some("2024-05-01T00:00:00Z")), ["2024-05-01T00:00:00Z", "2024-04-30T23:59:00Z"]);
+    }
+
+    #[test]
+    fn triggers_must_have_a_known_type_and_are_audited() {
+        let test = TestDb::new("triggers");
+        let db = test.db();
+        let trigger = |trigger_type: &str| SoundTrigger {
+            id: None,
+            trigger_type: trigger_type.to_string(),
+            trigger_value: "glass".to_string(),
+            is_active: true,
+            created_at: String::new(),
+        };
+
+        assert!(db.save_trigger(&trigger("smell")).is_err());
+        let id = db.save_trigger(&trigger("sound")).unwrap();
+        assert!(db.update_trigger(id, Some("Sound"), None, None).is_err());
+        assert!(db.update_trigger(id, Some("speech"), Some("help"), None).unwrap());
+        assert!(db.delete_trigger(id).unwrap());
+        assert!(!db.delete_trigger(id).unwrap());
+
+        let log = db.get_audit_log(None, None).unwrap();
+        let actions: Vec<&str> = log.iter().map(|e| e.action.as_str()).collect();
+        assert_eq!(actions, ["trigger_created", "trigger_updated", "trigger_deleted"]);
+        assert!(log.iter().all(|e| e.record_id.is_none()));
+        assert!(log[2].details.contains("\"trigger_value\":\"help\""));
+        assert!(db.verify_audit_chain().unwrap().is_empty());
+    }
+
+    #[test]
+    fn audit_chain_detects_edited_and_deleted_entries() {
+        let test = TestDb::new("audit-chain");
+        let db = test.db();
+        for (record_id, action) in [(Some(1), "created"), (Some(1), "played"), (None, "trigger_created"), (Some(2), "created"), (Some(1), "verified")] {
+            db.log_access(record_id, action, "{}").unwrap();
+        }
+        let log = db.get_audit_log(None, None).unwrap();
+        assert_eq!(log.len(), 5);
+        assert_eq!(log[0].prev_hash, AUDIT_GENESIS_HASH);
+        assert!(log.windows(2).all(|pair| pair[1].prev_hash == pair[0].hash));
+        assert!(db.verify_audit_chain().unwrap().is_empty());
+        assert_eq!(db.get_audit_log(Some(1), None).unwrap().len(), 3);
+
+        // The triggers refuse edits; someone with the key can still drop them
+        assert!(db.connection.execute("DELETE FROM audit_log WHERE id = ?1", [log[3].id]).is_err());
+        db.connection
+            .execute_batch("DROP TRIGGER audit_log_no_update; DROP TRIGGER audit_log_no_delete;")
+            .unwrap();
+
+        // Rewriting what an entry says breaks its own hash
+        db.connection.execute("UPDATE audit_log SET details = '{\"edited\":true}' WHERE id = ?1", [log[1].id]).unwrap();
+        let breaks = db.audit_chain_breaks().unwrap();
+        assert_eq!(breaks.len(), 1);
+        assert_eq!(breaks[0].record_id, Some(1));
+        assert_eq!(db.verify_audit_chain().unwrap(), [format!("Audit entry {} has been altered", log[1].id)]);
+
+        // Removing an entry breaks the link from the one after it
+        db.connection.execute("DELETE FROM audit_log WHERE id = ?1", [log[3].id]).unwrap();
+        let breaks = db.audit_chain_breaks().unwrap();
+        assert_eq!(breaks.len(), 2);
+        assert_eq!(breaks[1].record_id, Some(1));
+        assert_eq!(breaks[1].message, format!("Audit entry {} does not follow the entry before it", log[4].id));
+    }
+
+    #[test]
+    fn deleting_a_record_only_removes_files_in_the_recordings_folder() {
+        let test = TestDb::new("delete-files");
+        let db = test.db();
+        let own = test.recordings_dir().join("kitchen.wav");
+        std::fs::write(&own, b"RIFF").unwrap();
+        let database_file = test.dir.join("dwight.db");
+        let escaping = test.recordings_dir().join("..").join("dwight.db");
+
+        for (path, removed) in [(&own, true), (&database_file, false), (&escaping, false)] {
+            let mut saved = record("Kitchen", path, "");
+            saved.id = Some(db.save_audio_record(&saved).unwrap() as i32);
+            db.delete_audio_record_and_file(&saved, &test.recordings_dir()).unwrap();
+            assert!(db.get_audio_record(saved.id.unwrap() as i64).unwrap().is_none());
+            assert_eq!(!path.exists(), removed, "{}", path.display());
+        }
+    }
+
+    #[test]
+    fn converting_to_encrypted_storage_encrypts_old_backups() {
+        let dir = std::env::temp_dir().join(format!("dwight-database-{}-backups", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let db_path = dir.join("dwight.db");
+        let backup_path = dir.join("dwight.db.v0-20240101_000000.bak");
+        for path in [&db_path, &backup_path] {
+            let conn = rusqlite::Connection::open(path).unwrap();
+            conn.execute_batch("CREATE TABLE audio_records (title TEXT); INSERT INTO audio_records VALUES ('Kitchen');")
+                .unwrap();
+        }
+        let key_hex = hex::encode([7u8; 32]);
+
+        assert!(encrypt_plaintext_db(&db_path, &key_hex).unwrap());
+        encrypt_plaintext_backups(&db_path, &key_hex).unwrap();
+
+        for path in [&db_path, &backup_path] {
+            assert!(!readable(&rusqlite::Connection::open(path).unwrap()), "{} is still plaintext", path.display());
+            let conn = rusqlite::Connection::open(path).unwrap();
+            conn.execute_batch(&key_pragma(&key_hex)).unwrap();
+            let title: String = conn.query_row("SELECT title FROM audio_records", [], |row| row.get(0)).unwrap();
+            assert_eq!(title, "Kitchen");
+        }
+
+        // Already-encrypted files are left alone on later opens
+        assert!(!encrypt_plaintext_db(&db_path, &key_hex).unwrap());
+        encrypt_plaintext_backups(&db_path, &key_hex).unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,133 @@
+use candle_core::{Device, Tensor, D};
+use candle_nn::VarBuilder;
+use candle_transformers::models::bert::{BertModel, Config, DTYPE};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use anyhow::{anyhow, Context, Result};
+use tokenizers::{PaddingParams, PaddingStrategy, Tokenizer, TruncationParams};
+use crate::database;
+
+/// Texts embedded per forward pass.
+const BATCH_SIZE: usize = 16;
+
+/// Longer inputs are truncated; sentence-transformers models are trained on
+/// at most this many tokens.
+const MAX_TOKENS: usize = 256;
+
+/// Directory the embedding model is loaded from.
+pub fn default_model_dir(app_config: &tauri::Config) -> PathBuf {
+    database::app_data_path(app_config).join("models").join("embeddings")
+}
+
+pub fn model_available(model_dir: &Path) -> bool {
+    ["config.json", "tokenizer.json", "model.safetensors"]
+        .iter()
+        .all(|file| model_dir.join(file).exists())
+}
+
+/// Sentence embeddings from a local BERT-family model (MiniLM, BGE, E5 and
+/// the like) running on candle. The directory must contain `config.json`,
+/// `tokenizer.json` and `model.safetensors`, as published on Hugging Face.
+/// Embeddings are mean-pooled over the tokens and normalised to unit length.
+pub struct Embedder {
+    model: BertModel,
+    tokenizer: Tokenizer,
+    device: Device,
+    model_id: String,
+}
+
+impl Embedder {
+    pub fn load(model_dir: &Path) -> Result<Self> {
+        let device = Device::Cpu;
+
+        let config_path = model_dir.join("config.json");
+        let config_json = std::fs::read_to_string(&config_path)
+            .with_context(|| format!("Missing embedding model config at {}", config_path.display()))?;
+        let config: Config = serde_json::from_str(&config_json)?;
+
+        let tokenizer_path = model_dir.join("tokenizer.json");
+        let mut tokenizer = Tokenizer::from_file(&tokenizer_path)
+            .map_err(|e| anyhow!("Failed to load tokenizer {}: {}", tokenizer_path.display(), e))?;
+        let pad_token = tokenizer
+            .id_to_token(config.pad_token_id as u32)
+            .unwrap_or_else(|| "[PAD]".to_string());
+        tokenizer.with_padding(Some(PaddingParams {
+            strategy: PaddingStrategy::BatchLongest,
+            pad_id: config.pad_token_id as u32,
+            pad_token,
+            ..Default::default()
+        }));
+        tokenizer
+            .with_truncation(Some(TruncationParams {
+                max_length: MAX_TOKENS.min(config.max_position_embeddings),
+                ..Default::default()
+            }))
+            .map_err(|e| anyhow!("Failed to configure tokenizer: {}", e))?;
+
+        let weights = model_dir.join("model.safetensors");
+        // Safety: the weights file is memory-mapped read-only and not modified while loaded
+        let vb = unsafe { VarBuilder::from_mmaped_safetensors(&[weights], DTYPE, &device)? };
+        let model = BertModel::load(vb, &config)?;
+
+        // Identifies the model in stored embeddings, which must be redone if it changes
+        let name = model_dir.file_name().and_then(|n| n.to_str()).unwrap_or("model");
+        let model_id = format!("{}-{}", name, &hex::encode(Sha256::digest(config_json.as_bytes()))[..12]);
+
+        Ok(Embedder { model, tokenizer, device, model_id })
+    }
+
+    pub fn model_id(&self) -> &str {
+        &self.model_id
+    }
+
+    pub fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let mut vectors = Vec::with_capacity(texts.len());
+        for batch in texts.chunks(BATCH_SIZE) {
+            vectors.extend(self.embed_batch(batch)?);
+        }
+        Ok(vectors)
+    }
+
+    fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let encodings = self
+            .tokenizer
+            .encode_batch(texts.to_vec(), true)
+            .map_err(|e| anyhow!("Tokenization failed: {}", e))?;
+
+        let ids = encodings
+            .iter()
+            .map(|e| Tensor::new(e.get_ids(), &self.device))
+            .collect::<candle_core::Result<Vec<_>>>()?;
+        let masks = encodings
+            .iter()
+            .map(|e| Tensor::new(e.get_attention_mask(), &self.device))
+            .collect::<candle_core::Result<Vec<_>>>()?;
+        let input_ids = Tensor::stack(&ids, 0)?;
+        let attention_mask = Tensor::stack(&masks, 0)?;
+        let token_type_ids = input_ids.zeros_like()?;
+
+        let hidden = self.model.forward(&input_ids, &token_type_ids, Some(&attention_mask))?;
+
+        // Mean over real tokens only, so padding doesn't dilute short texts
+        let mask = attention_mask.to_dtype(DTYPE)?.unsqueeze(2)?;
+        let summed = hidden.broadcast_mul(&mask)?.sum(1)?;
+        let pooled = summed.broadcast_div(&mask.sum(1)?)?;
+        let normalized = pooled.broadcast_div(&pooled.sqr()?.sum_keepdim(D::Minus1)?.sqrt()?)?;
+        Ok(normalized.to_vec2::<f32>()?)
+    }
+}
+
+pub fn vector_to_blob(vector: &[f32]) -> Vec<u8> {
+    vector.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+pub fn blob_to_vector(blob: &[u8]) -> Vec<f32> {
+    blob.chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect()
+}
+
+/// Cosine similarity of two unit-length vectors.
+pub fn similarity(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
@@ -0,0 +1,174 @@
+use std::path::Path;
+use anyhow::{anyhow, Context, Result};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::OsRng;
+use ed25519_dalek::SigningKey;
+use serde::{Deserialize, Serialize};
+use tauri::{command, AppHandle, Manager, State};
+use zeroize::Zeroizing;
+use crate::database::{self, AudioEvent, AudioRecord, AuditEntry, StoredSegment};
+use crate::evidence_bundle::{self, BundleVerification, BundleWriter, AUDIO_PREFIX, EVIDENCE_ENTRY};
+use crate::library::PartialFile;
+use crate::state::AppState;
+use crate::vault;
+
+/// The signing key, encrypted like recordings. Generated on first export.
+const SIGNING_KEY_FILE: &str = "evidence_signing.key";
+
+/// Everything known about one record, as written to `evidence.json`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EvidenceRecord {
+    pub record: AudioRecord,
+    pub segments: Vec<StoredSegment>,
+    pub events: Vec<AudioEvent>,
+    pub audit_log: Vec<AuditEntry>,
+    /// Bundle entry holding the decrypted audio, if the file still existed
+    pub audio_entry: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EvidenceDocument {
+    pub exported_at: String,
+    pub records: Vec<EvidenceRecord>,
+    /// Result of checking the whole audit log at export time
+    pub audit_chain_errors: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EvidenceExportReport {
+    pub path: String,
+    pub records: usize,
+    pub files: usize,
+    pub missing_files: Vec<String>,
+    /// Share this with recipients so they can check who signed the bundle
+    pub public_key: String,
+    pub size_bytes: u64,
+}
+
+/// Loads this install's signing key, creating it the first time.
+fn signing_key(config: &tauri::Config) -> Result<SigningKey> {
+    let data_dir = database::app_data_path(config);
+    let path = data_dir.join(SIGNING_KEY_FILE);
+    if path.exists() {
+        let bytes = Zeroizing::new(vault::read_file(&path)?);
+        let seed: [u8; 32] = bytes
+            .as_slice()
+            .try_into()
+            .map_err(|_| anyhow!("{} is corrupt", SIGNING_KEY_FILE))?;
+        return Ok(SigningKey::from_bytes(&seed));
+    }
+
+    let mut seed = Zeroizing::new([0u8; 32]);
+    OsRng.fill_bytes(seed.as_mut());
+    std::fs::create_dir_all(&data_dir)?;
+    vault::write_file(&path, seed.as_ref())?;
+    println!("Generated evidence signing key {}", path.display());
+    Ok(SigningKey::from_bytes(&seed))
+}
+
+/// Writes the chosen records to a signed zip at `destination`: decrypted
+/// audio, `evidence.json` with each record's transcript, segments, events
+/// and audit trail, and a manifest of file hashes signed with this
+/// install's Ed25519 key. Anyone can check it with [`evidence_bundle::verify_bundle`].
+pub fn export_bundle(state: &AppState, record_ids: &[i64], destination: &Path) -> Result<EvidenceExportReport> {
+    if record_ids.is_empty() {
+        return Err(anyhow!("Choose at least one recording to export"));
+    }
+    let db = state.db().map_err(|e| anyhow!(e))?;
+    let key = signing_key(state.app_config())?;
+    let public_key = hex::encode(key.verifying_key().as_bytes());
+
+    let (partial, file) = PartialFile::create(destination)?;
+    let mut bundle = BundleWriter::new(file);
+
+    let mut records = Vec::new();
+    let mut missing_files = Vec::new();
+    for &id in record_ids {
+        let record = db.get_audio_record(id)?.ok_or_else(|| anyhow!("Audio record {} not found", id))?;
+        let path = Path::new(&record.file_path);
+        let audio_entry = if path.is_file() {
+            let bytes = vault::read_file(path).with_context(|| format!("Failed to read {}", record.file_path))?;
+            let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("audio.wav");
+            let name = format!("{}{}-{}", AUDIO_PREFIX, id, file_name);
+            bundle.add_audio(id, &name, &bytes, record.sha256.clone())?;
+            Some(name)
+        } else {
+            missing_files.push(record.file_path.clone());
+            None
+        };
+
+        records.push(EvidenceRecord {
+            segments: db.get_segments(Some(id), None, None)?,
+            events: db.get_audio_events(Some(id), None, None, None)?,
+            audit_log: db.get_audit_log(Some(id), None)?,
+            record,
+            audio_entry,
+        });
+    }
+
+    let document = EvidenceDocument {
+        exported_at: chrono::Utc::now().to_rfc3339(),
+        records,
+        audit_chain_errors: db.verify_audit_chain()?,
+    };
+    let evidence = serde_json::to_vec_pretty(&document)?;
+    bundle.add_file(EVIDENCE_ENTRY, &evidence)?;
+    let manifest = bundle.finish(&key, &document.exported_at)?;
+    partial.persist()?;
+
+    let details = serde_json::json!({ "bundle": destination.to_string_lossy(), "public_key": public_key }).to_string();
+    for record in &document.records {
+        if let Some(id) = record.record.id {
+            db.log_access(Some(id as i64), "evidence_exported", &details)?;
+        }
+    }
+
+    Ok(EvidenceExportReport {
+        path: destination.to_string_lossy().to_string(),
+        records: document.records.len(),
+        files: manifest.audio.len(),
+        missing_files,
+        public_key,
+        size_bytes: std::fs::metadata(destination)?.len(),
+    })
+}
+
+/// Exports the given records as a signed evidence bundle at `destination`
+/// (a `.zip` path).
+#[command]
+pub async fn export_evidence_bundle(
+    record_ids: Vec<i64>,
+    destination: String,
+    app_handle: AppHandle,
+) -> Result<EvidenceExportReport, String> {
+    tokio::task::spawn_blocking(move || {
+        let state = app_handle.state::<AppState>();
+        export_bundle(&state, &record_ids, Path::new(&destination)).map_err(|e| format!("Evidence export error: {:#}", e))
+    })
+    .await
+    .map_err(|e| format!("Evidence export error: {}", e))?
+}
+
+/// Checks a bundle's signature and file hashes. Needs no unlocked storage,
+/// so bundles from other installs can be checked too.
+#[command]
+pub async fn verify_evidence_bundle(
+    bundle_path: String,
+    trusted_key: Option<String>,
+) -> Result<BundleVerification, String> {
+    tokio::task::spawn_blocking(move || {
+        evidence_bundle::verify_bundle(Path::new(&bundle_path), trusted_key.as_deref())
+            .map_err(|e| format!("Evidence verification error: {:#}", e))
+    })
+    .await
+    .map_err(|e| format!("Evidence verification error: {}", e))?
+}
+
+/// The public half of this install's signing key, hex encoded.
+#[command]
+pub async fn get_evidence_public_key(state: State<'_, AppState>) -> Result<String, String> {
+    // Only read once storage is unlocked, since the key file is encrypted
+    state.db()?;
+    let key = signing_key(state.app_config()).map_err(|e| format!("Evidence key error: {}", e))?;
+    Ok(hex::encode(key.verifying_key().as_bytes()))
+}
@@ -0,0 +1,418 @@
+// Also compiled into the standalone `verify-evidence` binary, so nothing
+// here may depend on the rest of the crate.
+
+use std::fs::File;
+use std::io::{Read, Seek, Write};
+use std::path::Path;
+use anyhow::{anyhow, Context, Result};
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+pub const BUNDLE_FORMAT_VERSION: u32 = 1;
+pub const MANIFEST_ENTRY: &str = "manifest.json";
+/// Ed25519 signature over the exact bytes of the manifest
+pub const SIGNATURE_ENTRY: &str = "manifest.sig";
+pub const EVIDENCE_ENTRY: &str = "evidence.json";
+pub const AUDIO_PREFIX: &str = "audio/";
+
+/// Lists every other file in the bundle with its hash. Signing the manifest
+/// therefore covers the whole bundle.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BundleManifest {
+    pub format_version: u32,
+    pub app_version: String,
+    pub created_at: String,
+    /// Hex encoded Ed25519 public key of the signer
+    pub public_key: String,
+    pub entries: Vec<BundleEntry>,
+    pub audio: Vec<AudioLink>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleEntry {
+    pub name: String,
+    pub size: u64,
+    pub sha256: String,
+}
+
+/// Ties a record to its audio entry and the hash taken when the audio was
+/// captured, so the verifier can show the audio is unchanged since then.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AudioLink {
+    pub record_id: i64,
+    pub entry: String,
+    pub captured_sha256: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct BundleVerification {
+    /// Signature and every hash check out and the signer is the trusted key.
+    /// Never true without a trusted key: a bundle re-signed by anyone is
+    /// self-consistent
+    pub valid: bool,
+    /// Signature and every hash check out, whoever the signer is
+    pub consistent: bool,
+    pub signature_valid: bool,
+    pub public_key: Option<String>,
+    /// Whether the signer matches the key the caller expected; `None` when
+    /// no key was given
+    pub trusted_key: Option<bool>,
+    pub created_at: Option<String>,
+    pub files_checked: usize,
+    /// Audio files whose hash matches the one taken at capture
+    pub audio_matching_capture: usize,
+    /// Audio with no capture hash to compare against
+    pub audio_unhashed: usize,
+    pub errors: Vec<String>,
+}
+
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    hex::encode(Sha256::digest(bytes))
+}
+
+pub fn parse_public_key(hex_key: &str) -> Result<VerifyingKey> {
+    let bytes: [u8; 32] = hex::decode(hex_key.trim())
+        .ok()
+        .and_then(|b| b.try_into().ok())
+        .ok_or_else(|| anyhow!("Public key must be 64 hex characters"))?;
+    VerifyingKey::from_bytes(&bytes).map_err(|_| anyhow!("Not a valid Ed25519 public key"))
+}
+
+/// Writes a bundle, hashing each file as it's added. [`BundleWriter::finish`]
+/// then signs a manifest listing them.
+pub struct BundleWriter<W: Write + Seek> {
+    zip: ZipWriter<W>,
+    options: SimpleFileOptions,
+    entries: Vec<BundleEntry>,
+    audio: Vec<AudioLink>,
+}
+
+impl<W: Write + Seek> BundleWriter<W> {
+    pub fn new(writer: W) -> Self {
+        BundleWriter {
+            zip: ZipWriter::new(writer),
+            options: SimpleFileOptions::default().compression_method(CompressionMethod::Deflated),
+            entries: Vec::new(),
+            audio: Vec::new(),
+        }
+    }
+
+    pub fn add_file(&mut self, name: &str, bytes: &[u8]) -> Result<()> {
+        self.zip.start_file(name, self.options)?;
+        self.zip.write_all(bytes)?;
+        self.entries.push(BundleEntry { name: name.to_string(), size: bytes.len() as u64, sha256: sha256_hex(bytes) });
+        Ok(())
+    }
+
+    /// Adds a record's audio, linked to the hash taken when it was captured.
+    pub fn add_audio(&mut self, record_id: i64, name: &str, bytes: &[u8], captured_sha256: Option<String>) -> Result<()> {
+        self.add_file(name, bytes)?;
+        self.audio.push(AudioLink { record_id, entry: name.to_string(), captured_sha256 });
+        Ok(())
+    }
+
+    pub fn finish(mut self, key: &SigningKey, created_at: &str) -> Result<BundleManifest> {
+        let manifest = BundleManifest {
+            format_version: BUNDLE_FORMAT_VERSION,
+            app_version: env!("CARGO_PKG_VERSION").to_string(),
+            created_at: created_at.to_string(),
+            public_key: hex::encode(key.verifying_key().as_bytes()),
+            entries: self.entries,
+            audio: self.audio,
+        };
+        let manifest_bytes = serde_json::to_vec_pretty(&manifest)?;
+        let signature = key.sign(&manifest_bytes);
+        self.zip.start_file(MANIFEST_ENTRY, self.options)?;
+        self.zip.write_all(&manifest_bytes)?;
+        self.zip.start_file(SIGNATURE_ENTRY, self.options)?;
+        self.zip.write_all(&signature.to_bytes())?;
+        self.zip.finish()?;
+        Ok(manifest)
+    }
+}
+
+fn read_entry(zip: &mut ZipArchive<File>, name: &str) -> Result<Vec<u8>> {
+    let mut entry = zip.by_name(name).with_context(|| format!("Bundle is missing {}", name))?;
+    let mut bytes = Vec::with_capacity(entry.size() as usize);
+    entry.read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// Hash of an entry, streamed so large recordings aren't held in memory.
+fn hash_entry(zip: &mut ZipArchive<File>, name: &str) -> Result<(u64, String)> {
+    let mut entry = zip.by_name(name).with_context(|| format!("Bundle is missing {}", name))?;
+    let mut hasher = Sha256::new();
+    let size = std::io::copy(&mut entry, &mut hasher)?;
+    Ok((size, hex::encode(hasher.finalize())))
+}
+
+/// Checks a bundle without touching any library: the manifest signature,
+/// the hash of every listed file, that nothing unlisted was added, and that
+/// each recording still matches the hash taken when it was captured. Only
+/// with `trusted_key`, and a bundle signed by that key, is it valid; without
+/// one the report can at most say the bundle is consistent.
+/// Problems are collected in the report; `Err` means the bundle couldn't be
+/// read at all.
+pub fn verify_bundle(path: &Path, trusted_key: Option<&str>) -> Result<BundleVerification> {
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut zip = ZipArchive::new(file).context("Not a valid evidence bundle")?;
+    let manifest_bytes = read_entry(&mut zip, MANIFEST_ENTRY)?;
+    let signature_bytes = read_entry(&mut zip, SIGNATURE_ENTRY)?;
+    let manifest: BundleManifest = serde_json::from_slice(&manifest_bytes).context("Unreadable bundle manifest")?;
+
+    let mut report = BundleVerification {
+        public_key: Some(manifest.public_key.clone()),
+        created_at: Some(manifest.created_at.clone()),
+        ..Default::default()
+    };
+    if manifest.format_version > BUNDLE_FORMAT_VERSION {
+        report.errors.push(format!(
+            "Bundle format {} is newer than this verifier supports ({})",
+            manifest.format_version, BUNDLE_FORMAT_VERSION
+        ));
+    }
+
+    match parse_public_key(&manifest.public_key) {
+        Ok(key) => {
+            let signature = Signature::from_slice(&signature_bytes).map_err(|_| anyhow!("Malformed signature"));
+            match signature.and_then(|s| key.verify_strict(&manifest_bytes, &s).map_err(|_| anyhow!("Bad signature"))) {
+                Ok(()) => report.signature_valid = true,
+                Err(_) => report.errors.push("The manifest signature does not verify".to_string()),
+            }
+            if let Some(trusted) = trusted_key {
+                let trusted = parse_public_key(trusted)?;
+                let matches = trusted == key;
+                if !matches {
+                    report.errors.push("The bundle was not signed by the trusted key".to_string());
+                }
+                report.trusted_key = Some(matches);
+            }
+        }
+        Err(e) => report.errors.push(format!("Signer key: {}", e)),
+    }
+
+    let mut hashes = std::collections::HashMap::new();
+    for expected in &manifest.entries {
+        match hash_entry(&mut zip, &expected.name) {
+            Ok((size, sha256)) => {
+                if size != expected.size || sha256 != expected.sha256 {
+                    report.errors.push(format!("{} has been modified", expected.name));
+                }
+                report.files_checked += 1;
+                hashes.insert(expected.name.as_str(), sha256);
+            }
+            Err(e) => report.errors.push(e.to_string()),
+        }
+    }
+    for name in zip.file_names() {
+        let listed = name == MANIFEST_ENTRY
+            || name == SIGNATURE_ENTRY
+            || manifest.entries.iter().any(|e| e.name == name);
+        if !listed {
+            report.errors.push(format!("{} is not covered by the signature", name));
+        }
+    }
+
+    for link in &manifest.audio {
+        let Some(actual) = hashes.get(link.entry.as_str()) else {
+            report.errors.push(format!("Audio for record {} is not listed in the manifest", link.record_id));
+            continue;
+        };
+        match &link.captured_sha256 {
+            Some(captured) if captured == actual => report.audio_matching_capture += 1,
+            Some(_) => report.errors.push(format!(
+                "Audio for record {} differs from the hash taken at capture",
+                link.record_id
+            )),
+            None => report.audio_unhashed += 1,
+        }
+    }
+
+    report.consistent = report.errors.is_empty();
+    report.valid = report.consistent && report.trusted_key == Some(true);
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    const AUDIO: &[u8] = b"RIFF not really a wav";
+
+    struct TestDir(PathBuf);
+
+    impl TestDir {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!("dwight-bundle-{}-{}", name, std::process::id()));
+            let _ = std::fs::remove_dir_all(&dir);
+            std::fs::create_dir_all(&dir).unwrap();
+            TestDir(dir)
+        }
+    }
+
+    impl Drop for TestDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn key(seed: u8) -> SigningKey {
+        SigningKey::from_bytes(&[seed; 32])
+    }
+
+    fn public_hex(key: &SigningKey) -> String {
+        hex::encode(key.verifying_key().as_bytes())
+    }
+
+    /// Exports one recording and its evidence document, as the app does.
+    fn export(path: &Path, key: &SigningKey, captured_sha256: Option<String>) {
+        let mut bundle = BundleWriter::new(File::create(path).unwrap());
+        bundle.add_audio(1, "audio/1-clip.wav", AUDIO, captured_sha256).unwrap();
+        bundle.add_file(EVIDENCE_ENTRY, b"{\"records\": []}").unwrap();
+        bundle.finish(key, "2026-01-01T00:00:00Z").unwrap();
+    }
+
+    fn entries(path: &Path) -> Vec<(String, Vec<u8>)> {
+        let mut zip = ZipArchive::new(File::open(path).unwrap()).unwrap();
+        let names: Vec<String> = zip.file_names().map(|n| n.to_string()).collect();
+        names.into_iter().map(|name| {
+            let bytes = read_entry(&mut zip, &name).unwrap();
+            (name, bytes)
+        }).collect()
+    }
+
+    /// Copies the bundle at `from` to `to`, entry by entry, through `edit`.
+    fn rewrite(from: &Path, to: &Path, edit: impl Fn(&mut Vec<(String, Vec<u8>)>)) {
+        let mut entries = entries(from);
+        edit(&mut entries);
+        let mut zip = ZipWriter::new(File::create(to).unwrap());
+        for (name, bytes) in entries {
+            zip.start_file(name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(&bytes).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    fn replace(entries: &mut [(String, Vec<u8>)], name: &str, bytes: Vec<u8>) {
+        entries.iter_mut().find(|(n, _)| n == name).unwrap().1 = bytes;
+    }
+
+    #[test]
+    fn an_untouched_bundle_verifies_against_its_key() {
+        let dir = TestDir::new("valid");
+        let path = dir.0.join("bundle.zip");
+        let signer = key(1);
+        export(&path, &signer, Some(sha256_hex(AUDIO)));
+
+        let report = verify_bundle(&path, Some(&public_hex(&signer))).unwrap();
+        assert!(report.valid, "{:?}", report.errors);
+        assert!(report.signature_valid);
+        assert_eq!(report.trusted_key, Some(true));
+        assert_eq!(report.files_checked, 2);
+        assert_eq!(report.audio_matching_capture, 1);
+
+        // Without a key it can only be consistent
+        let report = verify_bundle(&path, None).unwrap();
+        assert!(report.consistent && !report.valid);
+        assert_eq!(report.trusted_key, None);
+    }
+
+    #[test]
+    fn a_modified_audio_entry_is_rejected() {
+        let dir = TestDir::new("modified");
+        let original = dir.0.join("original.zip");
+        let tampered = dir.0.join("tampered.zip");
+        let signer = key(1);
+        export(&original, &signer, Some(sha256_hex(AUDIO)));
+        rewrite(&original, &tampered, |entries| replace(entries, "audio/1-clip.wav", b"RIFF edited".to_vec()));
+
+        let report = verify_bundle(&tampered, Some(&public_hex(&signer))).unwrap();
+        assert!(!report.valid && !report.consistent);
+        assert!(report.signature_valid);
+        assert!(report.errors.iter().any(|e| e == "audio/1-clip.wav has been modified"), "{:?}", report.errors);
+        assert!(report.errors.iter().any(|e| e.contains("differs from the hash taken at capture")), "{:?}", report.errors);
+    }
+
+    #[test]
+    fn an_added_entry_is_rejected() {
+        let dir = TestDir::new("added");
+        let original = dir.0.join("original.zip");
+        let tampered = dir.0.join("tampered.zip");
+        let signer = key(1);
+        export(&original, &signer, Some(sha256_hex(AUDIO)));
+        rewrite(&original, &tampered, |entries| entries.push(("audio/2-extra.wav".to_string(), AUDIO.to_vec())));
+
+        let report = verify_bundle(&tampered, Some(&public_hex(&signer))).unwrap();
+        assert!(!report.valid && !report.consistent);
+        assert!(report.signature_valid);
+        assert_eq!(report.errors, vec!["audio/2-extra.wav is not covered by the signature".to_string()]);
+    }
+
+    #[test]
+    fn a_bundle_re_signed_with_another_key_is_not_trusted() {
+        let dir = TestDir::new("resigned");
+        let original = dir.0.join("original.zip");
+        let tampered = dir.0.join("tampered.zip");
+        let signer = key(1);
+        let forger = key(2);
+        export(&original, &signer, Some(sha256_hex(AUDIO)));
+
+        // Swap the audio, fix up the manifest to match and sign it again
+        rewrite(&original, &tampered, |entries| {
+            let forged_audio = b"RIFF forged".to_vec();
+            let manifest_bytes = &entries.iter().find(|(n, _)| n == MANIFEST_ENTRY).unwrap().1;
+            let mut manifest: BundleManifest = serde_json::from_slice(manifest_bytes).unwrap();
+            manifest.public_key = public_hex(&forger);
+            let entry = manifest.entries.iter_mut().find(|e| e.name == "audio/1-clip.wav").unwrap();
+            entry.size = forged_audio.len() as u64;
+            entry.sha256 = sha256_hex(&forged_audio);
+            manifest.audio[0].captured_sha256 = Some(sha256_hex(&forged_audio));
+            let manifest_bytes = serde_json::to_vec_pretty(&manifest).unwrap();
+            let signature = forger.sign(&manifest_bytes).to_bytes().to_vec();
+
+            replace(entries, "audio/1-clip.wav", forged_audio);
+            replace(entries, MANIFEST_ENTRY, manifest_bytes);
+            replace(entries, SIGNATURE_ENTRY, signature);
+        });
+
+        let report = verify_bundle(&tampered, Some(&public_hex(&signer))).unwrap();
+        assert!(report.signature_valid);
+        assert!(!report.valid);
+        assert_eq!(report.trusted_key, Some(false));
+        assert_eq!(report.errors, vec!["The bundle was not signed by the trusted key".to_string()]);
+
+        // Editing the manifest without re-signing breaks the signature instead
+        let unsigned = dir.0.join("unsigned.zip");
+        rewrite(&original, &unsigned, |entries| {
+            let mut manifest = entries.iter().find(|(n, _)| n == MANIFEST_ENTRY).unwrap().1.clone();
+            manifest.extend_from_slice(b"\n");
+            replace(entries, MANIFEST_ENTRY, manifest);
+        });
+        let report = verify_bundle(&unsigned, Some(&public_hex(&signer))).unwrap();
+        assert!(!report.signature_valid && !report.valid);
+    }
+
+    #[test]
+    fn audio_that_differs_from_its_capture_hash_is_rejected() {
+        let dir = TestDir::new("capture");
+        let path = dir.0.join("bundle.zip");
+        let signer = key(1);
+        export(&path, &signer, Some(sha256_hex(b"the audio as captured")));
+
+        let report = verify_bundle(&path, Some(&public_hex(&signer))).unwrap();
+        assert!(report.signature_valid);
+        assert!(!report.valid && !report.consistent);
+        assert_eq!(report.audio_matching_capture, 0);
+        assert_eq!(report.errors, vec!["Audio for record 1 differs from the hash taken at capture".to_string()]);
+
+        // No capture hash at all is reported, not rejected
+        export(&path, &signer, None);
+        let report = verify_bundle(&path, Some(&public_hex(&signer))).unwrap();
+        assert!(report.valid, "{:?}", report.errors);
+        assert_eq!(report.audio_unhashed, 1);
+    }
+}
@@ -0,0 +1,208 @@
+use std::path::Path;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tauri::{command, AppHandle, Manager, State};
+use crate::database::{AuditEntry, Database};
+use crate::state::AppState;
+use crate::vault;
+
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    hex::encode(Sha256::digest(bytes))
+}
+
+/// SHA-256 of a recording's audio. Encrypted files are hashed decrypted, so
+/// re-encrypting a file (after an import, say) doesn't count as a change.
+pub fn hash_file(path: &Path) -> Result<String> {
+    Ok(sha256_hex(&vault::read_file(path)?))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IntegrityStatus {
+    /// The audio and its records are as they were when captured
+    Intact,
+    Tampered,
+    /// The audio file is gone
+    Missing,
+    /// The record predates hashing, so there is nothing to compare against
+    Unhashed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IntegrityReport {
+    pub record_id: i64,
+    pub file_path: String,
+    pub status: IntegrityStatus,
+    /// Hash recorded when the audio was saved
+    pub expected_sha256: Option<String>,
+    /// Hash of the file as it is now
+    pub actual_sha256: Option<String>,
+    /// Whether the whole audit log still chains together. A break elsewhere
+    /// in the log doesn't make this record `tampered` on its own
+    pub chain_valid: bool,
+    /// Everything that contributed to a `tampered` status
+    pub problems: Vec<String>,
+    /// The record's audit entries, oldest first
+    pub history: Vec<AuditEntry>,
+    pub checked_at: String,
+}
+
+/// The hash logged when a record was created or imported. The log is
+/// hash-chained, so this is harder to alter quietly than the record row.
+fn logged_sha256(history: &[AuditEntry]) -> Option<String> {
+    let entry = history.iter().find(|e| e.action == "created" || e.action == "imported")?;
+    let details: serde_json::Value = serde_json::from_str(&entry.details).ok()?;
+    details.get("sha256")?.as_str().map(str::to_string)
+}
+
+/// Rehashes a record's audio and checks it, the stored hash and the audit
+/// log against each other. The check itself is logged.
+pub fn verify_record(db: &Database, record_id: i64) -> Result<IntegrityReport> {
+    let record = db
+        .get_audio_record(record_id)?
+        .ok_or_else(|| anyhow!("Audio record {} not found", record_id))?;
+    let history = db.get_audit_log(Some(record_id), None)?;
+    let chain_breaks = db.audit_chain_breaks()?;
+
+    let mut problems: Vec<String> = chain_breaks
+        .iter()
+        .filter(|b| b.record_id == Some(record_id))
+        .map(|b| b.message.clone())
+        .collect();
+    let logged = logged_sha256(&history);
+    if logged.is_some() && logged != record.sha256 {
+        problems.push("The stored hash differs from the one logged when the record was created".to_string());
+    }
+    // Trust the chained log over the row when they disagree
+    let expected_sha256 = logged.or(record.sha256.clone());
+
+    let path = Path::new(&record.file_path);
+    let actual_sha256 = if path.is_file() { Some(hash_file(path)?) } else { None };
+    if let (Some(expected), Some(actual)) = (&expected_sha256, &actual_sha256) {
+        if expected != actual {
+            problems.push("The audio file no longer matches its recorded hash".to_string());
+        }
+    }
+
+    let status = if !problems.is_empty() {
+        IntegrityStatus::Tampered
+    } else if actual_sha256.is_none() {
+        IntegrityStatus::Missing
+    } else if expected_sha256.is_none() {
+        IntegrityStatus::Unhashed
+    } else {
+        IntegrityStatus::Intact
+    };
+
+    let details = serde_json::json!({ "status": status, "sha256": actual_sha256 });
+    db.log_access(Some(record_id), "verified", &details.to_string())?;
+
+    Ok(IntegrityReport {
+        record_id,
+        file_path: record.file_path,
+        status,
+        expected_sha256,
+        actual_sha256,
+        chain_valid: chain_breaks.is_empty(),
+        problems,
+        history,
+        checked_at: chrono::Utc::now().to_rfc3339(),
+    })
+}
+
+/// Checks a recording for tampering; see [`verify_record`].
+#[command]
+pub async fn verify_record_integrity(record_id: i64, app_handle: AppHandle) -> Result<IntegrityReport, String> {
+    tokio::task::spawn_blocking(move || {
+        let db = app_handle.state::<AppState>().db()?;
+        verify_record(&db, record_id).map_err(|e| format!("Integrity error: {}", e))
+    })
+    .await
+    .map_err(|e| format!("Integrity error: {}", e))?
+}
+
+/// Audit entries, oldest first, for one record or the whole library. With a
+/// limit, only the most recent entries.
+#[command]
+pub async fn get_audit_log(
+    record_id: Option<i64>,
+    limit: Option<u32>,
+    state: State<'_, AppState>,
+) -> Result<Vec<AuditEntry>, String> {
+    let db = state.db()?;
+    db.get_audit_log(record_id, limit.map(|l| l as usize))
+        .map_err(|e| format!("Database error: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zeroize::Zeroizing;
+    use crate::database::AudioRecord;
+
+    fn save(db: &Database, path: &Path, audio: &[u8]) -> i64 {
+        std::fs::write(path, audio).unwrap();
+        db.save_audio_record(&AudioRecord {
+            id: None,
+            title: "Kitchen".to_string(),
+            file_path: path.to_string_lossy().into_owned(),
+            transcript: None,
+            duration: 1.0,
+            created_at: String::new(),
+            triggers: None,
+            tags: Vec::new(),
+            starred: false,
+            sha256: Some(sha256_hex(audio)),
+            capture_device: None,
+            captured_at: None,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn only_the_record_whose_history_was_altered_is_tampered() {
+        let dir = std::env::temp_dir().join(format!("dwight-integrity-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let pool = Database::open_snapshot(&dir.join("dwight.db"), &Zeroizing::new([7u8; 32])).unwrap();
+        let db = Database::from_pool(&pool).unwrap();
+        let kitchen = save(&db, &dir.join("kitchen.wav"), b"RIFF kitchen");
+        let hallway = save(&db, &dir.join("hallway.wav"), b"RIFF hallway");
+
+        let report = verify_record(&db, kitchen).unwrap();
+        assert_eq!(report.status, IntegrityStatus::Intact);
+        assert!(report.chain_valid && report.problems.is_empty());
+
+        // Alter the hallway's creation entry behind the app's back
+        drop(db);
+        pool.get()
+            .unwrap()
+            .execute_batch(&format!(
+                "DROP TRIGGER audit_log_no_update;
+                 UPDATE audit_log SET details = '{{}}' WHERE record_id = {} AND action = 'created';",
+                hallway
+            ))
+            .unwrap();
+        let db = Database::from_pool(&pool).unwrap();
+
+        let report = verify_record(&db, kitchen).unwrap();
+        assert_eq!(report.status, IntegrityStatus::Intact, "{:?}", report.problems);
+        assert!(!report.chain_valid);
+
+        let report = verify_record(&db, hallway).unwrap();
+        assert_eq!(report.status, IntegrityStatus::Tampered);
+        assert!(!report.chain_valid);
+        assert!(report.problems.iter().any(|p| p.ends_with("has been altered")), "{:?}", report.problems);
+
+        // Changed audio is tampering whatever the log says
+        std::fs::write(dir.join("kitchen.wav"), b"RIFF edited").unwrap();
+        let report = verify_record(&db, kitchen).unwrap();
+        assert_eq!(report.status, IntegrityStatus::Tampered);
+        assert_eq!(report.problems, ["The audio file no longer matches its recorded hash"]);
+
+        drop(db);
+        drop(pool);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,485 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tauri::{command, AppHandle, Manager};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+use crate::audio_capture;
+use crate::database::{AudioRecord, Database};
+use crate::integrity::sha256_hex;
+use crate::state::AppState;
+use crate::vault::{self, KdfParams};
+use crate::whisper::{NonSpeechEvent, TranscriptionSegment};
+
+const ARCHIVE_FORMAT_VERSION: u32 = 1;
+const MANIFEST_ENTRY: &str = "manifest.json";
+const DATABASE_ENTRY: &str = "dwight.db";
+const AUDIO_PREFIX: &str = "audio/";
+
+const MIN_PASSPHRASE_CHARS: usize = 8;
+
+/// Describes a library archive. Checksums cover the bytes as stored in the
+/// archive, so corruption is caught before the passphrase is even tried.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Manifest {
+    pub format_version: u32,
+    pub app_version: String,
+    pub created_at: String,
+    pub schema_version: i64,
+    /// How the archive key is derived from the export passphrase
+    pub kdf: KdfParams,
+    pub key_check: String,
+    pub database: ArchiveEntry,
+    pub files: Vec<ArchivedFile>,
+    /// Files that records pointed at but which were gone at export time
+    pub missing_files: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchiveEntry {
+    pub name: String,
+    pub size: u64,
+    pub sha256: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedFile {
+    pub record_id: i64,
+    pub original_path: String,
+    #[serde(flatten)]
+    pub entry: ArchiveEntry,
+    /// Hash of the decrypted audio, used to spot recordings already present
+    pub content_sha256: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExportReport {
+    pub path: String,
+    pub records: usize,
+    pub files: usize,
+    pub missing_files: Vec<String>,
+    pub size_bytes: u64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ImportReport {
+    pub records_imported: usize,
+    /// Titles of recordings skipped because the library already had them
+    pub duplicates_skipped: Vec<String>,
+    pub triggers_imported: usize,
+    pub memories_imported: usize,
+    /// Original paths of recordings imported without audio; their records
+    /// are kept with an empty file path
+    pub missing_files: Vec<String>,
+    pub errors: Vec<String>,
+}
+
+/// Working directory for a snapshot, removed with everything in it on drop.
+struct ScratchDir(PathBuf);
+
+impl ScratchDir {
+    fn new(purpose: &str) -> Result<Self> {
+        let dir = std::env::temp_dir().join(format!(
+            "dwight-{}-{}",
+            purpose,
+            chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default()
+        ));
+        std::fs::create_dir_all(&dir)?;
+        Ok(ScratchDir(dir))
+    }
+}
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// An export written under a `.partial` name and renamed into place by
+/// [`PartialFile::persist`]. Dropped before then, e.g. by a `?` part way
+/// through, it removes the unfinished file.
+pub(crate) struct PartialFile {
+    path: PathBuf,
+    destination: PathBuf,
+    persisted: bool,
+}
+
+impl PartialFile {
+    pub(crate) fn create(destination: &Path) -> Result<(Self, File)> {
+        let path = destination.with_extension("partial");
+        let file = File::create(&path).with_context(|| format!("Failed to create {}", path.display()))?;
+        let partial = PartialFile { path, destination: destination.to_path_buf(), persisted: false };
+        Ok((partial, file))
+    }
+
+    /// Moves the finished file to its destination. Close the file first.
+    pub(crate) fn persist(mut self) -> Result<()> {
+        std::fs::rename(&self.path, &self.destination)
+            .with_context(|| format!("Failed to write {}", self.destination.display()))?;
+        self.persisted = true;
+        Ok(())
+    }
+}
+
+impl Drop for PartialFile {
+    fn drop(&mut self) {
+        if !self.persisted {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+fn check_passphrase(passphrase: &str) -> Result<()> {
+    if passphrase.chars().count() < MIN_PASSPHRASE_CHARS {
+        return Err(anyhow!("Archive passphrase must be at least {} characters", MIN_PASSPHRASE_CHARS));
+    }
+    Ok(())
+}
+
+fn file_name_of(path: &str) -> String {
+    Path::new(path)
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("audio.wav")
+        .to_string()
+}
+
+/// `dir/name`, or `dir/stem-N.ext` with the first free N if that's taken.
+pub(crate) fn unique_path(dir: &Path, name: &str) -> PathBuf {
+    let candidate = dir.join(name);
+    if !candidate.exists() {
+        return candidate;
+    }
+    let path = Path::new(name);
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("audio");
+    let extension = path.extension().and_then(|e| e.to_str());
+    (1..)
+        .map(|n| match extension {
+            Some(ext) => dir.join(format!("{}-{}.{}", stem, n, ext)),
+            None => dir.join(format!("{}-{}", stem, n)),
+        })
+        .find(|p| !p.exists())
+        .expect("some numbered file name is free")
+}
+
+/// Writes the whole library to a single zip: a database snapshot taken with
+/// the online backup API, every referenced audio file, and a manifest. The
+/// database and audio are encrypted with a key derived from `passphrase`,
+/// so the archive can be opened on another machine with a different
+/// storage key.
+pub fn export_to(state: &AppState, destination: &Path, passphrase: &str) -> Result<ExportReport> {
+    check_passphrase(passphrase)?;
+    let key = state.database_key().ok_or_else(|| anyhow!("Storage is locked"))?;
+    let (archive_key, kdf) = vault::new_passphrase_key(passphrase)?;
+
+    let scratch = ScratchDir::new("export")?;
+    let snapshot_path = scratch.0.join(DATABASE_ENTRY);
+    let schema_version = {
+        let db = state.db().map_err(|e| anyhow!(e))?;
+        db.snapshot_to(&snapshot_path, &key, &archive_key)?;
+        db.schema_version()?
+    };
+
+    // List records from the snapshot so the files match the rows exactly
+    let records = {
+        let pool = Database::open_snapshot(&snapshot_path, &archive_key)?;
+        let snapshot = Database::from_pool(&pool)?;
+        snapshot.get_all_audio_records()?
+    };
+
+    let (partial, file) = PartialFile::create(destination)?;
+    let mut zip = ZipWriter::new(file);
+    // Everything but the manifest is encrypted, so compressing it is wasted effort
+    let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+    let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    let mut files = Vec::new();
+    let mut missing_files = Vec::new();
+    for record in &records {
+        let Some(record_id) = record.id else { continue };
+        if !Path::new(&record.file_path).is_file() {
+            missing_files.push(record.file_path.clone());
+            continue;
+        }
+
+        let audio = vault::read_file(Path::new(&record.file_path))
+            .with_context(|| format!("Failed to read {}", record.file_path))?;
+        let sealed = vault::seal_file_bytes(&archive_key, &audio)?;
+        let name = format!("{}{}-{}", AUDIO_PREFIX, record_id, file_name_of(&record.file_path));
+
+        zip.start_file(name.as_str(), stored)?;
+        zip.write_all(&sealed)?;
+        files.push(ArchivedFile {
+            record_id: record_id as i64,
+            original_path: record.file_path.clone(),
+            entry: ArchiveEntry { name, size: sealed.len() as u64, sha256: sha256_hex(&sealed) },
+            content_sha256: sha256_hex(&audio),
+        });
+    }
+
+    let snapshot = std::fs::read(&snapshot_path)?;
+    zip.start_file(DATABASE_ENTRY, stored)?;
+    zip.write_all(&snapshot)?;
+
+    let manifest = Manifest {
+        format_version: ARCHIVE_FORMAT_VERSION,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        created_at: chrono::Utc::now().to_rfc3339(),
+        schema_version,
+        key_check: vault::key_check(&archive_key),
+        kdf,
+        database: ArchiveEntry {
+            name: DATABASE_ENTRY.to_string(),
+            size: snapshot.len() as u64,
+            sha256: sha256_hex(&snapshot),
+        },
+        files,
+        missing_files,
+    };
+    zip.start_file(MANIFEST_ENTRY, deflated)?;
+    zip.write_all(serde_json::to_string_pretty(&manifest)?.as_bytes())?;
+    zip.finish()?;
+    partial.persist()?;
+
+    let db = state.db().map_err(|e| anyhow!(e))?;
+    let details = serde_json::json!({ "archive": destination.to_string_lossy() }).to_string();
+    for file in &manifest.files {
+        db.log_access(Some(file.record_id), "exported", &details)?;
+    }
+
+    Ok(ExportReport {
+        path: destination.to_string_lossy().to_string(),
+        records: records.len(),
+        files: manifest.files.len(),
+        missing_files: manifest.missing_files,
+        size_bytes: std::fs::metadata(destination)?.len(),
+    })
+}
+
+fn read_entry(zip: &mut ZipArchive<File>, name: &str) -> Result<Vec<u8>> {
+    let mut entry = zip.by_name(name).with_context(|| format!("Archive is missing {}", name))?;
+    let mut bytes = Vec::with_capacity(entry.size() as usize);
+    entry.read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn verify_entry(zip: &mut ZipArchive<File>, expected: &ArchiveEntry) -> Result<()> {
+    let mut entry = zip
+        .by_name(&expected.name)
+        .with_context(|| format!("Archive is missing {}", expected.name))?;
+    let mut hasher = Sha256::new();
+    let size = std::io::copy(&mut entry, &mut hasher)?;
+    if size != expected.size || hex::encode(hasher.finalize()) != expected.sha256 {
+        return Err(anyhow!("Archive is corrupt: checksum mismatch for {}", expected.name));
+    }
+    Ok(())
+}
+
+/// Merges an archive from [`export_to`] into this library. Audio files
+/// are re-encrypted into the recordings folder and their records point at
+/// the new location. Recordings already present, judged by creation time
+/// and audio content, are skipped, as are triggers and memories that
+/// already exist, so importing the same archive twice is harmless.
+pub fn import_from(state: &AppState, archive_path: &Path, passphrase: &str) -> Result<ImportReport> {
+    let mut zip = ZipArchive::new(File::open(archive_path)
+        .with_context(|| format!("Failed to open {}", archive_path.display()))?)?;
+    let manifest: Manifest = serde_json::from_slice(&read_entry(&mut zip, MANIFEST_ENTRY)?)
+        .context("Archive manifest is unreadable")?;
+    if manifest.format_version > ARCHIVE_FORMAT_VERSION {
+        return Err(anyhow!("Archive was written by a newer version of Dwight ({})", manifest.app_version));
+    }
+
+    verify_entry(&mut zip, &manifest.database)?;
+    for file in &manifest.files {
+        verify_entry(&mut zip, &file.entry)?;
+    }
+
+    let archive_key = vault::passphrase_key(passphrase, &manifest.kdf)?;
+    if vault::key_check(&archive_key) != manifest.key_check {
+        return Err(anyhow!("Incorrect archive passphrase"));
+    }
+
+    let scratch = ScratchDir::new("import")?;
+    let snapshot_path = scratch.0.join(DATABASE_ENTRY);
+    std::fs::write(&snapshot_path, read_entry(&mut zip, &manifest.database.name)?)?;
+    let pool = Database::open_snapshot(&snapshot_path, &archive_key)?;
+    let snapshot = Database::from_pool(&pool)?;
+
+    let db = state.db().map_err(|e| anyhow!(e))?;
+    let recordings_dir = audio_capture::recordings_dir(state.app_config());
+    std::fs::create_dir_all(&recordings_dir)?;
+
+    let files: HashMap<i64, &ArchivedFile> = manifest.files.iter().map(|f| (f.record_id, f)).collect();
+    let mut local_records = db.get_all_audio_records()?;
+    let mut local_hashes: HashMap<String, Option<String>> = HashMap::new();
+    let mut report = ImportReport::default();
+
+    let mut records = snapshot.get_all_audio_records()?;
+    records.reverse();
+    for mut record in records {
+        let Some(old_id) = record.id.map(|id| id as i64) else { continue };
+        let file = files.get(&old_id).copied();
+
+        let duplicate = local_records.iter().filter(|local| local.created_at == record.created_at).any(|local| {
+            match file {
+                Some(file) => {
+                    let hash = local_hashes.entry(local.file_path.clone()).or_insert_with(|| {
+                        vault::read_file(Path::new(&local.file_path)).ok().map(|audio| sha256_hex(&audio))
+                    });
+                    hash.as_deref() == Some(file.content_sha256.as_str())
+                }
+                None => local.title == record.title && (local.duration - record.duration).abs() < 0.01,
+            }
+        });
+        if duplicate {
+            report.duplicates_skipped.push(record.title.clone());
+            continue;
+        }
+
+        let mut written = None;
+        match file {
+            Some(file) => {
+                let audio = vault::open_file_bytes(&archive_key, &read_entry(&mut zip, &file.entry.name)?)
+                    .with_context(|| format!("Failed to decrypt {}", file.entry.name))?;
+                let path = unique_path(&recordings_dir, &file_name_of(&file.original_path));
+                vault::write_file(&path, &audio)?;
+                local_hashes.insert(path.to_string_lossy().to_string(), Some(file.content_sha256.clone()));
+                record.file_path = path.to_string_lossy().to_string();
+                written = Some(path);
+            }
+            None => {
+                // The path belongs to the exporting machine; keeping it would
+                // point this record at whatever happens to live there here
+                report.missing_files.push(std::mem::take(&mut record.file_path));
+            }
+        }
+
+        match import_record(&db, &snapshot, &record, old_id) {
+            Ok(new_id) => {
+                record.id = Some(new_id as i32);
+                local_records.push(record);
+                report.records_imported += 1;
+            }
+            Err(e) => {
+                if let Some(path) = written {
+                    let _ = std::fs::remove_file(path);
+                }
+                report.errors.push(format!("{}: {}", record.title, e));
+            }
+        }
+    }
+
+    let local_triggers = db.get_all_triggers()?;
+    for trigger in snapshot.get_all_triggers()? {
+        let exists = local_triggers.iter().any(|local| {
+            local.trigger_type == trigger.trigger_type
+                && local.trigger_value.eq_ignore_ascii_case(&trigger.trigger_value)
+        });
+        if !exists {
+            match db.save_trigger(&trigger) {
+                Ok(_) => report.triggers_imported += 1,
+                Err(e) => report.errors.push(format!("Trigger '{}': {}", trigger.trigger_value, e)),
+            }
+        }
+    }
+
+    let local_memories = db.get_dwight_memory_context(i64::MAX as usize)?;
+    for memory in snapshot.get_dwight_memory_context(i64::MAX as usize)? {
+        let exists = local_memories
+            .iter()
+            .any(|local| local.created_at == memory.created_at && local.user_input == memory.user_input);
+        if !exists {
+            db.import_dwight_memory(&memory)?;
+            report.memories_imported += 1;
+        }
+    }
+
+    state.request_index_update();
+    Ok(report)
+}
+
+/// Copies one record with its segments and events, returning its new id.
+fn import_record(db: &Database, snapshot: &Database, record: &AudioRecord, old_id: i64) -> Result<i64> {
+    let new_id = db.import_audio_record(record)?;
+
+    let segments: Vec<TranscriptionSegment> = snapshot
+        .get_segments(Some(old_id), None, None)?
+        .into_iter()
+        .map(|s| TranscriptionSegment { start: s.start, end: s.end, text: s.text, confidence: s.confidence as f32 })
+        .collect();
+    if !segments.is_empty() {
+        db.save_transcription(new_id, record.transcript.as_deref().unwrap_or(""), &segments)?;
+    }
+
+    let events: Vec<NonSpeechEvent> = snapshot
+        .get_audio_events(Some(old_id), None, None, None)?
+        .into_iter()
+        .map(|e| NonSpeechEvent { start: e.start, end: e.end, event_type: e.event_type, confidence: e.confidence as f32 })
+        .collect();
+    if !events.is_empty() {
+        db.save_audio_events(new_id, &events)?;
+    }
+
+    Ok(new_id)
+}
+
+/// Exports the library to `destination` (a `.zip` path), encrypted with
+/// `passphrase`.
+#[command]
+pub async fn export_library(destination: String, passphrase: String, app_handle: AppHandle) -> Result<ExportReport, String> {
+    tokio::task::spawn_blocking(move || {
+        let passphrase = zeroize::Zeroizing::new(passphrase);
+        let state = app_handle.state::<AppState>();
+        export_to(&state, Path::new(&destination), &passphrase).map_err(|e| format!("Export error: {:#}", e))
+    })
+    .await
+    .map_err(|e| format!("Export error: {}", e))?
+}
+
+/// Restores or merges a library archive into this one.
+#[command]
+pub async fn import_library(archive_path: String, passphrase: String, app_handle: AppHandle) -> Result<ImportReport, String> {
+    tokio::task::spawn_blocking(move || {
+        let passphrase = zeroize::Zeroizing::new(passphrase);
+        let state = app_handle.state::<AppState>();
+        import_from(&state, Path::new(&archive_path), &passphrase).map_err(|e| format!("Import error: {:#}", e))
+    })
+    .await
+    .map_err(|e| format!("Import error: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn partial_files_are_removed_unless_persisted() {
+        let dir = std::env::temp_dir().join(format!("dwight-partial-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let destination = dir.join("library.zip");
+        let partial_path = dir.join("library.partial");
+
+        let failed = || -> Result<()> {
+            let (_partial, mut file) = PartialFile::create(&destination)?;
+            file.write_all(b"half an archive")?;
+            assert!(partial_path.is_file());
+            Err(anyhow!("disk full"))
+        };
+        assert!(failed().is_err());
+        assert!(!partial_path.exists());
+        assert!(!destination.exists());
+
+        let (partial, mut file) = PartialFile::create(&destination).unwrap();
+        file.write_all(b"a whole archive").unwrap();
+        drop(file);
+        partial.persist().unwrap();
+        assert!(!partial_path.exists());
+        assert_eq!(std::fs::read(&destination).unwrap(), b"a whole archive");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
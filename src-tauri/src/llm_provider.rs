@@ -0,0 +1,371 @@
+use serde::{Deserialize, Serialize};
+use anyhow::{anyhow, Result};
+
+/// The API a model is served through.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProviderKind {
+    /// Ollama `/api/generate`, a single prompt
+    #[default]
+    OllamaGenerate,
+    /// Ollama `/api/chat`, a list of messages
+    OllamaChat,
+    /// `/v1/chat/completions` as served by llama.cpp, LM Studio, vLLM and others
+    OpenAiChat,
+    /// Deterministic canned output for development and tests; never touches the network
+    Stub,
+}
+
+impl ProviderKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProviderKind::OllamaGenerate => "ollama_generate",
+            ProviderKind::OllamaChat => "ollama_chat",
+            ProviderKind::OpenAiChat => "openai_chat",
+            ProviderKind::Stub => "stub",
+        }
+    }
+}
+
+/// Token counts and timings as reported by the server.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Usage {
+    pub prompt_tokens: Option<u32>,
+    pub completion_tokens: Option<u32>,
+    /// Ollama only: time spent loading the model, reading the prompt and generating
+    pub load_ms: Option<u64>,
+    pub prompt_eval_ms: Option<u64>,
+    pub eval_ms: Option<u64>,
+}
+
+impl Usage {
+    /// Generation speed, from the server's own timing when it gives one.
+    pub fn tokens_per_second(&self) -> Option<f32> {
+        let tokens = self.completion_tokens? as f32;
+        let eval_ms = self.eval_ms.filter(|&ms| ms > 0)?;
+        Some(tokens * 1000.0 / eval_ms as f32)
+    }
+}
+
+/// One parsed line of a streamed response. Lines such as keep-alives and
+/// role announcements have neither text nor `done`.
+#[derive(Debug, Default, PartialEq)]
+pub struct StreamChunk {
+    pub token: Option<String>,
+    pub done: bool,
+    pub usage: Option<Usage>,
+}
+
+/// Sampling settings and system prompt a model is run with unless a request
+/// says otherwise.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ModelDefaults {
+    pub temperature: f32,
+    pub top_p: f32,
+    /// Longest answer, in tokens
+    pub max_tokens: u32,
+    /// Context window in tokens; the server's own default when unset. Only
+    /// Ollama accepts this per request.
+    pub context_length: Option<u32>,
+    pub system_prompt: Option<String>,
+}
+
+impl Default for ModelDefaults {
+    fn default() -> Self {
+        ModelDefaults {
+            temperature: 0.7,
+            top_p: 0.9,
+            max_tokens: 512,
+            context_length: None,
+            system_prompt: None,
+        }
+    }
+}
+
+/// Per-request generation settings. Anything left unset comes from the
+/// model's [`ModelDefaults`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GenerationOptions {
+    pub temperature: Option<f32>,
+    pub top_p: Option<f32>,
+    pub max_tokens: Option<u32>,
+    pub context_length: Option<u32>,
+    pub system_prompt: Option<String>,
+    /// Fixed seed for reproducible sampling
+    pub seed: Option<i64>,
+    /// Generation stops before any of these strings
+    pub stop: Option<Vec<String>>,
+}
+
+impl GenerationOptions {
+    /// These options with gaps filled from `defaults`.
+    pub fn or_defaults(&self, defaults: &ModelDefaults) -> GenerationOptions {
+        GenerationOptions {
+            temperature: self.temperature.or(Some(defaults.temperature)),
+            top_p: self.top_p.or(Some(defaults.top_p)),
+            max_tokens: self.max_tokens.or(Some(defaults.max_tokens)),
+            context_length: self.context_length.or(defaults.context_length),
+            system_prompt: self.system_prompt.clone().or_else(|| defaults.system_prompt.clone()),
+            seed: self.seed,
+            stop: self.stop.clone(),
+        }
+    }
+
+    pub fn validate(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        if self.temperature.is_some_and(|t| !(0.0..=2.0).contains(&t)) {
+            errors.push("Temperature must be between 0 and 2".to_string());
+        }
+        if self.top_p.is_some_and(|p| !(p > 0.0 && p <= 1.0)) {
+            errors.push("top_p must be above 0 and at most 1".to_string());
+        }
+        if self.max_tokens == Some(0) {
+            errors.push("Maximum tokens must be at least 1".to_string());
+        }
+        if self.context_length == Some(0) {
+            errors.push("Context length must be at least one token".to_string());
+        }
+        errors
+    }
+}
+
+/// Request body for `prompt`, sent as a single user message to chat APIs.
+/// `options` should already have the model's defaults applied.
+pub fn request_body(kind: ProviderKind, model: &str, prompt: &str, stream: bool, options: &GenerationOptions) -> serde_json::Value {
+    let system_prompt = options.system_prompt.as_deref().filter(|s| !s.trim().is_empty());
+    let mut messages = Vec::new();
+    if let Some(system) = system_prompt {
+        messages.push(serde_json::json!({ "role": "system", "content": system }));
+    }
+    messages.push(serde_json::json!({ "role": "user", "content": prompt }));
+
+    // Ollama names these differently from OpenAI and ignores what it doesn't know
+    let mut ollama_options = serde_json::Map::new();
+    let mut set = |key: &str, value: Option<serde_json::Value>| {
+        if let Some(value) = value {
+            ollama_options.insert(key.to_string(), value);
+        }
+    };
+    set("temperature", options.temperature.map(Into::into));
+    set("top_p", options.top_p.map(Into::into));
+    set("num_predict", options.max_tokens.map(Into::into));
+    set("num_ctx", options.context_length.map(Into::into));
+    set("seed", options.seed.map(Into::into));
+    set("stop", options.stop.clone().map(Into::into));
+
+    let mut body = match kind {
+        ProviderKind::OllamaGenerate | ProviderKind::Stub => serde_json::json!({
+            "model": model,
+            "prompt": prompt,
+            "stream": stream,
+            "options": ollama_options,
+        }),
+        ProviderKind::OllamaChat => serde_json::json!({
+            "model": model,
+            "messages": messages,
+            "stream": stream,
+            "options": ollama_options,
+        }),
+        ProviderKind::OpenAiChat => {
+            let mut body = serde_json::json!({
+                "model": model,
+                "messages": messages,
+                "stream": stream,
+            });
+            let mut set = |key: &str, value: Option<serde_json::Value>| {
+                if let Some(value) = value {
+                    body[key] = value;
+                }
+            };
+            set("temperature", options.temperature.map(Into::into));
+            set("top_p", options.top_p.map(Into::into));
+            set("max_tokens", options.max_tokens.map(Into::into));
+            set("seed", options.seed.map(Into::into));
+            set("stop", options.stop.clone().map(Into::into));
+            if stream {
+                // Otherwise streamed responses carry no token counts
+                body["stream_options"] = serde_json::json!({ "include_usage": true });
+            }
+            body
+        }
+    };
+    if let (ProviderKind::OllamaGenerate | ProviderKind::Stub, Some(system)) = (kind, system_prompt) {
+        body["system"] = system.into();
+    }
+    body
+}
+
+fn error_message(body: &serde_json::Value) -> Option<String> {
+    match &body["error"] {
+        serde_json::Value::String(message) => Some(message.clone()),
+        // OpenAI-style `{"error": {"message": ...}}`
+        serde_json::Value::Object(error) => Some(
+            error.get("message").and_then(|m| m.as_str()).unwrap_or("unknown error").to_string(),
+        ),
+        _ => None,
+    }
+}
+
+fn nanos_to_ms(value: &serde_json::Value) -> Option<u64> {
+    value.as_u64().map(|ns| ns / 1_000_000)
+}
+
+/// Token counts from a complete response or the last streamed line, if the
+/// server included them.
+pub fn usage(kind: ProviderKind, body: &serde_json::Value) -> Option<Usage> {
+    let count = |value: &serde_json::Value| value.as_u64().map(|n| n as u32);
+    let usage = match kind {
+        ProviderKind::OpenAiChat => Usage {
+            prompt_tokens: count(&body["usage"]["prompt_tokens"]),
+            completion_tokens: count(&body["usage"]["completion_tokens"]),
+            ..Default::default()
+        },
+        // Durations are in nanoseconds
+        _ => Usage {
+            prompt_tokens: count(&body["prompt_eval_count"]),
+            completion_tokens: count(&body["eval_count"]),
+            load_ms: nanos_to_ms(&body["load_duration"]),
+            prompt_eval_ms: nanos_to_ms(&body["prompt_eval_duration"]),
+            eval_ms: nanos_to_ms(&body["eval_duration"]),
+        },
+    };
+    Some(usage).filter(|u| *u != Usage::default())
+}
+
+/// The answer text of a non-streamed response.
+pub fn response_text(kind: ProviderKind, body: &serde_json::Value) -> Result<String> {
+    if let Some(error) = error_message(body) {
+        return Err(anyhow!("Model error: {}", error));
+    }
+    let text = match kind {
+        ProviderKind::OllamaGenerate | ProviderKind::Stub => body["response"].as_str(),
+        ProviderKind::OllamaChat => body["message"]["content"].as_str(),
+        ProviderKind::OpenAiChat => body["choices"][0]["message"]["content"].as_str(),
+    };
+    text.map(str::to_string)
+        .ok_or_else(|| anyhow!("Unexpected response from {} server", kind.as_str()))
+}
+
+/// Parses one line of a streamed response. Ollama streams NDJSON; OpenAI
+/// servers stream server-sent events (`data: {...}`, ending `data: [DONE]`).
+pub fn parse_stream_line(kind: ProviderKind, line: &str) -> Result<StreamChunk> {
+    let line = line.trim();
+    let json = match kind {
+        ProviderKind::OpenAiChat => match line.strip_prefix("data:").map(str::trim) {
+            Some("[DONE]") => return Ok(StreamChunk { done: true, ..Default::default() }),
+            Some(data) => data,
+            // Blank separators, `event:` and `:` comment lines
+            None => return Ok(StreamChunk::default()),
+        },
+        _ if line.is_empty() => return Ok(StreamChunk::default()),
+        _ => line,
+    };
+
+    let message: serde_json::Value = serde_json::from_str(json)?;
+    if let Some(error) = error_message(&message) {
+        return Err(anyhow!("Model error: {}", error));
+    }
+    let (token, done) = match kind {
+        ProviderKind::OllamaGenerate | ProviderKind::Stub => {
+            (message["response"].as_str(), message["done"].as_bool().unwrap_or(false))
+        }
+        ProviderKind::OllamaChat => {
+            (message["message"]["content"].as_str(), message["done"].as_bool().unwrap_or(false))
+        }
+        // The final chunk carries a finish_reason, then usage if asked for; `[DONE]` follows
+        ProviderKind::OpenAiChat => (message["choices"][0]["delta"]["content"].as_str(), false),
+    };
+    Ok(StreamChunk {
+        token: token.filter(|t| !t.is_empty()).map(str::to_string),
+        done,
+        usage: usage(kind, &message),
+    })
+}
+
+/// Where to list the models a server has, given the endpoint requests go
+/// to: Ollama's `/api/tags` or OpenAI's `/v1/models`.
+pub fn models_url(kind: ProviderKind, endpoint: &str) -> Option<String> {
+    let endpoint = endpoint.trim_end_matches('/');
+    match kind {
+        ProviderKind::OllamaGenerate | ProviderKind::OllamaChat => {
+            let base = endpoint.find("/api/").map_or(endpoint, |i| &endpoint[..i]);
+            Some(format!("{}/api/tags", base))
+        }
+        ProviderKind::OpenAiChat => {
+            let base = endpoint.find("/chat/completions").map_or(endpoint, |i| &endpoint[..i]);
+            Some(format!("{}/models", base))
+        }
+        ProviderKind::Stub => None,
+    }
+}
+
+/// Model names from a [`models_url`] response.
+pub fn installed_models(kind: ProviderKind, body: &serde_json::Value) -> Vec<String> {
+    let (list, key) = match kind {
+        ProviderKind::OpenAiChat => (&body["data"], "id"),
+        _ => (&body["models"], "name"),
+    };
+    list.as_array()
+        .map(|models| models.iter().filter_map(|m| m[key].as_str().map(str::to_string)).collect())
+        .unwrap_or_default()
+}
+
+/// Canned reply for the stub provider, echoing the prompt so callers can
+/// check what was sent.
+pub fn stub_reply(model: &str, prompt: &str) -> String {
+    let prompt: String = prompt.split_whitespace().take(12).collect::<Vec<_>>().join(" ");
+    format!("[stub {}] This is a canned response for testing. You said: {}", model, prompt)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token(kind: ProviderKind, line: &str) -> Option<String> {
+        parse_stream_line(kind, line).unwrap().token
+    }
+
+    #[test]
+    fn parses_ollama_stream_lines() {
+        for (kind, line) in [
+            (ProviderKind::OllamaGenerate, r#"{"response":"Bears","done":false}"#),
+            (ProviderKind::OllamaChat, r#"{"message":{"role":"assistant","content":"Bears"},"done":false}"#),
+        ] {
+            assert_eq!(token(kind, line).as_deref(), Some("Bears"));
+            assert_eq!(parse_stream_line(kind, "  \r\n").unwrap(), StreamChunk::default());
+        }
+
+        let last = parse_stream_line(
+            ProviderKind::OllamaGenerate,
+            r#"{"response":"","done":true,"prompt_eval_count":5,"eval_count":4,"eval_duration":2000000000}"#,
+        )
+        .unwrap();
+        assert!(last.done);
+        assert_eq!(last.token, None);
+        assert_eq!(last.usage.unwrap().tokens_per_second(), Some(2.0));
+    }
+
+    #[test]
+    fn parses_openai_server_sent_events() {
+        let kind = ProviderKind::OpenAiChat;
+        assert_eq!(token(kind, r#"data: {"choices":[{"delta":{"content":"Bears"}}]}"#).as_deref(), Some("Bears"));
+        for ignored in ["", ": keep-alive", "event: message", r#"data: {"choices":[{"delta":{"role":"assistant"}}]}"#] {
+            assert_eq!(parse_stream_line(kind, ignored).unwrap(), StreamChunk::default(), "{}", ignored);
+        }
+        let usage = parse_stream_line(kind, r#"data: {"choices":[],"usage":{"prompt_tokens":5,"completion_tokens":4}}"#)
+            .unwrap()
+            .usage
+            .unwrap();
+        assert_eq!((usage.prompt_tokens, usage.completion_tokens), (Some(5), Some(4)));
+        assert!(parse_stream_line(kind, "data: [DONE]").unwrap().done);
+    }
+
+    #[test]
+    fn stream_errors_are_reported() {
+        assert!(parse_stream_line(ProviderKind::OllamaGenerate, r#"{"error":"model 'llama3' not found"}"#).is_err());
+        assert!(parse_stream_line(ProviderKind::OpenAiChat, r#"data: {"error":{"message":"overloaded"}}"#).is_err());
+        assert!(parse_stream_line(ProviderKind::OllamaChat, "{not json").is_err());
+    }
+}
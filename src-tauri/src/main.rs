@@ -6,20 +6,46 @@
 use tauri::{Manager, WindowEvent};
 
 mod whisper;
+mod whisper_candle;
+mod transcription;
 mod database;
+mod migrations;
 mod ai;
 mod ai_models;
+mod llm_provider;
+mod model_registry;
 mod python_integration;
+mod audio_capture;
+mod audio_analysis;
+mod audio_decode;
+mod resample;
+mod preprocess;
+mod vad;
+mod trigger_engine;
+mod state;
+mod vault;
+mod retention;
+mod library;
+mod integrity;
+mod evidence_bundle;
+mod evidence;
+mod embeddings;
+mod rag;
 
 fn main() {
     tauri::Builder::default()
+        .manage(audio_capture::AudioCapture::default())
+        .manage(trigger_engine::TriggerEngine::default())
         .setup(|app| {
-            // Initialize database on startup
-            let config = app.config();
-            match database::Database::new(&config) {
-                Ok(_) => println!("Database initialized successfully"),
-                Err(e) => eprintln!("Failed to initialize database: {}", e),
-            }
+            // Open the database pool and load engines once for all commands
+            let state = state::AppState::new(app.config()).map_err(|e| {
+                eprintln!("Failed to initialize database: {}", e);
+                e
+            })?;
+            println!("Database initialized successfully");
+            app.manage(state);
+            retention::spawn_background_task(app.handle());
+            rag::spawn_background_indexer(app.handle());
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
@@ -30,14 +56,34 @@ fn main() {
             whisper::configure_whisper,
             whisper::get_whisper_status,
             
+            // Rolling audio capture (DVR mode)
+            audio_capture::start_audio_capture,
+            audio_capture::stop_audio_capture,
+            audio_capture::get_capture_status,
+            audio_capture::save_audio_clip,
+            resample::convert_audio_for_whisper,
+            
+            // Live trigger evaluation
+            trigger_engine::start_trigger_engine,
+            trigger_engine::stop_trigger_engine,
+            trigger_engine::get_trigger_engine_status,
+            
             // Original AI chat
             ai::chat_with_dwight,
             ai::analyze_audio_intelligence,
             
             // Advanced AI models
             ai_models::chat_with_llama,
+            ai_models::stream_llama,
+            ai_models::cancel_llm_stream,
             ai_models::rag_search,
             ai_models::get_ai_models,
+            model_registry::add_ai_model,
+            model_registry::update_ai_model,
+            model_registry::remove_ai_model,
+            model_registry::set_ai_model_enabled,
+            model_registry::reset_ai_models,
+            model_registry::probe_ai_models,
             ai_models::enhanced_dwight_chat,
             ai_models::ai_audio_analysis,
             
@@ -45,13 +91,58 @@ fn main() {
             python_integration::execute_python_script,
             python_integration::get_python_scripts,
             python_integration::python_audio_preprocessing,
+            python_integration::native_audio_preprocessing,
             python_integration::python_ml_classification,
             
+            // Encrypted storage
+            vault::get_storage_status,
+            vault::unlock_storage,
+            vault::lock_storage,
+            vault::change_passphrase,
+            
+            // Retention and storage cleanup
+            retention::get_retention_policy,
+            retention::set_retention_policy,
+            retention::preview_retention,
+            retention::run_retention_now,
+            
+            // Library backup and transfer
+            library::export_library,
+            library::import_library,
+            
             // Database operations
             database_commands::save_audio_record,
             database_commands::get_audio_records,
+            database_commands::list_audio_records,
+            database_commands::get_audio_record,
+            database_commands::update_audio_record,
+            database_commands::delete_audio_record,
+            database_commands::set_record_starred,
             database_commands::save_trigger,
-            database_commands::get_triggers
+            database_commands::get_triggers,
+            database_commands::update_trigger,
+            database_commands::set_trigger_active,
+            database_commands::delete_trigger,
+            
+            // Transcript search
+            database_commands::search_transcripts,
+            
+            // Stored segments and analysis events
+            database_commands::get_segments,
+            database_commands::get_audio_events,
+            
+            // Chain of custody
+            integrity::verify_record_integrity,
+            integrity::get_audit_log,
+            
+            // Signed evidence bundles
+            evidence::export_evidence_bundle,
+            evidence::verify_evidence_bundle,
+            evidence::get_evidence_public_key,
+            
+            // Retrieval over recordings and memories
+            rag::update_rag_index,
+            rag::get_rag_status
         ])
         .on_window_event(|event| {
             if let WindowEvent::CloseRequested { .. } = event.event() {
@@ -63,8 +154,11 @@ fn main() {
 }
 
 mod database_commands {
-    use tauri::command;
-    use crate::database::{Database, AudioRecord, SoundTrigger};
+    use std::path::Path;
+    use tauri::{command, State};
+    use crate::integrity;
+    use crate::database::{AudioEvent, AudioRecord, RecordFilter, RecordPage, SearchHit, SoundTrigger, StoredSegment};
+    use crate::state::AppState;
 
     #[command]
     pub async fn save_audio_record(
@@ -73,10 +167,12 @@ mod database_commands {
         transcript: Option<String>,
         duration: f64,
         triggers: Option<String>,
-        app_handle: tauri::AppHandle,
+        tags: Option<Vec<String>>,
+        state: State<'_, AppState>,
     ) -> Result<i64, String> {
-        let config = app_handle.config();
-        let db = Database::new(&config).map_err(|e| format!("Database error: {}", e))?;
+        let db = state.db()?;
+        // Files the user brings in are hashed as they are when first recorded
+        let sha256 = integrity::hash_file(Path::new(&file_path)).ok();
         
         let record = AudioRecord {
             id: None,
@@ -86,27 +182,97 @@ mod database_commands {
             duration,
             created_at: String::new(),
             triggers,
+            tags: tags.unwrap_or_default(),
+            starred: false,
+            sha256,
+            capture_device: None,
+            captured_at: None,
         };
         
         db.save_audio_record(&record).map_err(|e| format!("Database error: {}", e))
     }
 
     #[command]
-    pub async fn get_audio_records(app_handle: tauri::AppHandle) -> Result<Vec<AudioRecord>, String> {
-        let config = app_handle.config();
-        let db = Database::new(&config).map_err(|e| format!("Database error: {}", e))?;
+    pub async fn get_audio_records(state: State<'_, AppState>) -> Result<Vec<AudioRecord>, String> {
+        let db = state.db()?;
         
         db.get_all_audio_records().map_err(|e| format!("Database error: {}", e))
     }
 
+    #[command]
+    pub async fn list_audio_records(filter: Option<RecordFilter>, state: State<'_, AppState>) -> Result<RecordPage, String> {
+        let db = state.db()?;
+        
+        db.list_audio_records(&filter.unwrap_or_default()).map_err(|e| format!("Database error: {}", e))
+    }
+
+    #[command]
+    pub async fn get_audio_record(id: i64, state: State<'_, AppState>) -> Result<AudioRecord, String> {
+        let db = state.db()?;
+        
+        let record = db.get_audio_record(id)
+            .map_err(|e| format!("Database error: {}", e))?
+            .ok_or_else(|| format!("Audio record {} not found", id))?;
+        db.log_access(Some(id), "viewed", "").map_err(|e| format!("Database error: {}", e))?;
+        Ok(record)
+    }
+
+    /// Renames and/or retags a record; fields left out are unchanged.
+    #[command]
+    pub async fn update_audio_record(
+        id: i64,
+        title: Option<String>,
+        tags: Option<Vec<String>>,
+        state: State<'_, AppState>,
+    ) -> Result<AudioRecord, String> {
+        let db = state.db()?;
+        
+        if let Some(title) = &title {
+            if title.trim().is_empty() {
+                return Err("Title cannot be empty".to_string());
+            }
+        }
+        let found = db.update_audio_record(id, title.as_deref().map(str::trim), tags.as_deref())
+            .map_err(|e| format!("Database error: {}", e))?;
+        if !found {
+            return Err(format!("Audio record {} not found", id));
+        }
+        db.get_audio_record(id)
+            .map_err(|e| format!("Database error: {}", e))?
+            .ok_or_else(|| format!("Audio record {} not found", id))
+    }
+
+    /// Deletes a record, its stored segments and events, and its audio file
+    /// if the app stored it; imported files elsewhere on disk are kept.
+    #[command]
+    pub async fn delete_audio_record(id: i64, state: State<'_, AppState>) -> Result<(), String> {
+        let db = state.db()?;
+        
+        let record = db.get_audio_record(id)
+            .map_err(|e| format!("Database error: {}", e))?
+            .ok_or_else(|| format!("Audio record {} not found", id))?;
+        
+        let recordings_dir = crate::audio_capture::recordings_dir(state.app_config());
+        db.delete_audio_record_and_file(&record, &recordings_dir)
+            .map_err(|e| format!("Database error: {}", e))
+    }
+
+    /// Starred records can be kept by the retention policy.
+    #[command]
+    pub async fn set_record_starred(id: i64, starred: bool, state: State<'_, AppState>) -> Result<(), String> {
+        let db = state.db()?;
+        
+        let found = db.set_record_starred(id, starred).map_err(|e| format!("Database error: {}", e))?;
+        if found { Ok(()) } else { Err(format!("Audio record {} not found", id)) }
+    }
+
     #[command]
     pub async fn save_trigger(
         trigger_type: String,
         trigger_value: String,
-        app_handle: tauri::AppHandle,
+        state: State<'_, AppState>,
     ) -> Result<i64, String> {
-        let config = app_handle.config();
-        let db = Database::new(&config).map_err(|e| format!("Database error: {}", e))?;
+        let db = state.db()?;
 
         let trigger = SoundTrigger {
             id: None,
@@ -116,14 +282,93 @@ mod database_commands {
             created_at: String::new(),
         };
         
-        db.save_trigger(&trigger).map_err(|e| format!("Database error: {}", e))
+        db.save_trigger(&trigger).map_err(|e| format!("Trigger error: {}", e))
+    }
+
+    /// Active triggers, or every trigger when `include_inactive` is set.
+    #[command]
+    pub async fn get_triggers(include_inactive: Option<bool>, state: State<'_, AppState>) -> Result<Vec<SoundTrigger>, String> {
+        let db = state.db()?;
+        
+        if include_inactive.unwrap_or(false) {
+            db.get_all_triggers().map_err(|e| format!("Database error: {}", e))
+        } else {
+            db.get_active_triggers().map_err(|e| format!("Database error: {}", e))
+        }
     }
 
     #[command]
-    pub async fn get_triggers(app_handle: tauri::AppHandle) -> Result<Vec<SoundTrigger>, String> {
-        let config = app_handle.config();
-        let db = Database::new(&config).map_err(|e| format!("Database error: {}", e))?;
+    pub async fn update_trigger(
+        id: i64,
+        trigger_type: Option<String>,
+        trigger_value: Option<String>,
+        state: State<'_, AppState>,
+    ) -> Result<(), String> {
+        let db = state.db()?;
         
-        db.get_active_triggers().map_err(|e| format!("Database error: {}", e))
+        if trigger_value.as_deref().is_some_and(|v| v.trim().is_empty()) {
+            return Err("Trigger value cannot be empty".to_string());
+        }
+        let found = db.update_trigger(id, trigger_type.as_deref(), trigger_value.as_deref().map(str::trim), None)
+            .map_err(|e| format!("Trigger error: {}", e))?;
+        if found { Ok(()) } else { Err(format!("Trigger {} not found", id)) }
     }
-}
\ No newline at end of file
+
+    #[command]
+    pub async fn set_trigger_active(id: i64, active: bool, state: State<'_, AppState>) -> Result<(), String> {
+        let db = state.db()?;
+        
+        let found = db.update_trigger(id, None, None, Some(active))
+            .map_err(|e| format!("Trigger error: {}", e))?;
+        if found { Ok(()) } else { Err(format!("Trigger {} not found", id)) }
+    }
+
+    #[command]
+    pub async fn delete_trigger(id: i64, state: State<'_, AppState>) -> Result<(), String> {
+        let db = state.db()?;
+        
+        let found = db.delete_trigger(id).map_err(|e| format!("Database error: {}", e))?;
+        if found { Ok(()) } else { Err(format!("Trigger {} not found", id)) }
+    }
+
+    #[command]
+    pub async fn search_transcripts(
+        query: String,
+        limit: Option<u32>,
+        state: State<'_, AppState>,
+    ) -> Result<Vec<SearchHit>, String> {
+        let db = state.db()?;
+        let limit = limit.unwrap_or(20).clamp(1, 200) as usize;
+        
+        db.search_transcripts(&query, limit).map_err(|e| format!("Search error: {}", e))
+    }
+
+    /// Transcript segments, optionally for one record and overlapping a time
+    /// range in seconds from the start of the recording.
+    #[command]
+    pub async fn get_segments(
+        record_id: Option<i64>,
+        start: Option<f64>,
+        end: Option<f64>,
+        state: State<'_, AppState>,
+    ) -> Result<Vec<StoredSegment>, String> {
+        let db = state.db()?;
+        
+        db.get_segments(record_id, start, end).map_err(|e| format!("Database error: {}", e))
+    }
+
+    /// Analysis events filtered by record, event type and time range.
+    #[command]
+    pub async fn get_audio_events(
+        record_id: Option<i64>,
+        event_type: Option<String>,
+        start: Option<f64>,
+        end: Option<f64>,
+        state: State<'_, AppState>,
+    ) -> Result<Vec<AudioEvent>, String> {
+        let db = state.db()?;
+        
+        db.get_audio_events(record_id, event_type.as_deref(), start, end)
+            .map_err(|e| format!("Database error: {}", e))
+    }
+}
@@ -0,0 +1,298 @@
+use rusqlite::{params, Connection, OptionalExtension};
+use std::path::{Path, PathBuf};
+use anyhow::{anyhow, Context, Result};
+
+pub struct Migration {
+    pub version: i64,
+    pub name: &'static str,
+    pub sql: &'static str,
+}
+
+/// Every schema change, oldest first. Versions must be contiguous from 1;
+/// never edit a migration once it has shipped, add a new one instead.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "initial",
+        sql: include_str!("migrations/0001_initial.sql"),
+    },
+    Migration {
+        version: 2,
+        name: "trigger_active_flags",
+        sql: include_str!("migrations/0002_trigger_active_flags.sql"),
+    },
+    Migration {
+        version: 3,
+        name: "transcript_search",
+        sql: include_str!("migrations/0003_transcript_search.sql"),
+    },
+    Migration {
+        version: 4,
+        name: "audio_events",
+        sql: include_str!("migrations/0004_audio_events.sql"),
+    },
+    Migration {
+        version: 5,
+        name: "record_tags",
+        sql: include_str!("migrations/0005_record_tags.sql"),
+    },
+    Migration {
+        version: 6,
+        name: "record_starred",
+        sql: include_str!("migrations/0006_record_starred.sql"),
+    },
+    Migration {
+        version: 7,
+        name: "chain_of_custody",
+        sql: include_str!("migrations/0007_chain_of_custody.sql"),
+    },
+    Migration {
+        version: 8,
+        name: "embeddings",
+        sql: include_str!("migrations/0008_embeddings.sql"),
+    },
+];
+
+pub fn latest_version() -> i64 {
+    MIGRATIONS.last().map(|m| m.version).unwrap_or(0)
+}
+
+/// Version recorded in `schema_version`, or 0 for a database that predates it.
+pub fn current_version(conn: &Connection) -> Result<i64> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS schema_version (
+            version INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            applied_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+    let version: Option<i64> = conn
+        .query_row("SELECT MAX(version) FROM schema_version", [], |row| row.get(0))
+        .optional()?
+        .flatten();
+    Ok(version.unwrap_or(0))
+}
+
+fn has_user_tables(conn: &Connection) -> Result<bool> {
+    let count: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table'
+         AND name NOT LIKE 'sqlite_%' AND name != 'schema_version'",
+        [],
+        |row| row.get(0),
+    )?;
+    Ok(count > 0)
+}
+
+/// Copies the database file next to it after folding the WAL back in, so the
+/// copy is consistent and stays encrypted with the same key. Returns its path.
+pub fn backup(conn: &Connection, db_path: &Path, from_version: i64) -> Result<PathBuf> {
+    let file_name = db_path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("dwight.db");
+    let backup_path = db_path.with_file_name(format!(
+        "{}.v{}-{}.bak",
+        file_name,
+        from_version,
+        chrono::Local::now().format("%Y%m%d_%H%M%S")
+    ));
+
+    let busy: i64 = conn
+        .query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |row| row.get(0))
+        .context("Failed to checkpoint database before backup")?;
+    if busy != 0 {
+        return Err(anyhow!("Database is busy; could not checkpoint it before backup"));
+    }
+    std::fs::copy(db_path, &backup_path)
+        .with_context(|| format!("Failed to back up database to {}", backup_path.display()))?;
+    Ok(backup_path)
+}
+
+/// Brings the database up to [`latest_version`], backing it up first when
+/// there is existing data. Each migration runs in its own transaction, so a
+/// failure leaves the database at the last version that applied cleanly.
+pub fn run(conn: &mut Connection, db_path: &Path) -> Result<i64> {
+    let current = current_version(conn)?;
+    let latest = latest_version();
+
+    if current > latest {
+        return Err(anyhow!(
+            "dwight.db is at schema version {} but this build only knows up to {}; \
+             it was probably written by a newer version of Dwight",
+            current,
+            latest
+        ));
+    }
+    if current == latest {
+        return Ok(current);
+    }
+
+    if has_user_tables(conn)? {
+        let backup_path = backup(conn, db_path, current)?;
+        println!("Backed up database to {} before migrating", backup_path.display());
+    }
+
+    for migration in MIGRATIONS.iter().filter(|m| m.version > current) {
+        let tx = conn.transaction()?;
+        tx.execute_batch(migration.sql)
+            .with_context(|| format!("Migration {} ({}) failed", migration.version, migration.name))?;
+        tx.execute(
+            "INSERT INTO schema_version (version, name, applied_at) VALUES (?1, ?2, ?3)",
+            params![migration.version, migration.name, chrono::Utc::now().to_rfc3339()],
+        )?;
+        tx.commit()?;
+        println!("Applied database migration {} ({})", migration.version, migration.name);
+    }
+
+    Ok(latest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Schema and data as left by releases before versioning, including the
+    /// text booleans 0002 repairs.
+    const PRE_VERSIONING_FIXTURE: &str = "
+        CREATE TABLE audio_records (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            title TEXT NOT NULL,
+            file_path TEXT NOT NULL,
+            transcript TEXT,
+            duration REAL NOT NULL,
+            created_at TEXT NOT NULL,
+            triggers TEXT
+        );
+        CREATE TABLE dwight_memory (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            context TEXT NOT NULL,
+            response TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            user_input TEXT NOT NULL
+        );
+        CREATE TABLE sound_triggers (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            trigger_type TEXT NOT NULL,
+            trigger_value TEXT NOT NULL,
+            is_active BOOLEAN NOT NULL DEFAULT 1,
+            created_at TEXT NOT NULL
+        );
+        INSERT INTO audio_records (title, file_path, transcript, duration, created_at)
+            VALUES ('Kitchen', '/tmp/kitchen.wav', 'the kettle is on', 4.5, '2024-01-01T00:00:00Z');
+        INSERT INTO sound_triggers (trigger_type, trigger_value, is_active, created_at)
+            VALUES ('speech', 'help', 'true', '2024-01-01T00:00:00Z'),
+                   ('sound', 'glass', 'false', '2024-01-01T00:00:00Z');
+    ";
+
+    /// A scratch directory holding `dwight.db`, removed when dropped.
+    struct Fixture {
+        dir: PathBuf,
+    }
+
+    impl Fixture {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!("dwight-migrations-{}-{}", std::process::id(), name));
+            let _ = std::fs::remove_dir_all(&dir);
+            std::fs::create_dir_all(&dir).unwrap();
+            Fixture { dir }
+        }
+
+        fn db_path(&self) -> PathBuf {
+            self.dir.join("dwight.db")
+        }
+
+        fn backups(&self) -> usize {
+            std::fs::read_dir(&self.dir)
+                .unwrap()
+                .filter(|entry| entry.as_ref().unwrap().file_name().to_string_lossy().ends_with(".bak"))
+                .count()
+        }
+    }
+
+    impl Drop for Fixture {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    fn table_exists(conn: &Connection, name: &str) -> bool {
+        conn.query_row("SELECT COUNT(*) FROM sqlite_master WHERE name = ?1", [name], |row| row.get::<_, i64>(0))
+            .unwrap()
+            > 0
+    }
+
+    #[test]
+    fn migrates_pre_versioning_database_to_head() {
+        let fixture = Fixture::new("head");
+        let mut conn = Connection::open(fixture.db_path()).unwrap();
+        conn.execute_batch(PRE_VERSIONING_FIXTURE).unwrap();
+        assert_eq!(current_version(&conn).unwrap(), 0);
+
+        assert_eq!(run(&mut conn, &fixture.db_path()).unwrap(), latest_version());
+        assert_eq!(current_version(&conn).unwrap(), latest_version());
+        assert_eq!(fixture.backups(), 1);
+
+        let flags: Vec<i64> = conn
+            .prepare("SELECT is_active FROM sound_triggers ORDER BY id")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        assert_eq!(flags, vec![1, 0]);
+
+        for table in ["segments", "records_fts", "segments_fts", "audio_events", "audit_log", "embeddings"] {
+            assert!(table_exists(&conn, table), "missing table {}", table);
+        }
+        let starred: bool = conn
+            .query_row("SELECT starred FROM audio_records WHERE title = 'Kitchen'", [], |row| row.get(0))
+            .unwrap();
+        assert!(!starred);
+
+        // Existing rows are searchable once 0003 backfills the index
+        let hits: i64 = conn
+            .query_row("SELECT COUNT(*) FROM records_fts WHERE records_fts MATCH 'kettle'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(hits, 1);
+    }
+
+    #[test]
+    fn rerun_at_head_is_a_no_op() {
+        let fixture = Fixture::new("rerun");
+        let mut conn = Connection::open(fixture.db_path()).unwrap();
+        conn.execute_batch(PRE_VERSIONING_FIXTURE).unwrap();
+        run(&mut conn, &fixture.db_path()).unwrap();
+
+        let applied = |conn: &Connection| -> i64 {
+            conn.query_row("SELECT COUNT(*) FROM schema_version", [], |row| row.get(0)).unwrap()
+        };
+        let before = applied(&conn);
+        assert_eq!(run(&mut conn, &fixture.db_path()).unwrap(), latest_version());
+        assert_eq!(applied(&conn), before);
+        assert_eq!(fixture.backups(), 1);
+    }
+
+    #[test]
+    fn fresh_database_is_not_backed_up() {
+        let fixture = Fixture::new("fresh");
+        let mut conn = Connection::open(fixture.db_path()).unwrap();
+        assert_eq!(run(&mut conn, &fixture.db_path()).unwrap(), latest_version());
+        assert_eq!(fixture.backups(), 0);
+    }
+
+    #[test]
+    fn rejects_database_from_newer_build() {
+        let fixture = Fixture::new("newer");
+        let mut conn = Connection::open(fixture.db_path()).unwrap();
+        run(&mut conn, &fixture.db_path()).unwrap();
+        conn.execute(
+            "INSERT INTO schema_version (version, name, applied_at) VALUES (?1, 'future', '2099-01-01T00:00:00Z')",
+            [latest_version() + 1],
+        )
+        .unwrap();
+
+        let error = run(&mut conn, &fixture.db_path()).unwrap_err();
+        assert!(error.to_string().contains("newer version"), "{}", error);
+    }
+}
@@ -0,0 +1,28 @@
+-- Tables as created by releases before schema versioning. IF NOT EXISTS
+-- lets existing installs adopt version 1 without changes.
+
+CREATE TABLE IF NOT EXISTS audio_records (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    title TEXT NOT NULL,
+    file_path TEXT NOT NULL,
+    transcript TEXT,
+    duration REAL NOT NULL,
+    created_at TEXT NOT NULL,
+    triggers TEXT
+);
+
+CREATE TABLE IF NOT EXISTS dwight_memory (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    context TEXT NOT NULL,
+    response TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    user_input TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS sound_triggers (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    trigger_type TEXT NOT NULL,
+    trigger_value TEXT NOT NULL,
+    is_active BOOLEAN NOT NULL DEFAULT 1,
+    created_at TEXT NOT NULL
+);
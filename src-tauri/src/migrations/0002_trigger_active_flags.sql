@@ -0,0 +1,5 @@
+-- Older builds stored is_active as the text 'true'/'false', which never
+-- matched `is_active = 1`, so saved triggers were never treated as active.
+
+UPDATE sound_triggers SET is_active = 1 WHERE is_active = 'true';
+UPDATE sound_triggers SET is_active = 0 WHERE is_active = 'false';
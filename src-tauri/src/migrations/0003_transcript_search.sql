@@ -0,0 +1,51 @@
+-- Transcript segments and full-text indexes over them and over records.
+
+CREATE TABLE segments (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    record_id INTEGER NOT NULL REFERENCES audio_records(id) ON DELETE CASCADE,
+    start REAL NOT NULL,
+    "end" REAL NOT NULL,
+    text TEXT NOT NULL,
+    confidence REAL NOT NULL DEFAULT 0
+);
+CREATE INDEX idx_segments_record ON segments(record_id, start);
+
+CREATE VIRTUAL TABLE records_fts USING fts5(
+    title,
+    transcript,
+    content = 'audio_records',
+    content_rowid = 'id',
+    tokenize = 'unicode61 remove_diacritics 2'
+);
+
+CREATE TRIGGER audio_records_fts_insert AFTER INSERT ON audio_records BEGIN
+    INSERT INTO records_fts(rowid, title, transcript) VALUES (new.id, new.title, new.transcript);
+END;
+CREATE TRIGGER audio_records_fts_delete AFTER DELETE ON audio_records BEGIN
+    INSERT INTO records_fts(records_fts, rowid, title, transcript) VALUES ('delete', old.id, old.title, old.transcript);
+END;
+CREATE TRIGGER audio_records_fts_update AFTER UPDATE OF title, transcript ON audio_records BEGIN
+    INSERT INTO records_fts(records_fts, rowid, title, transcript) VALUES ('delete', old.id, old.title, old.transcript);
+    INSERT INTO records_fts(rowid, title, transcript) VALUES (new.id, new.title, new.transcript);
+END;
+
+CREATE VIRTUAL TABLE segments_fts USING fts5(
+    text,
+    content = 'segments',
+    content_rowid = 'id',
+    tokenize = 'unicode61 remove_diacritics 2'
+);
+
+CREATE TRIGGER segments_fts_insert AFTER INSERT ON segments BEGIN
+    INSERT INTO segments_fts(rowid, text) VALUES (new.id, new.text);
+END;
+CREATE TRIGGER segments_fts_delete AFTER DELETE ON segments BEGIN
+    INSERT INTO segments_fts(segments_fts, rowid, text) VALUES ('delete', old.id, old.text);
+END;
+CREATE TRIGGER segments_fts_update AFTER UPDATE OF text ON segments BEGIN
+    INSERT INTO segments_fts(segments_fts, rowid, text) VALUES ('delete', old.id, old.text);
+    INSERT INTO segments_fts(rowid, text) VALUES (new.id, new.text);
+END;
+
+-- Index what existing installs already have
+INSERT INTO records_fts(records_fts) VALUES ('rebuild');
@@ -0,0 +1,12 @@
+-- Non-speech events found by audio analysis, linked to the analysed record.
+
+CREATE TABLE audio_events (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    record_id INTEGER NOT NULL REFERENCES audio_records(id) ON DELETE CASCADE,
+    start REAL NOT NULL,
+    "end" REAL NOT NULL,
+    event_type TEXT NOT NULL,
+    confidence REAL NOT NULL DEFAULT 0
+);
+CREATE INDEX idx_audio_events_record ON audio_events(record_id, start);
+CREATE INDEX idx_audio_events_type ON audio_events(event_type);
@@ -0,0 +1,4 @@
+-- Free-form tags on recordings, stored as a JSON array of strings.
+
+ALTER TABLE audio_records ADD COLUMN tags TEXT NOT NULL DEFAULT '[]';
+CREATE INDEX idx_audio_records_created ON audio_records(created_at);
@@ -0,0 +1,3 @@
+-- Starred recordings can be exempted from retention cleanup.
+
+ALTER TABLE audio_records ADD COLUMN starred INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,24 @@
+-- Integrity data for recordings and an append-only, hash-chained audit log.
+
+ALTER TABLE audio_records ADD COLUMN sha256 TEXT;
+ALTER TABLE audio_records ADD COLUMN capture_device TEXT;
+ALTER TABLE audio_records ADD COLUMN captured_at TEXT;
+
+-- No foreign key: entries must outlive the records they describe
+CREATE TABLE audit_log (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    record_id INTEGER,
+    action TEXT NOT NULL,
+    details TEXT NOT NULL DEFAULT '',
+    created_at TEXT NOT NULL,
+    prev_hash TEXT NOT NULL,
+    hash TEXT NOT NULL
+);
+CREATE INDEX idx_audit_log_record ON audit_log(record_id, id);
+
+CREATE TRIGGER audit_log_no_update BEFORE UPDATE ON audit_log BEGIN
+    SELECT RAISE(ABORT, 'audit_log is append-only');
+END;
+CREATE TRIGGER audit_log_no_delete BEFORE DELETE ON audit_log BEGIN
+    SELECT RAISE(ABORT, 'audit_log is append-only');
+END;
@@ -0,0 +1,20 @@
+-- Sentence embeddings of transcript chunks and Dwight memories, searched by
+-- rag_search. Vectors are little-endian f32, normalised to unit length so a
+-- dot product is the cosine similarity.
+
+CREATE TABLE embeddings (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    record_id INTEGER REFERENCES audio_records(id) ON DELETE CASCADE,
+    memory_id INTEGER REFERENCES dwight_memory(id) ON DELETE CASCADE,
+    chunk INTEGER NOT NULL,
+    start REAL,
+    "end" REAL,
+    text TEXT NOT NULL,
+    -- Hash of the source text and the model, to spot chunks that need redoing
+    source_hash TEXT NOT NULL,
+    model TEXT NOT NULL,
+    vector BLOB NOT NULL,
+    CHECK ((record_id IS NULL) != (memory_id IS NULL))
+);
+CREATE INDEX idx_embeddings_record ON embeddings(record_id, chunk);
+CREATE INDEX idx_embeddings_memory ON embeddings(memory_id, chunk);
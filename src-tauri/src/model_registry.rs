@@ -0,0 +1,367 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use tauri::{command, State};
+use crate::ai_models::ModelConfig;
+use crate::database;
+use crate::llm_provider::{GenerationOptions, ModelDefaults, ProviderKind};
+use crate::state::AppState;
+use crate::vault;
+
+/// Stored unencrypted so models work while storage is locked. API keys are
+/// kept out of it, in the OS keyring.
+const REGISTRY_FILE: &str = "ai_models.json";
+
+/// The language models the user has set up, persisted to `ai_models.json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelRegistry {
+    pub models: Vec<ModelConfig>,
+}
+
+/// `ai_models.json` as written: the models without their API keys.
+#[derive(Serialize, Deserialize)]
+struct RegistryFile {
+    models: Vec<ModelConfig>,
+    /// Models whose API key is in the OS keyring
+    #[serde(default)]
+    keyring_keys: Vec<String>,
+}
+
+fn api_key_secret(model_id: &str) -> String {
+    format!("ai-model:{}", model_id)
+}
+
+/// The models with their API keys taken out, and the keys by model id.
+fn without_api_keys(models: &[ModelConfig]) -> (Vec<ModelConfig>, Vec<(String, String)>) {
+    let mut api_keys = Vec::new();
+    let models = models
+        .iter()
+        .cloned()
+        .map(|mut model| {
+            if let Some(api_key) = model.api_key.take() {
+                api_keys.push((model.id.clone(), api_key));
+            }
+            model
+        })
+        .collect();
+    (models, api_keys)
+}
+
+fn read_registry_file(path: &std::path::Path) -> Option<RegistryFile> {
+    let content = std::fs::read_to_string(path).ok()?;
+    serde_json::from_str(&content)
+        .map_err(|e| eprintln!("Ignoring unreadable {}: {}", REGISTRY_FILE, e))
+        .ok()
+}
+
+/// What probing a model's server found.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelProbe {
+    pub id: String,
+    pub endpoint: Option<String>,
+    /// Whether the server answered its model listing
+    pub reachable: bool,
+    /// Models the server reports, e.g. everything pulled into Ollama
+    pub installed_models: Vec<String>,
+    /// Whether `id` is among them; `None` if the server couldn't be asked
+    pub model_installed: Option<bool>,
+    pub error: Option<String>,
+}
+
+fn ollama_model(id: &str, name: &str, model_type: &str, enabled: bool) -> ModelConfig {
+    ModelConfig {
+        id: id.to_string(),
+        name: name.to_string(),
+        model_type: model_type.to_string(),
+        api_endpoint: Some("http://localhost:11434/api/generate".to_string()), // Ollama endpoint
+        local_path: None,
+        provider: ProviderKind::OllamaGenerate,
+        api_key: None,
+        defaults: ModelDefaults::default(),
+        enabled,
+    }
+}
+
+impl Default for ModelRegistry {
+    fn default() -> Self {
+        ModelRegistry {
+            models: vec![
+                ollama_model("llama3:8b", "Llama 3 8B", "llama", true),
+                // Disabled by default due to resource requirements
+                ollama_model("llama3:70b", "Llama 3 70B", "llama", false),
+                ollama_model("mixtral:8x7b", "Mixtral 8x7B", "mixtral", true),
+                ollama_model("mistral:7b", "Mistral 7B", "mistral", true),
+            ],
+        }
+    }
+}
+
+impl ModelRegistry {
+    pub fn load(app_config: &tauri::Config) -> Self {
+        let path = database::app_data_path(app_config).join(REGISTRY_FILE);
+        let Some(file) = read_registry_file(&path) else {
+            return Self::default();
+        };
+        let mut registry = ModelRegistry { models: file.models };
+
+        // Earlier versions wrote keys into the file; move them to the keyring
+        if registry.models.iter().any(|m| m.api_key.is_some()) {
+            if let Err(e) = registry.save(app_config) {
+                eprintln!("Failed to move API keys out of {}: {}", REGISTRY_FILE, e);
+            }
+        }
+
+        for model in registry.models.iter_mut().filter(|m| m.api_key.is_none()) {
+            if !file.keyring_keys.contains(&model.id) {
+                continue;
+            }
+            match vault::load_secret(&api_key_secret(&model.id)) {
+                Ok(api_key) => model.api_key = api_key,
+                Err(e) => eprintln!("Could not read the API key for '{}': {}", model.id, e),
+            }
+        }
+        registry
+    }
+
+    /// Writes the registry, with each API key put in the OS keyring. Without
+    /// a keyring a key isn't saved at all and lasts until the app closes.
+    pub fn save(&self, app_config: &tauri::Config) -> Result<()> {
+        let data_dir = database::app_data_path(app_config);
+        std::fs::create_dir_all(&data_dir)?;
+        let path = data_dir.join(REGISTRY_FILE);
+        let previous = read_registry_file(&path).map(|f| f.keyring_keys).unwrap_or_default();
+
+        let (models, api_keys) = without_api_keys(&self.models);
+        let mut file = RegistryFile { models, keyring_keys: Vec::new() };
+        for (id, api_key) in api_keys {
+            match vault::store_secret(&api_key_secret(&id), &api_key) {
+                Ok(()) => file.keyring_keys.push(id),
+                Err(e) => eprintln!("Not saving the API key for '{}': {}", id, e),
+            }
+        }
+        std::fs::write(&path, serde_json::to_string_pretty(&file)?)?;
+
+        for id in previous.iter().filter(|id| !file.keyring_keys.contains(id)) {
+            if let Err(e) = vault::delete_secret(&api_key_secret(id)) {
+                eprintln!("Failed to remove the API key for '{}': {}", id, e);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn get(&self, id: &str) -> Option<&ModelConfig> {
+        self.models.iter().find(|model| model.id == id)
+    }
+
+    pub fn validate(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        for (i, model) in self.models.iter().enumerate() {
+            if self.models[..i].iter().any(|other| other.id == model.id) {
+                errors.push(format!("More than one model has the id '{}'", model.id));
+            }
+            errors.extend(validate_model(model));
+        }
+        errors
+    }
+}
+
+pub fn validate_model(model: &ModelConfig) -> Vec<String> {
+    let mut errors = Vec::new();
+    if model.id.trim().is_empty() {
+        errors.push("Model id cannot be empty".to_string());
+    }
+    if model.name.trim().is_empty() {
+        errors.push(format!("Model '{}' needs a display name", model.id));
+    }
+    match model.api_endpoint.as_deref() {
+        _ if model.provider == ProviderKind::Stub => {}
+        None => errors.push(format!("Model '{}' needs an API endpoint", model.id)),
+        Some(endpoint) if !endpoint.starts_with("http://") && !endpoint.starts_with("https://") => {
+            errors.push(format!("Endpoint for '{}' must be an http:// or https:// URL", model.id));
+        }
+        Some(_) => {}
+    }
+    let defaults = GenerationOptions::default().or_defaults(&model.defaults);
+    errors.extend(defaults.validate().into_iter().map(|e| format!("{} (model '{}')", e, model.id)));
+    errors
+}
+
+/// Applies `edit` to the saved registry, validates and saves the result,
+/// and swaps it into the running app.
+fn edit_registry<T>(
+    state: &AppState,
+    edit: impl FnOnce(&mut ModelRegistry) -> Result<T, String>,
+) -> Result<T, String> {
+    state.advanced_ai.edit_registry(|registry| {
+        let result = edit(registry)?;
+        let errors = registry.validate();
+        if !errors.is_empty() {
+            return Err(format!("Invalid model configuration: {}", errors.join("; ")));
+        }
+        registry
+            .save(state.app_config())
+            .map_err(|e| format!("Failed to save model registry: {}", e))?;
+        Ok(result)
+    })
+}
+
+#[command]
+pub async fn add_ai_model(model: ModelConfig, state: State<'_, AppState>) -> Result<ModelConfig, String> {
+    edit_registry(&state, |registry| {
+        if registry.get(&model.id).is_some() {
+            return Err(format!("A model with the id '{}' already exists", model.id));
+        }
+        registry.models.push(model.clone());
+        Ok(model)
+    })
+}
+
+/// Replaces the model with the same id.
+#[command]
+pub async fn update_ai_model(model: ModelConfig, state: State<'_, AppState>) -> Result<ModelConfig, String> {
+    edit_registry(&state, |registry| {
+        let existing = registry
+            .models
+            .iter_mut()
+            .find(|m| m.id == model.id)
+            .ok_or_else(|| format!("No model with the id '{}'", model.id))?;
+        *existing = model.clone();
+        Ok(model)
+    })
+}
+
+/// Returns false if there was no such model.
+#[command]
+pub async fn remove_ai_model(id: String, state: State<'_, AppState>) -> Result<bool, String> {
+    edit_registry(&state, |registry| {
+        let before = registry.models.len();
+        registry.models.retain(|m| m.id != id);
+        Ok(registry.models.len() != before)
+    })
+}
+
+#[command]
+pub async fn set_ai_model_enabled(id: String, enabled: bool, state: State<'_, AppState>) -> Result<ModelConfig, String> {
+    edit_registry(&state, |registry| {
+        let model = registry
+            .models
+            .iter_mut()
+            .find(|m| m.id == id)
+            .ok_or_else(|| format!("No model with the id '{}'", id))?;
+        model.enabled = enabled;
+        Ok(model.clone())
+    })
+}
+
+/// Restores the built-in model list, discarding any edits.
+#[command]
+pub async fn reset_ai_models(state: State<'_, AppState>) -> Result<Vec<ModelConfig>, String> {
+    edit_registry(&state, |registry| {
+        *registry = ModelRegistry::default();
+        Ok(registry.models.clone())
+    })
+}
+
+/// Asks each model's server (or just the one given by `id`) which models it
+/// has, showing whether it is up and whether the model is actually installed.
+#[command]
+pub async fn probe_ai_models(id: Option<String>, state: State<'_, AppState>) -> Result<Vec<ModelProbe>, String> {
+    let ai = &state.advanced_ai;
+    let models: Vec<ModelConfig> = ai
+        .registry()
+        .models
+        .into_iter()
+        .filter(|m| id.as_deref().is_none_or(|id| m.id == id))
+        .collect();
+    if let Some(id) = &id {
+        if models.is_empty() {
+            return Err(format!("No model with the id '{}'", id));
+        }
+    }
+    let mut probes = Vec::with_capacity(models.len());
+    for model in &models {
+        probes.push(ai.probe(model).await);
+    }
+    Ok(probes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn model(id: &str, provider: ProviderKind, endpoint: Option<&str>) -> ModelConfig {
+        ModelConfig {
+            id: id.to_string(),
+            name: id.to_uppercase(),
+            model_type: "llama".to_string(),
+            api_endpoint: endpoint.map(str::to_string),
+            local_path: None,
+            provider,
+            api_key: None,
+            defaults: ModelDefaults::default(),
+            enabled: true,
+        }
+    }
+
+    #[test]
+    fn validate_model_checks_names_endpoints_and_defaults() {
+        let ollama = |endpoint| model("llama3:8b", ProviderKind::OllamaGenerate, endpoint);
+        assert!(validate_model(&ollama(Some("http://localhost:11434/api/generate"))).is_empty());
+        assert!(validate_model(&ollama(Some("https://models.example.com/v1"))).is_empty());
+        assert_eq!(validate_model(&ollama(None)), ["Model 'llama3:8b' needs an API endpoint"]);
+        assert_eq!(
+            validate_model(&ollama(Some("localhost:11434"))),
+            ["Endpoint for 'llama3:8b' must be an http:// or https:// URL"]
+        );
+        // The stub never makes a request
+        assert!(validate_model(&model("stub", ProviderKind::Stub, None)).is_empty());
+
+        let mut unnamed = model("  ", ProviderKind::Stub, None);
+        unnamed.name = String::new();
+        assert_eq!(validate_model(&unnamed), ["Model id cannot be empty", "Model '  ' needs a display name"]);
+
+        let mut bad_defaults = model("stub", ProviderKind::Stub, None);
+        bad_defaults.defaults.temperature = 3.0;
+        bad_defaults.defaults.max_tokens = 0;
+        assert_eq!(
+            validate_model(&bad_defaults),
+            [
+                "Temperature must be between 0 and 2 (model 'stub')",
+                "Maximum tokens must be at least 1 (model 'stub')",
+            ]
+        );
+    }
+
+    #[test]
+    fn registry_validation_catches_duplicate_ids() {
+        assert!(ModelRegistry::default().validate().is_empty());
+
+        let registry = ModelRegistry {
+            models: vec![
+                model("stub", ProviderKind::Stub, None),
+                model("other", ProviderKind::Stub, None),
+                model("stub", ProviderKind::OllamaGenerate, None),
+            ],
+        };
+        assert_eq!(
+            registry.validate(),
+            ["More than one model has the id 'stub'", "Model 'stub' needs an API endpoint"]
+        );
+    }
+
+    #[test]
+    fn api_keys_are_taken_out_of_the_saved_models() {
+        let mut with_key = model("gpt", ProviderKind::OpenAiChat, Some("https://api.example.com/v1/chat/completions"));
+        with_key.api_key = Some("sk-secret".to_string());
+        let models = vec![with_key, model("stub", ProviderKind::Stub, None)];
+
+        let (stripped, api_keys) = without_api_keys(&models);
+        assert!(stripped.iter().all(|m| m.api_key.is_none()));
+        assert_eq!(api_keys, [("gpt".to_string(), "sk-secret".to_string())]);
+        let json = serde_json::to_string(&RegistryFile { models: stripped, keyring_keys: vec!["gpt".to_string()] }).unwrap();
+        assert!(!json.contains("sk-secret"));
+
+        // Files from before keys moved to the keyring still load
+        let old: RegistryFile = serde_json::from_str(r#"{"models": []}"#).unwrap();
+        assert!(old.keyring_keys.is_empty());
+    }
+}
@@ -0,0 +1,443 @@
+use rustfft::{num_complex::Complex, FftPlanner};
+use serde::{Deserialize, Serialize};
+use anyhow::{anyhow, Result};
+use crate::audio_decode;
+use crate::resample;
+
+/// Matches the `audio_preprocessing` Python script: order-5 Butterworth, 80–8000 Hz.
+pub const BANDPASS_ORDER: usize = 5;
+pub const BANDPASS_LOW_HZ: f64 = 80.0;
+pub const BANDPASS_HIGH_HZ: f64 = 8000.0;
+
+/// How many preprocessed samples are returned alongside the features, as in the script.
+const AUDIO_DATA_PREVIEW: usize = 1000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PreprocessFeatures {
+    pub duration: f64,
+    pub sample_rate: u32,
+    pub rms_energy: f64,
+    pub zero_crossing_rate: f64,
+    /// Mean FFT magnitude. Kept under the script's name for compatibility,
+    /// although it is not a centroid in the usual sense.
+    pub spectral_centroid: f64,
+}
+
+pub struct PreprocessedAudio {
+    pub samples: Vec<f64>,
+    pub features: PreprocessFeatures,
+}
+
+impl PreprocessedAudio {
+    /// Same JSON shape the Python script returns.
+    pub fn to_script_output(&self) -> serde_json::Value {
+        let preview: Vec<f64> = self.samples.iter().take(AUDIO_DATA_PREVIEW).copied().collect();
+        serde_json::json!({
+            "success": true,
+            "audio_data": preview,
+            "features": self.features,
+            "message": "Audio preprocessing completed successfully"
+        })
+    }
+}
+
+/// Decode, downmix, normalize, resample, bandpass and measure a file.
+pub fn preprocess_file(file_path: &str, sample_rate: u32) -> Result<PreprocessedAudio> {
+    let decoded = audio_decode::decode_file(file_path, None)?;
+    preprocess_samples(&decoded.samples, decoded.sample_rate, sample_rate)
+}
+
+/// The script's chain on mono samples: peak-normalize, resample, then a
+/// zero-phase Butterworth bandpass. Two differences from the script are
+/// deliberate:
+///
+/// - Resampling is band-limited sinc with the length rounded, where the
+///   script uses `scipy.signal.resample` (FFT, which assumes the signal is
+///   periodic) with the length truncated. Samples can differ slightly and
+///   the output may be one sample longer.
+/// - The high band edge is clamped to 0.99 × Nyquist. At 16 kHz the
+///   script asks for exactly Nyquist, which scipy rejects, so it never got
+///   past this step at that rate.
+pub fn preprocess_samples(samples: &[f32], source_rate: u32, sample_rate: u32) -> Result<PreprocessedAudio> {
+    let normalized = normalize_peak(samples);
+    let resampled = resample::resample(&normalized, source_rate, sample_rate)?;
+    let data: Vec<f64> = resampled.iter().map(|&s| s as f64).collect();
+
+    // scipy rejects a high edge at Nyquist; keep the edge just below it
+    let nyquist = sample_rate as f64 / 2.0;
+    let high_hz = BANDPASS_HIGH_HZ.min(nyquist * 0.99);
+    let (b, a) = butter_bandpass(BANDPASS_ORDER, BANDPASS_LOW_HZ, high_hz, sample_rate)?;
+    let filtered = filtfilt(&b, &a, &data)?;
+
+    let features = extract_features(&filtered, sample_rate);
+    Ok(PreprocessedAudio {
+        samples: filtered,
+        features,
+    })
+}
+
+/// Scales so the loudest sample sits at full scale. Silence is returned unchanged.
+pub fn normalize_peak(samples: &[f32]) -> Vec<f32> {
+    let peak = samples.iter().fold(0.0f32, |m, s| m.max(s.abs()));
+    if peak <= 0.0 {
+        return samples.to_vec();
+    }
+    samples.iter().map(|s| s / peak).collect()
+}
+
+pub fn extract_features(data: &[f64], sample_rate: u32) -> PreprocessFeatures {
+    let n = data.len().max(1) as f64;
+    let rms_energy = (data.iter().map(|s| s * s).sum::<f64>() / n).sqrt();
+
+    // np.sign is 0 at 0, so a touch of zero counts as half a crossing
+    let sign = |x: f64| -> f64 {
+        if x > 0.0 {
+            1.0
+        } else if x < 0.0 {
+            -1.0
+        } else {
+            0.0
+        }
+    };
+    let zero_crossing_rate = if data.len() > 1 {
+        data.windows(2)
+            .map(|w| (sign(w[1]) - sign(w[0])).abs() / 2.0)
+            .sum::<f64>()
+            / (data.len() - 1) as f64
+    } else {
+        0.0
+    };
+
+    let mut spectrum: Vec<Complex<f64>> = data.iter().map(|&s| Complex::new(s, 0.0)).collect();
+    let spectral_centroid = if spectrum.is_empty() {
+        0.0
+    } else {
+        FftPlanner::<f64>::new()
+            .plan_fft_forward(spectrum.len())
+            .process(&mut spectrum);
+        spectrum.iter().map(|c| c.norm()).sum::<f64>() / n
+    };
+
+    PreprocessFeatures {
+        duration: data.len() as f64 / sample_rate.max(1) as f64,
+        sample_rate,
+        rms_energy,
+        zero_crossing_rate,
+        spectral_centroid,
+    }
+}
+
+fn poly(roots: &[Complex<f64>]) -> Vec<Complex<f64>> {
+    let mut coeffs = vec![Complex::new(1.0, 0.0)];
+    for root in roots {
+        let mut next = vec![Complex::new(0.0, 0.0); coeffs.len() + 1];
+        for (i, c) in coeffs.iter().enumerate() {
+            next[i] += c;
+            next[i + 1] -= c * root;
+        }
+        coeffs = next;
+    }
+    coeffs
+}
+
+/// Digital Butterworth bandpass as `(b, a)`, designed the way
+/// `scipy.signal.butter(order, [low, high], btype='band')` does: analog
+/// prototype, lowpass-to-bandpass, then bilinear transform.
+pub fn butter_bandpass(order: usize, low_hz: f64, high_hz: f64, sample_rate: u32) -> Result<(Vec<f64>, Vec<f64>)> {
+    let nyquist = sample_rate as f64 / 2.0;
+    let (low, high) = (low_hz / nyquist, high_hz / nyquist);
+    if order == 0 || !(0.0 < low && low < high && high < 1.0) {
+        return Err(anyhow!(
+            "Invalid bandpass {}–{} Hz at {} Hz sample rate",
+            low_hz,
+            high_hz,
+            sample_rate
+        ));
+    }
+
+    // Analog prototype poles on the unit circle, no zeros, unity gain
+    let prototype: Vec<Complex<f64>> = (0..order)
+        .map(|k| {
+            let m = -(order as f64) + 1.0 + 2.0 * k as f64;
+            -Complex::new(0.0, std::f64::consts::PI * m / (2.0 * order as f64)).exp()
+        })
+        .collect();
+
+    // Pre-warp the band edges (fs = 2, as scipy uses for normalized frequencies)
+    let fs2 = 4.0;
+    let warped_low = fs2 * (std::f64::consts::PI * low / 2.0).tan();
+    let warped_high = fs2 * (std::f64::consts::PI * high / 2.0).tan();
+    let bw = warped_high - warped_low;
+    let wo = (warped_low * warped_high).sqrt();
+
+    // Lowpass to bandpass: each pole splits in two, N zeros land at the origin
+    let mut poles = Vec::with_capacity(order * 2);
+    for p in &prototype {
+        let p_lp = p * (bw / 2.0);
+        let root = (p_lp * p_lp - wo * wo).sqrt();
+        poles.push(p_lp + root);
+    }
+    for p in &prototype {
+        let p_lp = p * (bw / 2.0);
+        let root = (p_lp * p_lp - wo * wo).sqrt();
+        poles.push(p_lp - root);
+    }
+    let zeros = vec![Complex::new(0.0, 0.0); order];
+    let gain = bw.powi(order as i32);
+
+    // Bilinear transform; the extra zeros go to z = -1
+    let z_zeros: Vec<Complex<f64>> = zeros
+        .iter()
+        .map(|z| (fs2 + z) / (fs2 - z))
+        .chain(std::iter::repeat_n(Complex::new(-1.0, 0.0), poles.len() - zeros.len()))
+        .collect();
+    let z_poles: Vec<Complex<f64>> = poles.iter().map(|p| (fs2 + p) / (fs2 - p)).collect();
+    let num: Complex<f64> = zeros.iter().map(|z| fs2 - z).product();
+    let den: Complex<f64> = poles.iter().map(|p| fs2 - p).product();
+    let z_gain = gain * (num / den).re;
+
+    let b = poly(&z_zeros).iter().map(|c| c.re * z_gain).collect();
+    let a = poly(&z_poles).iter().map(|c| c.re).collect();
+    Ok((b, a))
+}
+
+/// Direct form II transposed IIR filter, as `scipy.signal.lfilter`, starting
+/// from state `zi`. `a[0]` must be 1.
+pub fn lfilter(b: &[f64], a: &[f64], x: &[f64], zi: &[f64]) -> Vec<f64> {
+    let n = a.len().max(b.len());
+    let coeff = |v: &[f64], i: usize| v.get(i).copied().unwrap_or(0.0);
+    let mut state = zi.to_vec();
+    state.resize(n - 1, 0.0);
+
+    x.iter()
+        .map(|&xn| {
+            let yn = coeff(b, 0) * xn + state.first().copied().unwrap_or(0.0);
+            for i in 0..n - 1 {
+                let next = state.get(i + 1).copied().unwrap_or(0.0);
+                state[i] = next + coeff(b, i + 1) * xn - coeff(a, i + 1) * yn;
+            }
+            yn
+        })
+        .collect()
+}
+
+/// Steady-state initial conditions for a unit step, as `scipy.signal.lfilter_zi`.
+pub fn lfilter_zi(b: &[f64], a: &[f64]) -> Result<Vec<f64>> {
+    let n = a.len().max(b.len());
+    let coeff = |v: &[f64], i: usize| v.get(i).copied().unwrap_or(0.0);
+    let size = n - 1;
+
+    // (I - companion(a).T) zi = b[1:] - a[1:] * b[0]
+    let mut m = vec![vec![0.0; size + 1]; size];
+    for (i, row) in m.iter_mut().enumerate() {
+        row[i] += 1.0;
+        row[0] += coeff(a, i + 1);
+        if i + 1 < size {
+            row[i + 1] -= 1.0;
+        }
+        row[size] = coeff(b, i + 1) - coeff(a, i + 1) * coeff(b, 0);
+    }
+
+    // Gaussian elimination with partial pivoting
+    for col in 0..size {
+        let pivot = (col..size)
+            .max_by(|&x, &y| m[x][col].abs().total_cmp(&m[y][col].abs()))
+            .unwrap_or(col);
+        if m[pivot][col].abs() < 1e-300 {
+            return Err(anyhow!("Filter has no steady state"));
+        }
+        m.swap(col, pivot);
+        let pivot_row = m[col].clone();
+        for (row, values) in m.iter_mut().enumerate() {
+            if row != col {
+                let factor = values[col] / pivot_row[col];
+                for (value, p) in values.iter_mut().zip(&pivot_row).skip(col) {
+                    *value -= factor * p;
+                }
+            }
+        }
+    }
+
+    Ok((0..size).map(|i| m[i][size] / m[i][i]).collect())
+}
+
+/// Zero-phase forward/backward filtering with odd padding, as
+/// `scipy.signal.filtfilt` with its default `padtype='odd'`.
+pub fn filtfilt(b: &[f64], a: &[f64], x: &[f64]) -> Result<Vec<f64>> {
+    let padlen = 3 * a.len().max(b.len());
+    if x.len() <= padlen {
+        return Err(anyhow!(
+            "Audio too short to filter: {} samples, need more than {}",
+            x.len(),
+            padlen
+        ));
+    }
+
+    let first = x[0];
+    let last = x[x.len() - 1];
+    let mut extended = Vec::with_capacity(x.len() + 2 * padlen);
+    extended.extend((1..=padlen).rev().map(|i| 2.0 * first - x[i]));
+    extended.extend_from_slice(x);
+    extended.extend((1..=padlen).map(|i| 2.0 * last - x[x.len() - 1 - i]));
+
+    let zi = lfilter_zi(b, a)?;
+    let scaled = |v: f64| zi.iter().map(|z| z * v).collect::<Vec<_>>();
+
+    let mut forward = lfilter(b, a, &extended, &scaled(extended[0]));
+    forward.reverse();
+    let mut backward = lfilter(b, a, &forward, &scaled(forward[0]));
+    backward.reverse();
+
+    Ok(backward[padlen..backward.len() - padlen].to_vec())
+}
+
+#[cfg(test)]
+// The reference values keep every digit they were printed with
+#[allow(clippy::excessive_precision)]
+mod tests {
+    use super::*;
+
+    // Reference values for `butter(5, [80/8000, 7920/8000], btype='band')` at
+    // 16 kHz, i.e. the script's 80-8000 Hz band with the high edge clamped to
+    // 0.99 x Nyquist, with `lfilter_zi` and `filtfilt` on `test_signal()`.
+    //
+    // These were NOT produced by scipy itself: no scipy install was available
+    // when they were written, so they come from a separate 50-digit
+    // re-implementation of scipy.signal's butter (zpk, lp2bp_zpk, bilinear_zpk),
+    // lfilter_zi and filtfilt (odd padding of 3 * max(len(a), len(b))). The
+    // analytic checks below don't depend on them. To replace them with real
+    // scipy output, run the following and record the scipy version here:
+    //
+    //     import numpy as np, scipy, scipy.signal as sg
+    //     np.set_printoptions(precision=17, floatmode='unique')
+    //     b, a = sg.butter(5, [80 / 8000, 7920 / 8000], btype='band')
+    //     n = np.arange(64) / 16000
+    //     x = np.sin(2 * np.pi * 1000 * n) + 0.5 * np.sin(2 * np.pi * 20 * n) + 0.25
+    //     print(scipy.__version__, repr(b), repr(a), repr(sg.lfilter_zi(b, a)), repr(sg.filtfilt(b, a, x)))
+    const BUTTER_B: [f64; 11] = [
+        9.03314275335156402e-01, 0.0, -4.51657137667578201e+00,
+        0.0, 9.03314275335156402e+00, 0.0,
+        -9.03314275335156402e+00, 0.0, 4.51657137667578201e+00,
+        0.0, -9.03314275335156402e-01,
+    ];
+    const BUTTER_A: [f64; 11] = [
+        1.00000000000000000e+00, 0.0, -4.79668159981780651e+00,
+        0.0, 9.20724237509200982e+00, 0.0,
+        -8.84036968250099342e+00, 0.0, 4.24578647328991909e+00,
+        0.0, -8.15976680024278034e-01,
+    ];
+    const LFILTER_ZI: [f64; 10] = [
+        -9.03314275335156402e-01, -9.03314275335156402e-01, 3.61325710134062561e+00,
+        3.61325710134062561e+00, -5.41988565201093841e+00, -5.41988565201093841e+00,
+        3.61325710134062561e+00, 3.61325710134062561e+00, -9.03314275335156402e-01,
+        -9.03314275335156402e-01,
+    ];
+    const FILTFILT_Y: [f64; 64] = [
+        -2.35728290435194443e-01, 1.50049255382436825e-01, 4.75585772436600274e-01,
+        6.96391104648848791e-01, 7.74856657358314549e-01, 7.03735176056016210e-01,
+        4.90572897638733907e-01, 1.72139109618000968e-01, -2.05640193971621699e-01,
+        -5.81323467944876104e-01, -8.99526451058063947e-01, -1.10827295780246526e+00,
+        -1.17684042525885690e+00, -1.09165707074173612e+00, -8.65988979441933537e-01,
+        -5.31460884749635820e-01, -1.38537277830507927e-01, 2.55292219043594060e-01,
+        5.91295330775911010e-01, 8.20248742881214410e-01, 9.09275034471976440e-01,
+        8.46356485499094369e-01, 6.43793261531067529e-01, 3.33572692199637033e-01,
+        -3.36292573566773570e-02, -4.01136953943725616e-01, -7.08848570055935934e-01,
+        -9.09508498864034176e-01, -9.67739223230913548e-01, -8.74613144665213560e-01,
+        -6.38835997237213626e-01, -2.96567445767123017e-01, 1.06159968965773066e-01,
+        5.07465623565332757e-01, 8.52885166820403273e-01, 1.08898473119939165e+00,
+        1.18695331893434064e+00, 1.13078148279791058e+00, 9.36594968745760537e-01,
+        6.32648461527039241e-01, 2.73161436217992382e-01, -8.86218840433619509e-02,
+        -3.89381362643679840e-01, -5.84947503018889980e-01, -6.37094728593241233e-01,
+        -5.39590258736276973e-01, -2.98708999745880277e-01, 4.71337924869968672e-02,
+        4.53870244506050513e-01, 8.57853257851224904e-01, 1.20606610003126424e+00,
+        1.44384940052731503e+00, 1.54326987259288129e+00, 1.48768737203158508e+00,
+        1.29348060749401261e+00, 9.88924194641661480e-01, 6.27808991111534875e-01,
+        2.64107597738994115e-01, -4.00288781641245650e-02, -2.38934291379205771e-01,
+        -2.96353346018692676e-01, -2.03726505019426518e-01, 2.98369388712857835e-02,
+        3.69142450020061297e-01,
+    ];
+
+    fn test_signal() -> Vec<f64> {
+        (0..64)
+            .map(|n| {
+                let t = n as f64 / 16000.0;
+                (2.0 * std::f64::consts::PI * 1000.0 * t).sin() + 0.5 * (2.0 * std::f64::consts::PI * 20.0 * t).sin() + 0.25
+            })
+            .collect()
+    }
+
+    fn assert_close(actual: &[f64], expected: &[f64], tolerance: f64) {
+        assert_eq!(actual.len(), expected.len());
+        for (i, (a, e)) in actual.iter().zip(expected).enumerate() {
+            assert!((a - e).abs() <= tolerance * e.abs().max(1.0), "index {}: {} != {}", i, a, e);
+        }
+    }
+
+    /// |H| of the filter at `hz`.
+    fn gain(b: &[f64], a: &[f64], hz: f64, sample_rate: f64) -> f64 {
+        let w = 2.0 * std::f64::consts::PI * hz / sample_rate;
+        let eval = |coeffs: &[f64]| {
+            let (re, im) = coeffs.iter().enumerate().fold((0.0, 0.0), |(re, im), (k, c)| {
+                (re + c * (w * k as f64).cos(), im - c * (w * k as f64).sin())
+            });
+            (re * re + im * im).sqrt()
+        };
+        eval(b) / eval(a)
+    }
+
+    #[test]
+    fn butter_bandpass_has_butterworth_response() {
+        // Prewarped bilinear design: exactly -3 dB at both edges, unity at the
+        // centre, and nulls at DC and Nyquist
+        let (low, high, rate) = (300.0, 3400.0, 16000.0);
+        let (b, a) = butter_bandpass(BANDPASS_ORDER, low, high, rate as u32).unwrap();
+        let half_power = std::f64::consts::FRAC_1_SQRT_2;
+        assert!((gain(&b, &a, low, rate) - half_power).abs() < 1e-9);
+        assert!((gain(&b, &a, high, rate) - half_power).abs() < 1e-9);
+        let warp = |hz: f64| (std::f64::consts::PI * hz / rate).tan();
+        let centre = (warp(low) * warp(high)).sqrt().atan() * rate / std::f64::consts::PI;
+        assert!((gain(&b, &a, centre, rate) - 1.0).abs() < 1e-9);
+        assert!(gain(&b, &a, 0.0, rate) < 1e-9);
+        assert!(gain(&b, &a, rate / 2.0, rate) < 1e-9);
+        // Maximally flat: never above unity
+        assert!((1..800).all(|i| gain(&b, &a, i as f64 * 10.0, rate) <= 1.0 + 1e-9));
+    }
+
+    #[test]
+    fn butter_bandpass_matches_reference() {
+        let (b, a) = butter_bandpass(BANDPASS_ORDER, BANDPASS_LOW_HZ, 8000.0 * 0.99, 16000).unwrap();
+        assert_close(&b, &BUTTER_B, 1e-9);
+        assert_close(&a, &BUTTER_A, 1e-9);
+    }
+
+    #[test]
+    fn butter_bandpass_rejects_edge_at_nyquist() {
+        assert!(butter_bandpass(BANDPASS_ORDER, BANDPASS_LOW_HZ, BANDPASS_HIGH_HZ, 16000).is_err());
+    }
+
+    #[test]
+    fn lfilter_zi_matches_reference() {
+        let zi = lfilter_zi(&BUTTER_B, &BUTTER_A).unwrap();
+        assert_close(&zi, &LFILTER_ZI, 1e-9);
+    }
+
+    #[test]
+    fn lfilter_zi_is_the_step_steady_state() {
+        // Starting from zi, a constant input gives the DC gain straight away;
+        // for a bandpass that is zero
+        let zi = lfilter_zi(&BUTTER_B, &BUTTER_A).unwrap();
+        let y = lfilter(&BUTTER_B, &BUTTER_A, &[1.0; 50], &zi);
+        assert!(y.iter().all(|v| v.abs() < 1e-9), "{:?}", y);
+    }
+
+    #[test]
+    fn filtfilt_matches_reference() {
+        let y = filtfilt(&BUTTER_B, &BUTTER_A, &test_signal()).unwrap();
+        assert_close(&y, &FILTFILT_Y, 1e-8);
+    }
+
+    #[test]
+    fn filtfilt_rejects_short_input() {
+        assert!(filtfilt(&BUTTER_B, &BUTTER_A, &[0.0; 33]).is_err());
+    }
+}
@@ -4,9 +4,11 @@ use pyo3::prelude::*;
 #[cfg(feature = "python-integration")]
 use pyo3::types::{PyDict, PyList};
 
-use tauri::command;
+use tauri::{command, State};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use crate::preprocess;
+use crate::state::AppState;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct PythonResult {
@@ -231,6 +233,11 @@ output = json.dumps(result)
         // Fallback implementation when Python integration is disabled
         let start_time = std::time::Instant::now();
         
+        // Scripts with a native port still run without Python
+        if script_name == "audio_preprocessing" {
+            return Self::native_audio_preprocessing(&input_data, start_time);
+        }
+        
         PythonResult {
             success: false,
             result: serde_json::json!({
@@ -241,6 +248,31 @@ output = json.dumps(result)
         }
     }
     
+    /// Runs the `preprocess` module port of the `audio_preprocessing` script.
+    pub fn native_audio_preprocessing(input_data: &serde_json::Value, start_time: std::time::Instant) -> PythonResult {
+        let file_path = input_data["file_path"].as_str().unwrap_or_default();
+        let sample_rate = input_data["sample_rate"].as_u64().unwrap_or(16000) as u32;
+        
+        match preprocess::preprocess_file(file_path, sample_rate) {
+            Ok(processed) => PythonResult {
+                success: true,
+                result: processed.to_script_output(),
+                error: None,
+                execution_time_ms: start_time.elapsed().as_millis() as u64,
+            },
+            Err(e) => PythonResult {
+                success: false,
+                result: serde_json::json!({
+                    "success": false,
+                    "error": e.to_string(),
+                    "message": "Audio preprocessing failed"
+                }),
+                error: Some(e.to_string()),
+                execution_time_ms: start_time.elapsed().as_millis() as u64,
+            },
+        }
+    }
+    
     #[cfg(feature = "python-integration")]
     fn run_python_code(&self, py: Python, code: &str, input_data: serde_json::Value) -> PyResult<serde_json::Value> {
         let locals = PyDict::new(py);
@@ -318,39 +350,56 @@ output = json.dumps(result)
 pub async fn execute_python_script(
     script_name: String,
     input_data: serde_json::Value,
+    state: State<'_, AppState>,
 ) -> Result<PythonResult, String> {
-    let runner = PythonRunner::new();
-    Ok(runner.execute_script(&script_name, input_data))
+    Ok(state.python.execute_script(&script_name, input_data))
 }
 
 #[command]
-pub async fn get_python_scripts() -> Result<Vec<PythonScript>, String> {
-    let runner = PythonRunner::new();
-    Ok(runner.get_available_scripts().into_iter().cloned().collect())
+pub async fn get_python_scripts(state: State<'_, AppState>) -> Result<Vec<PythonScript>, String> {
+    Ok(state.python.get_available_scripts().into_iter().cloned().collect())
 }
 
 #[command]
 pub async fn python_audio_preprocessing(
     file_path: String,
     sample_rate: Option<u32>,
+    state: State<'_, AppState>,
+) -> Result<PythonResult, String> {
+    let input_data = serde_json::json!({
+        "file_path": file_path,
+        "sample_rate": sample_rate.unwrap_or(16000)
+    });
+    
+    Ok(state.python.execute_script("audio_preprocessing", input_data))
+}
+
+/// Native Rust preprocessing, available in every build for cross-checking the Python script.
+#[command]
+pub async fn native_audio_preprocessing(
+    file_path: String,
+    sample_rate: Option<u32>,
 ) -> Result<PythonResult, String> {
-    let runner = PythonRunner::new();
     let input_data = serde_json::json!({
         "file_path": file_path,
         "sample_rate": sample_rate.unwrap_or(16000)
     });
     
-    Ok(runner.execute_script("audio_preprocessing", input_data))
+    tokio::task::spawn_blocking(move || {
+        PythonRunner::native_audio_preprocessing(&input_data, std::time::Instant::now())
+    })
+    .await
+    .map_err(|e| format!("Audio preprocessing error: {}", e))
 }
 
 #[command]
 pub async fn python_ml_classification(
     audio_features: serde_json::Value,
+    state: State<'_, AppState>,
 ) -> Result<PythonResult, String> {
-    let runner = PythonRunner::new();
     let input_data = serde_json::json!({
         "features": audio_features
     });
     
-    Ok(runner.execute_script("ml_inference", input_data))
+    Ok(state.python.execute_script("ml_inference", input_data))
 }
\ No newline at end of file
@@ -0,0 +1,408 @@
+use std::cmp::Ordering;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tauri::{command, AppHandle, Manager, State};
+use crate::database::{AudioRecord, Database, DwightMemory, EmbeddingChunk, EmbeddingSource, StoredSegment};
+use crate::embeddings::{self, Embedder};
+use crate::state::AppState;
+
+/// Transcript chunks are cut at segment boundaries once they reach this
+/// many words, which keeps them within what small embedding models handle well.
+const WORDS_PER_CHUNK: usize = 80;
+
+pub const DEFAULT_TOP_K: usize = 5;
+pub const MAX_TOP_K: usize = 50;
+
+/// A retrieved chunk with its similarity to the query and where it came from.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RagHit {
+    /// Cosine similarity, higher is closer
+    pub score: f32,
+    pub source: EmbeddingSource,
+    pub text: String,
+    pub record_title: Option<String>,
+    /// Seconds into the recording
+    pub start: Option<f64>,
+    pub end: Option<f64>,
+    pub created_at: String,
+    /// Human-readable reference, e.g. `"Kitchen" (recording 12) at 01:05-01:20`
+    pub citation: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct IndexReport {
+    pub model: String,
+    /// Sources (recordings and memories) embedded on this run
+    pub sources_indexed: usize,
+    pub chunks_embedded: usize,
+    /// Sources whose embeddings were already current
+    pub up_to_date: usize,
+    /// Sources that couldn't be embedded; they are retried on the next run
+    pub errors: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RagStatus {
+    pub model_dir: String,
+    pub model_available: bool,
+    pub indexed_chunks: i64,
+    pub indexed_sources: i64,
+}
+
+fn format_timestamp(seconds: f64) -> String {
+    let total = seconds.max(0.0) as u64;
+    let (hours, minutes, secs) = (total / 3600, total / 60 % 60, total % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, secs)
+    } else {
+        format!("{:02}:{:02}", minutes, secs)
+    }
+}
+
+fn citation(source: EmbeddingSource, title: Option<&str>, chunk: &EmbeddingChunk, created_at: &str) -> String {
+    match source {
+        EmbeddingSource::Recording(id) => {
+            let mut citation = format!("\"{}\" (recording {})", title.unwrap_or("Untitled"), id);
+            if let (Some(start), Some(end)) = (chunk.start, chunk.end) {
+                citation.push_str(&format!(" at {}-{}", format_timestamp(start), format_timestamp(end)));
+            }
+            citation
+        }
+        EmbeddingSource::Memory(id) => format!("conversation {} from {}", id, created_at),
+    }
+}
+
+/// Splits a record into chunks along its segments, or by word count for
+/// transcripts saved without segments.
+fn record_chunks(record: &AudioRecord, segments: &[StoredSegment]) -> Vec<EmbeddingChunk> {
+    let mut chunks = Vec::new();
+    if segments.is_empty() {
+        let words: Vec<&str> = record.transcript.as_deref().unwrap_or("").split_whitespace().collect();
+        for window in words.chunks(WORDS_PER_CHUNK) {
+            chunks.push(EmbeddingChunk { start: None, end: None, text: window.join(" ") });
+        }
+        return chunks;
+    }
+
+    let mut current: Option<EmbeddingChunk> = None;
+    let mut words = 0;
+    for segment in segments {
+        let text = segment.text.trim();
+        if text.is_empty() {
+            continue;
+        }
+        let chunk = current.get_or_insert_with(|| EmbeddingChunk {
+            start: Some(segment.start),
+            end: Some(segment.end),
+            text: String::new(),
+        });
+        if !chunk.text.is_empty() {
+            chunk.text.push(' ');
+        }
+        chunk.text.push_str(text);
+        chunk.end = Some(segment.end);
+        words += text.split_whitespace().count();
+        if words >= WORDS_PER_CHUNK {
+            chunks.extend(current.take());
+            words = 0;
+        }
+    }
+    chunks.extend(current);
+    chunks
+}
+
+fn memory_chunks(memory: &DwightMemory) -> Vec<EmbeddingChunk> {
+    let text = format!("User: {}\nDwight: {}", memory.user_input.trim(), memory.response.trim());
+    vec![EmbeddingChunk { start: None, end: None, text }]
+}
+
+fn source_label(source: EmbeddingSource) -> String {
+    match source {
+        EmbeddingSource::Recording(id) => format!("recording {}", id),
+        EmbeddingSource::Memory(id) => format!("conversation {}", id),
+    }
+}
+
+fn source_hash(model: &str, chunks: &[EmbeddingChunk]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(model.as_bytes());
+    for chunk in chunks {
+        hasher.update(format!("\0{:?}\0{:?}\0", chunk.start, chunk.end).as_bytes());
+        hasher.update(chunk.text.as_bytes());
+    }
+    hex::encode(hasher.finalize())
+}
+
+/// Embeds recordings and memories that are new or changed since they were
+/// last indexed, or were indexed with a different model. Deleted recordings
+/// take their embeddings with them. A source that fails to embed is noted in
+/// the report and skipped, keeping whatever it had before.
+pub fn update_index(db: &Database, embedder: &Embedder) -> Result<IndexReport> {
+    let existing = db.embedding_hashes()?;
+    let mut report = IndexReport { model: embedder.model_id().to_string(), ..Default::default() };
+
+    let mut sources = Vec::new();
+    for record in db.get_all_audio_records()? {
+        let Some(id) = record.id.map(|id| id as i64) else { continue };
+        let segments = db.get_segments(Some(id), None, None)?;
+        sources.push((EmbeddingSource::Recording(id), record_chunks(&record, &segments)));
+    }
+    for memory in db.get_dwight_memory_context(i64::MAX as usize)? {
+        let Some(id) = memory.id.map(|id| id as i64) else { continue };
+        sources.push((EmbeddingSource::Memory(id), memory_chunks(&memory)));
+    }
+
+    for (source, chunks) in sources {
+        let hash = source_hash(embedder.model_id(), &chunks);
+        match existing.get(&source) {
+            Some(stored) if *stored == hash => {
+                report.up_to_date += 1;
+                continue;
+            }
+            None if chunks.is_empty() => continue,
+            _ => {}
+        }
+        let texts: Vec<String> = chunks.iter().map(|c| c.text.clone()).collect();
+        let vectors = match embedder.embed(&texts) {
+            Ok(vectors) => vectors,
+            Err(e) => {
+                report.errors.push(format!("Failed to embed {}: {}", source_label(source), e));
+                continue;
+            }
+        };
+        db.replace_embeddings(source, &hash, embedder.model_id(), &chunks, &vectors)?;
+        report.sources_indexed += 1;
+        report.chunks_embedded += chunks.len();
+    }
+    Ok(report)
+}
+
+/// The `top_k` stored chunks closest to `query`, best first.
+pub fn search(db: &Database, embedder: &Embedder, query: &str, top_k: usize) -> Result<Vec<RagHit>> {
+    let query_vector = embedder
+        .embed(&[query.to_string()])?
+        .pop()
+        .ok_or_else(|| anyhow!("Embedding model returned nothing"))?;
+
+    let mut scored: Vec<(f32, _)> = db
+        .load_embeddings(embedder.model_id())?
+        .into_iter()
+        .map(|stored| (embeddings::similarity(&query_vector, &stored.vector), stored))
+        .collect();
+    scored.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(Ordering::Equal));
+    scored.truncate(top_k);
+
+    Ok(scored
+        .into_iter()
+        .map(|(score, stored)| RagHit {
+            score,
+            source: stored.source,
+            citation: citation(stored.source, stored.title.as_deref(), &stored.chunk, &stored.created_at),
+            text: stored.chunk.text,
+            record_title: stored.title,
+            start: stored.chunk.start,
+            end: stored.chunk.end,
+            created_at: stored.created_at,
+        })
+        .collect())
+}
+
+/// Searches whatever has been indexed so far; indexing itself happens in the
+/// background as things are saved. Blocking; embedding the query is CPU-bound.
+pub fn retrieve(state: &AppState, query: &str, top_k: usize) -> Result<Vec<RagHit>> {
+    let query = query.trim();
+    if query.is_empty() {
+        return Err(anyhow!("Query cannot be empty"));
+    }
+    let embedder = state.embedder()?;
+    let db = state.db().map_err(|e| anyhow!(e))?;
+    search(&db, &embedder, query, top_k.clamp(1, MAX_TOP_K))
+}
+
+/// Keeps the index current for as long as the app is open: runs at startup,
+/// on unlock, and whenever transcripts or memories are saved (see
+/// [`AppState::request_index_update`]). Runs are skipped while storage is
+/// locked or no embedding model is installed.
+pub fn spawn_background_indexer(app_handle: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            app_handle.state::<AppState>().index_update_requested().await;
+
+            let handle = app_handle.clone();
+            let result = tokio::task::spawn_blocking(move || {
+                let state = handle.state::<AppState>();
+                if !embeddings::model_available(&embeddings::default_model_dir(state.app_config())) {
+                    return Ok(None);
+                }
+                let db = state.db().map_err(|e| anyhow!(e))?;
+                update_index(&db, &*state.embedder()?).map(Some)
+            })
+            .await;
+
+            match result {
+                Ok(Ok(Some(report))) => {
+                    for error in &report.errors {
+                        eprintln!("RAG indexing: {}", error);
+                    }
+                    if report.sources_indexed > 0 {
+                        println!("RAG indexing embedded {} sources", report.sources_indexed);
+                    }
+                }
+                Ok(Ok(None)) => {}
+                Ok(Err(e)) => eprintln!("RAG indexing skipped: {}", e),
+                Err(e) => eprintln!("RAG indexing failed: {}", e),
+            }
+        }
+    });
+}
+
+/// Embeds anything new or changed right away instead of waiting for the
+/// background indexer, e.g. to see the report after a big import.
+#[command]
+pub async fn update_rag_index(app_handle: AppHandle) -> Result<IndexReport, String> {
+    tokio::task::spawn_blocking(move || {
+        let state = app_handle.state::<AppState>();
+        let embedder = state.embedder().map_err(|e| format!("RAG error: {}", e))?;
+        let db = state.db()?;
+        update_index(&db, &embedder).map_err(|e| format!("RAG error: {}", e))
+    })
+    .await
+    .map_err(|e| format!("RAG error: {}", e))?
+}
+
+#[command]
+pub async fn get_rag_status(state: State<'_, AppState>) -> Result<RagStatus, String> {
+    let model_dir = embeddings::default_model_dir(state.app_config());
+    let db = state.db()?;
+    let (indexed_chunks, indexed_sources) = db.embedding_counts().map_err(|e| format!("Database error: {}", e))?;
+    Ok(RagStatus {
+        model_available: embeddings::model_available(&model_dir),
+        model_dir: model_dir.to_string_lossy().to_string(),
+        indexed_chunks,
+        indexed_sources,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(transcript: Option<&str>) -> AudioRecord {
+        AudioRecord {
+            id: Some(12),
+            title: "Kitchen".to_string(),
+            file_path: String::new(),
+            transcript: transcript.map(str::to_string),
+            duration: 60.0,
+            created_at: String::new(),
+            triggers: None,
+            tags: Vec::new(),
+            starred: false,
+            sha256: None,
+            capture_device: None,
+            captured_at: None,
+        }
+    }
+
+    fn segment(start: f64, end: f64, text: &str) -> StoredSegment {
+        StoredSegment { id: 0, record_id: 12, start, end, text: text.to_string(), confidence: 1.0 }
+    }
+
+    /// `count` distinct words, so chunk contents can be checked by index.
+    fn numbered_words(from: usize, count: usize) -> String {
+        (from..from + count).map(|i| format!("w{}", i)).collect::<Vec<_>>().join(" ")
+    }
+
+    fn chunk(start: Option<f64>, end: Option<f64>, text: &str) -> EmbeddingChunk {
+        EmbeddingChunk { start, end, text: text.to_string() }
+    }
+
+    #[test]
+    fn transcripts_without_segments_are_cut_every_chunk_of_words() {
+        let transcript = numbered_words(0, 2 * WORDS_PER_CHUNK + 5);
+        let chunks = record_chunks(&record(Some(&transcript)), &[]);
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks[0].text, numbered_words(0, WORDS_PER_CHUNK));
+        assert_eq!(chunks[1].text, numbered_words(WORDS_PER_CHUNK, WORDS_PER_CHUNK));
+        assert_eq!(chunks[2].text, numbered_words(2 * WORDS_PER_CHUNK, 5));
+        assert!(chunks.iter().all(|c| c.start.is_none() && c.end.is_none()));
+
+        assert!(record_chunks(&record(None), &[]).is_empty());
+        assert!(record_chunks(&record(Some("  \n ")), &[]).is_empty());
+    }
+
+    #[test]
+    fn segments_are_joined_until_a_chunk_reaches_the_word_limit() {
+        let half = WORDS_PER_CHUNK / 2;
+        let segments = [
+            segment(0.0, 5.0, &numbered_words(0, half)),
+            segment(5.0, 6.0, "   "),
+            segment(6.0, 10.0, &numbered_words(half, half)),
+            // Reached the limit exactly, so this starts a new chunk
+            segment(10.0, 12.0, &numbered_words(2 * half, 3)),
+            segment(12.0, 13.0, ""),
+        ];
+        let chunks = record_chunks(&record(Some("ignored when there are segments")), &segments);
+        assert_eq!(chunks.len(), 2);
+        assert_eq!((chunks[0].start, chunks[0].end), (Some(0.0), Some(10.0)));
+        assert_eq!(chunks[0].text, numbered_words(0, 2 * half));
+        assert_eq!((chunks[1].start, chunks[1].end), (Some(10.0), Some(12.0)));
+        assert_eq!(chunks[1].text, numbered_words(2 * half, 3));
+
+        // A single long segment is never split
+        let long = [segment(0.0, 30.0, &numbered_words(0, 3 * WORDS_PER_CHUNK))];
+        assert_eq!(record_chunks(&record(None), &long).len(), 1);
+
+        let blank = [segment(0.0, 1.0, " "), segment(1.0, 2.0, "")];
+        assert!(record_chunks(&record(None), &blank).is_empty());
+    }
+
+    #[test]
+    fn timestamps_show_hours_only_when_needed() {
+        assert_eq!(format_timestamp(0.0), "00:00");
+        assert_eq!(format_timestamp(65.9), "01:05");
+        assert_eq!(format_timestamp(3599.0), "59:59");
+        assert_eq!(format_timestamp(3600.0), "1:00:00");
+        assert_eq!(format_timestamp(36_125.0), "10:02:05");
+        assert_eq!(format_timestamp(-3.0), "00:00");
+    }
+
+    #[test]
+    fn citations_name_the_source() {
+        let timed = chunk(Some(65.0), Some(80.0), "");
+        let untimed = chunk(None, None, "");
+        assert_eq!(
+            citation(EmbeddingSource::Recording(12), Some("Kitchen"), &timed, "2024-05-01"),
+            "\"Kitchen\" (recording 12) at 01:05-01:20"
+        );
+        assert_eq!(
+            citation(EmbeddingSource::Recording(12), None, &untimed, "2024-05-01"),
+            "\"Untitled\" (recording 12)"
+        );
+        assert_eq!(
+            citation(EmbeddingSource::Memory(3), None, &untimed, "2024-05-01T10:00:00Z"),
+            "conversation 3 from 2024-05-01T10:00:00Z"
+        );
+    }
+
+    #[test]
+    fn source_hash_changes_with_model_text_and_times() {
+        let chunks = vec![chunk(Some(0.0), Some(5.0), "bears eat beets"), chunk(Some(5.0), Some(9.0), "battlestar")];
+        let hash = source_hash("minilm", &chunks);
+        assert_eq!(hash, source_hash("minilm", &chunks));
+        assert_eq!(hash.len(), 64);
+
+        assert_ne!(hash, source_hash("bge-small", &chunks));
+        let mut retimed = chunks.clone();
+        retimed[1].end = Some(9.5);
+        assert_ne!(hash, source_hash("minilm", &retimed));
+        let mut reworded = chunks.clone();
+        reworded[0].text = "bears eat beets.".to_string();
+        assert_ne!(hash, source_hash("minilm", &reworded));
+        // Moving a word across the chunk boundary is a change too
+        let regrouped = vec![chunk(Some(0.0), Some(5.0), "bears eat"), chunk(Some(5.0), Some(9.0), "beets battlestar")];
+        assert_ne!(hash, source_hash("minilm", &regrouped));
+        assert_ne!(hash, source_hash("minilm", &chunks[..1]));
+    }
+}
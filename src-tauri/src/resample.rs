@@ -0,0 +1,202 @@
+use rubato::{
+    Resampler, SincFixedIn, SincInterpolationParameters, SincInterpolationType, WindowFunction,
+};
+use std::path::Path;
+use tauri::{command, AppHandle, Manager};
+use anyhow::{anyhow, Result};
+use crate::audio_capture;
+use crate::audio_decode;
+use crate::database::AudioRecord;
+use crate::library;
+use crate::state::AppState;
+
+/// Whisper models expect 16 kHz mono input.
+pub const WHISPER_SAMPLE_RATE: u32 = 16000;
+
+const CHUNK_FRAMES: usize = 4096;
+
+/// Folds interleaved frames down to mono.
+///
+/// Channels are averaged, except that the LFE channel of a 5.1/7.1 layout is
+/// dropped since it carries no speech and skews the level.
+pub fn downmix(interleaved: &[f32], channels: u16) -> Vec<f32> {
+    let channels = channels.max(1) as usize;
+    if channels == 1 {
+        return interleaved.to_vec();
+    }
+
+    // WAVE/SMPTE order puts LFE fourth: FL FR FC LFE ...
+    let lfe = if channels == 6 || channels == 8 { Some(3) } else { None };
+    let used = if lfe.is_some() { channels - 1 } else { channels } as f32;
+
+    interleaved
+        .chunks_exact(channels)
+        .map(|frame| {
+            frame
+                .iter()
+                .enumerate()
+                .filter(|(ch, _)| Some(*ch) != lfe)
+                .map(|(_, s)| *s)
+                .sum::<f32>()
+                / used
+        })
+        .collect()
+}
+
+/// Band-limited sinc resampling of a mono signal.
+///
+/// The output lines up with the input to within a sample, and its length is
+/// exactly `len * to_rate / from_rate` (rounded).
+pub fn resample(samples: &[f32], from_rate: u32, to_rate: u32) -> Result<Vec<f32>> {
+    if from_rate == 0 || to_rate == 0 {
+        return Err(anyhow!("Invalid sample rate conversion {} -> {}", from_rate, to_rate));
+    }
+    if from_rate == to_rate || samples.is_empty() {
+        return Ok(samples.to_vec());
+    }
+
+    let ratio = to_rate as f64 / from_rate as f64;
+    let params = SincInterpolationParameters {
+        sinc_len: 256,
+        f_cutoff: 0.95,
+        interpolation: SincInterpolationType::Linear,
+        oversampling_factor: 256,
+        window: WindowFunction::BlackmanHarris2,
+    };
+    let mut resampler = SincFixedIn::<f32>::new(ratio, 1.0, params, CHUNK_FRAMES, 1)
+        .map_err(|e| anyhow!("Failed to create resampler: {}", e))?;
+
+    let expected = (samples.len() as f64 * ratio).round() as usize;
+    // SincFixedIn starts reading half a filter length before the first
+    // sample, so its output is already aligned; `output_delay()` reports the
+    // filter's length regardless and must not be trimmed again
+    let mut output = Vec::with_capacity(expected + CHUNK_FRAMES);
+
+    let mut chunks = samples.chunks_exact(CHUNK_FRAMES);
+    for chunk in chunks.by_ref() {
+        let out = resampler.process(&[chunk], None)?;
+        output.extend_from_slice(&out[0]);
+    }
+    let remainder = chunks.remainder();
+    if !remainder.is_empty() {
+        let out = resampler.process_partial(Some(&[remainder]), None)?;
+        output.extend_from_slice(&out[0]);
+    }
+    // Flush the filter tail until the end of the signal has come out
+    while output.len() < expected {
+        let out = resampler.process_partial::<&[f32]>(None, None)?;
+        if out[0].is_empty() {
+            break;
+        }
+        output.extend_from_slice(&out[0]);
+    }
+
+    output.resize(expected, 0.0);
+    Ok(output)
+}
+
+/// Converts any supported recording to a 16 kHz mono WAV and adds it to the
+/// library as a new recording, so it can be found, exported and expired like
+/// any other. Like every saved recording it is encrypted; use an export to
+/// take it out of the app. The source is left as it is.
+#[command]
+pub async fn convert_audio_for_whisper(file_path: String, app_handle: AppHandle) -> Result<AudioRecord, String> {
+    let output_dir = audio_capture::recordings_dir(&app_handle.config());
+
+    tokio::task::spawn_blocking(move || -> Result<AudioRecord> {
+        let db = app_handle.state::<AppState>().db().map_err(|e| anyhow!(e))?;
+        let decoded = audio_decode::decode_file(&file_path, Some(WHISPER_SAMPLE_RATE))?;
+        std::fs::create_dir_all(&output_dir)?;
+
+        let stem = Path::new(&file_path).file_stem().and_then(|s| s.to_str()).unwrap_or("audio");
+        let source = match db.find_record_by_path(&file_path)? {
+            Some(id) => db.get_audio_record(id)?,
+            None => None,
+        };
+        // Never overwrite an earlier conversion or a recording that happens to share the name
+        let output_path = library::unique_path(&output_dir, &format!("{}_16k.wav", stem));
+        let sha256 = audio_capture::save_wav(&output_path, &decoded.samples, decoded.sample_rate)?;
+
+        let mut record = AudioRecord {
+            id: None,
+            title: format!("{} (16 kHz)", source.as_ref().map_or(stem, |r| r.title.as_str())),
+            file_path: output_path.to_string_lossy().to_string(),
+            transcript: None,
+            duration: decoded.samples.len() as f64 / decoded.sample_rate as f64,
+            created_at: String::new(),
+            triggers: None,
+            tags: source.as_ref().map(|r| r.tags.clone()).unwrap_or_default(),
+            starred: false,
+            sha256: Some(sha256),
+            capture_device: source.as_ref().and_then(|r| r.capture_device.clone()),
+            captured_at: source.as_ref().and_then(|r| r.captured_at.clone()),
+        };
+        match db.save_audio_record(&record) {
+            Ok(id) => record.id = Some(id as i32),
+            Err(e) => {
+                let _ = std::fs::remove_file(&output_path);
+                return Err(e.into());
+            }
+        }
+        Ok(record)
+    })
+    .await
+    .map_err(|e| format!("Audio conversion error: {}", e))?
+    .map_err(|e| format!("Audio conversion error: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine(freq: f32, rate: u32, len: usize) -> Vec<f32> {
+        (0..len).map(|i| (2.0 * std::f32::consts::PI * freq * i as f32 / rate as f32).sin()).collect()
+    }
+
+    #[test]
+    fn downmix_averages_channels() {
+        assert_eq!(downmix(&[0.5, -0.5, 0.25], 1), [0.5, -0.5, 0.25]);
+        assert_eq!(downmix(&[1.0, 0.0, 0.5, 0.5], 2), [0.5, 0.5]);
+        // A trailing partial frame is dropped
+        assert_eq!(downmix(&[0.25, 0.5, 0.75, 1.0], 3), [0.5]);
+    }
+
+    #[test]
+    fn downmix_drops_lfe_from_surround_layouts() {
+        // FL FR FC LFE SL SR with a loud LFE that must not leak into the mix
+        let frame_51 = [0.5, 0.5, 0.5, 1.0, 0.5, 0.5];
+        assert_eq!(downmix(&frame_51, 6), [0.5]);
+        let frame_71 = [0.25, 0.25, 0.25, -1.0, 0.25, 0.25, 0.25, 0.25];
+        assert_eq!(downmix(&frame_71, 8), [0.25]);
+        // Other layouts have no LFE, so every channel counts
+        assert_eq!(downmix(&[0.0, 0.0, 0.0, 1.0], 4), [0.25]);
+    }
+
+    #[test]
+    fn resample_output_length_is_the_scaled_input_length() {
+        for (from, to, len) in [(48000, 16000, 48000), (44100, 16000, 44100), (8000, 16000, 1234), (22050, 16000, 100)] {
+            let out = resample(&sine(440.0, from, len), from, to).unwrap();
+            assert_eq!(out.len(), (len as f64 * to as f64 / from as f64).round() as usize, "{} -> {}", from, to);
+        }
+        assert_eq!(resample(&[0.1, 0.2], 16000, 16000).unwrap(), [0.1, 0.2]);
+        assert!(resample(&[], 48000, 16000).unwrap().is_empty());
+        assert!(resample(&[0.1], 0, 16000).is_err());
+    }
+
+    #[test]
+    fn resample_keeps_the_signal_in_place() {
+        // An impulse 0.1 s in must still be 0.1 s in afterwards, up or down
+        for from in [48000, 44100, 8000] {
+            let mut impulse = vec![0.0; from as usize];
+            impulse[from as usize / 10] = 1.0;
+            let out = resample(&impulse, from, 16000).unwrap();
+            let peak = (0..out.len()).max_by(|&a, &b| out[a].abs().total_cmp(&out[b].abs())).unwrap();
+            assert!(peak.abs_diff(1600) <= 1, "{} Hz: impulse moved to {}", from, peak);
+        }
+
+        // A tone well inside the passband keeps its level, start to end
+        let out = resample(&sine(440.0, 44100, 44100), 44100, 16000).unwrap();
+        let rms = (out[500..15500].iter().map(|s| s * s).sum::<f32>() / 15000.0).sqrt();
+        assert!((rms - std::f32::consts::FRAC_1_SQRT_2).abs() < 0.01, "rms {}", rms);
+    }
+}
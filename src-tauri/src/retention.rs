@@ -0,0 +1,375 @@
+use std::path::Path;
+use std::time::Duration;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tauri::{command, AppHandle, Manager, State};
+use crate::audio_capture;
+use crate::database::{self, AudioRecord, Database};
+use crate::state::AppState;
+
+const POLICY_FILE: &str = "retention_policy.json";
+
+/// Emitted with a [`RetentionReport`] after the background task removes anything.
+pub const RETENTION_EVENT: &str = "retention-cleanup";
+
+/// How often the background task wakes to see whether a run is due.
+const TICK: Duration = Duration::from_secs(60);
+
+/// Which recordings to expire. With no limits set nothing is ever removed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetentionPolicy {
+    /// Whether the background task enforces the policy
+    pub enabled: bool,
+    /// Remove recordings older than this many days
+    pub max_age_days: Option<u32>,
+    /// Remove the oldest recordings while their total size is above this
+    pub max_total_mb: Option<u64>,
+    /// Never remove recordings that fired a trigger
+    pub keep_triggered: bool,
+    /// Never remove starred recordings
+    pub keep_starred: bool,
+    pub interval_minutes: u32,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        RetentionPolicy {
+            enabled: true,
+            max_age_days: None,
+            max_total_mb: None,
+            keep_triggered: true,
+            keep_starred: true,
+            interval_minutes: 60,
+        }
+    }
+}
+
+impl RetentionPolicy {
+    pub fn load(app_config: &tauri::Config) -> Self {
+        let path = database::app_data_path(app_config).join(POLICY_FILE);
+        match std::fs::read_to_string(path) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+                eprintln!("Ignoring unreadable {}: {}", POLICY_FILE, e);
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+
+    pub fn save(&self, app_config: &tauri::Config) -> Result<()> {
+        let data_dir = database::app_data_path(app_config);
+        std::fs::create_dir_all(&data_dir)?;
+        std::fs::write(data_dir.join(POLICY_FILE), serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn validate(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        if self.max_age_days == Some(0) {
+            errors.push("Maximum age must be at least one day".to_string());
+        }
+        if self.max_total_mb == Some(0) {
+            errors.push("Maximum total size must be at least 1 MB".to_string());
+        }
+        if self.interval_minutes == 0 {
+            errors.push("Cleanup interval must be at least one minute".to_string());
+        }
+        errors
+    }
+
+    fn is_protected(&self, record: &AudioRecord) -> bool {
+        let triggered = record.triggers.as_deref().is_some_and(|t| !t.trim().is_empty());
+        (self.keep_starred && record.starred) || (self.keep_triggered && triggered)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RemovalReason {
+    MaxAge,
+    MaxTotalSize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetentionCandidate {
+    pub record_id: i64,
+    pub title: String,
+    pub file_path: String,
+    pub created_at: String,
+    pub size_bytes: u64,
+    pub reason: RemovalReason,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetentionReport {
+    pub dry_run: bool,
+    pub removed: Vec<RetentionCandidate>,
+    pub freed_bytes: u64,
+    /// Size of the recordings left afterwards
+    pub remaining_bytes: u64,
+    pub errors: Vec<String>,
+}
+
+/// Works out what the policy would remove, oldest first: everything past
+/// the maximum age, then the oldest of the rest until the total fits.
+/// Protected recordings count towards the total but are never chosen.
+/// Recordings imported from outside `recordings_dir` are only ever dropped
+/// from the library, so they don't count towards the total and are never
+/// chosen to make room.
+pub fn plan(
+    policy: &RetentionPolicy,
+    records: &[AudioRecord],
+    recordings_dir: &Path,
+    now: DateTime<Utc>,
+) -> (Vec<RetentionCandidate>, u64) {
+    let mut entries: Vec<_> = records
+        .iter()
+        .filter(|r| r.id.is_some())
+        .map(|r| {
+            let created = DateTime::parse_from_rfc3339(&r.created_at).ok().map(|t| t.with_timezone(&Utc));
+            let path = Path::new(&r.file_path);
+            let size = database::is_recording_file(path, recordings_dir)
+                .then(|| std::fs::metadata(path).map(|m| m.len()).unwrap_or(0));
+            (r, created, size)
+        })
+        .collect();
+    // Records with an unreadable date sort first but are never aged out
+    entries.sort_by_key(|(_, created, _)| *created);
+
+    let mut total: u64 = entries.iter().filter_map(|(_, _, size)| *size).sum();
+    let mut removed = Vec::new();
+    let mut remaining = Vec::new();
+
+    let cutoff = policy.max_age_days.map(|days| now - chrono::Duration::days(days as i64));
+    for (record, created, size) in entries {
+        let expired = matches!((cutoff, created), (Some(cutoff), Some(created)) if created < cutoff);
+        if expired && !policy.is_protected(record) {
+            total -= size.unwrap_or(0);
+            removed.push(candidate(record, size.unwrap_or(0), RemovalReason::MaxAge));
+        } else {
+            remaining.push((record, size));
+        }
+    }
+
+    if let Some(max_mb) = policy.max_total_mb {
+        let max_bytes = max_mb.saturating_mul(1024 * 1024);
+        for (record, size) in remaining {
+            if total <= max_bytes {
+                break;
+            }
+            let Some(size) = size else { continue };
+            if !policy.is_protected(record) {
+                total -= size;
+                removed.push(candidate(record, size, RemovalReason::MaxTotalSize));
+            }
+        }
+    }
+
+    (removed, total)
+}
+
+fn candidate(record: &AudioRecord, size_bytes: u64, reason: RemovalReason) -> RetentionCandidate {
+    RetentionCandidate {
+        record_id: record.id.unwrap_or_default() as i64,
+        title: record.title.clone(),
+        file_path: record.file_path.clone(),
+        created_at: record.created_at.clone(),
+        size_bytes,
+        reason,
+    }
+}
+
+/// Applies the policy, or with `dry_run` only reports what it would remove.
+pub fn enforce(db: &Database, policy: &RetentionPolicy, recordings_dir: &Path, dry_run: bool) -> Result<RetentionReport> {
+    let records = db.get_all_audio_records()?;
+    let (planned, mut remaining_bytes) = plan(policy, &records, recordings_dir, Utc::now());
+
+    let mut report = RetentionReport {
+        dry_run,
+        removed: Vec::new(),
+        freed_bytes: 0,
+        remaining_bytes,
+        errors: Vec::new(),
+    };
+    if dry_run {
+        report.freed_bytes = planned.iter().map(|c| c.size_bytes).sum();
+        report.removed = planned;
+        return Ok(report);
+    }
+
+    for candidate in planned {
+        let record = records.iter().find(|r| r.id.map(|id| id as i64) == Some(candidate.record_id));
+        let Some(record) = record else { continue };
+        match db.delete_audio_record_and_file(record, recordings_dir) {
+            Ok(()) => {
+                report.freed_bytes += candidate.size_bytes;
+                report.removed.push(candidate);
+            }
+            Err(e) => {
+                remaining_bytes += candidate.size_bytes;
+                report.errors.push(e.to_string());
+            }
+        }
+    }
+    report.remaining_bytes = remaining_bytes;
+    Ok(report)
+}
+
+/// Runs the saved policy every `interval_minutes` for as long as the app is
+/// open. The policy is re-read each time so changes apply without a restart;
+/// runs are skipped while storage is locked.
+pub fn spawn_background_task(app_handle: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut since_last_run = Duration::MAX;
+        loop {
+            tokio::time::sleep(TICK).await;
+            since_last_run = since_last_run.saturating_add(TICK);
+
+            let policy = RetentionPolicy::load(&app_handle.config());
+            let interval = Duration::from_secs(policy.interval_minutes.max(1) as u64 * 60);
+            if !policy.enabled || since_last_run < interval {
+                continue;
+            }
+            since_last_run = Duration::ZERO;
+
+            let handle = app_handle.clone();
+            let result = tokio::task::spawn_blocking(move || {
+                let db = handle.state::<AppState>().db().map_err(|e| anyhow!(e))?;
+                enforce(&db, &policy, &audio_capture::recordings_dir(&handle.config()), false)
+            })
+            .await;
+
+            match result {
+                Ok(Ok(report)) => {
+                    for error in &report.errors {
+                        eprintln!("Retention cleanup: {}", error);
+                    }
+                    if !report.removed.is_empty() {
+                        println!(
+                            "Retention cleanup removed {} recordings ({} bytes)",
+                            report.removed.len(),
+                            report.freed_bytes
+                        );
+                        if let Err(e) = app_handle.emit_all(RETENTION_EVENT, &report) {
+                            eprintln!("Failed to emit retention event: {}", e);
+                        }
+                    }
+                }
+                Ok(Err(e)) => eprintln!("Retention cleanup skipped: {}", e),
+                Err(e) => eprintln!("Retention cleanup failed: {}", e),
+            }
+        }
+    });
+}
+
+#[command]
+pub async fn get_retention_policy(state: State<'_, AppState>) -> Result<RetentionPolicy, String> {
+    Ok(RetentionPolicy::load(state.app_config()))
+}
+
+#[command]
+pub async fn set_retention_policy(policy: RetentionPolicy, state: State<'_, AppState>) -> Result<RetentionPolicy, String> {
+    let errors = policy.validate();
+    if !errors.is_empty() {
+        return Err(format!("Invalid retention policy: {}", errors.join("; ")));
+    }
+    policy
+        .save(state.app_config())
+        .map_err(|e| format!("Failed to save retention policy: {}", e))?;
+    Ok(policy)
+}
+
+/// What the saved policy (or `policy`, to try one out before saving) would
+/// remove right now. Nothing is deleted.
+#[command]
+pub async fn preview_retention(policy: Option<RetentionPolicy>, app_handle: AppHandle) -> Result<RetentionReport, String> {
+    run(app_handle, policy, true).await
+}
+
+/// Applies the saved policy immediately, whether or not the background task
+/// is enabled.
+#[command]
+pub async fn run_retention_now(app_handle: AppHandle) -> Result<RetentionReport, String> {
+    run(app_handle, None, false).await
+}
+
+async fn run(app_handle: AppHandle, policy: Option<RetentionPolicy>, dry_run: bool) -> Result<RetentionReport, String> {
+    let policy = policy.unwrap_or_else(|| RetentionPolicy::load(&app_handle.config()));
+    let errors = policy.validate();
+    if !errors.is_empty() {
+        return Err(format!("Invalid retention policy: {}", errors.join("; ")));
+    }
+
+    tokio::task::spawn_blocking(move || {
+        let db = app_handle.state::<AppState>().db()?;
+        let recordings_dir = audio_capture::recordings_dir(&app_handle.config());
+        enforce(&db, &policy, &recordings_dir, dry_run).map_err(|e| format!("Retention error: {}", e))
+    })
+    .await
+    .map_err(|e| format!("Retention error: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(id: i32, file_path: &Path, created_at: &str) -> AudioRecord {
+        AudioRecord {
+            id: Some(id),
+            title: format!("Recording {}", id),
+            file_path: file_path.to_string_lossy().into_owned(),
+            transcript: None,
+            duration: 1.0,
+            created_at: created_at.to_string(),
+            triggers: None,
+            tags: Vec::new(),
+            starred: false,
+            sha256: None,
+            capture_device: None,
+            captured_at: None,
+        }
+    }
+
+    #[test]
+    fn files_outside_recordings_are_not_counted_or_chosen_for_space() {
+        let root = std::env::temp_dir().join(format!("dwight-retention-{}", std::process::id()));
+        let app_data = root.join("app");
+        let recordings = app_data.join("recordings");
+        let external = root.join("music");
+        std::fs::create_dir_all(&recordings).unwrap();
+        std::fs::create_dir_all(&external).unwrap();
+        let own = recordings.join("own.wav");
+        let theirs = external.join("theirs.wav");
+        let escaping = recordings.join("..").join("..").join("music").join("theirs.wav");
+        let database = app_data.join("dwight.db");
+        std::fs::write(&own, vec![0u8; 2 * 1024 * 1024]).unwrap();
+        std::fs::write(&theirs, vec![0u8; 4 * 1024 * 1024]).unwrap();
+        std::fs::write(&database, vec![0u8; 1024 * 1024]).unwrap();
+
+        let records = [
+            record(1, &theirs, "2020-01-01T00:00:00Z"),
+            record(2, &escaping, "2020-01-02T00:00:00Z"),
+            record(3, &own, "2024-01-01T00:00:00Z"),
+            record(4, &database, "2020-01-03T00:00:00Z"),
+        ];
+        let now = DateTime::parse_from_rfc3339("2024-01-02T00:00:00Z").unwrap().with_timezone(&Utc);
+
+        let policy = RetentionPolicy { max_total_mb: Some(1), ..RetentionPolicy::default() };
+        let (removed, remaining) = plan(&policy, &records, &recordings, now);
+        assert_eq!(removed.iter().map(|c| c.record_id).collect::<Vec<_>>(), [3]);
+        assert_eq!(remaining, 0);
+
+        // Old external recordings still leave the library, but free nothing
+        let policy = RetentionPolicy { max_age_days: Some(30), ..RetentionPolicy::default() };
+        let (removed, remaining) = plan(&policy, &records, &recordings, now);
+        assert_eq!(
+            removed.iter().map(|c| (c.record_id, c.size_bytes)).collect::<Vec<_>>(),
+            [(1, 0), (2, 0), (4, 0)]
+        );
+        assert_eq!(remaining, 2 * 1024 * 1024);
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,183 @@
+use std::sync::{Arc, Mutex, MutexGuard, RwLock};
+use anyhow::{anyhow, Result};
+use tokio::sync::Notify;
+use crate::ai::DwightAI;
+use crate::ai_models::AdvancedAI;
+use crate::database::{self, Database, DbPool};
+use crate::embeddings::{self, Embedder};
+use crate::python_integration::PythonRunner;
+use crate::vault::{Key, StorageStatus, Vault};
+use crate::whisper::{WhisperConfig, WhisperEngine};
+
+/// Long-lived services shared by every command: the database pool and the
+/// engines, built once at startup and handed out through `tauri::State`.
+/// The pool only exists while the vault is unlocked.
+pub struct AppState {
+    app_config: Arc<tauri::Config>,
+    vault: Mutex<Vault>,
+    pool: RwLock<Option<DbPool>>,
+    whisper: RwLock<Arc<WhisperEngine>>,
+    /// Loaded on first use; the model is large and many sessions never need it
+    embedder: Mutex<Option<Arc<Embedder>>>,
+    /// Wakes the background RAG indexer; see [`crate::rag::spawn_background_indexer`]
+    index_requested: Notify,
+    pub dwight: DwightAI,
+    pub advanced_ai: AdvancedAI,
+    pub python: PythonRunner,
+}
+
+impl AppState {
+    pub fn new(app_config: Arc<tauri::Config>) -> Result<Self> {
+        let vault = Vault::open(&database::app_data_path(&app_config));
+        let pool = match vault.database_key() {
+            Some(key) => Some(Database::open_pool(&app_config, &key)?),
+            None => None,
+        };
+        let whisper = WhisperEngine::with_config(WhisperConfig::load(&app_config));
+        let advanced_ai = AdvancedAI::new(&app_config);
+        // Catch up on anything saved while the app was closed
+        let index_requested = Notify::new();
+        index_requested.notify_one();
+
+        Ok(AppState {
+            app_config,
+            vault: Mutex::new(vault),
+            pool: RwLock::new(pool),
+            whisper: RwLock::new(Arc::new(whisper)),
+            embedder: Mutex::new(None),
+            index_requested,
+            dwight: DwightAI::new(),
+            advanced_ai,
+            python: PythonRunner::new(),
+        })
+    }
+
+    pub fn app_config(&self) -> &tauri::Config {
+        &self.app_config
+    }
+
+    /// Checks a connection out of the pool; it goes back when dropped.
+    pub fn db(&self) -> Result<Database, String> {
+        let pool = match self.pool.read() {
+            Ok(pool) => pool.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        };
+        let pool = pool.ok_or_else(|| "Storage is locked; unlock it to use the database".to_string())?;
+        Database::from_pool(&pool).map_err(|e| format!("Database error: {}", e))
+    }
+
+    fn vault(&self) -> MutexGuard<'_, Vault> {
+        match self.vault.lock() {
+            Ok(vault) => vault,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    fn set_pool(&self, pool: Option<DbPool>) {
+        match self.pool.write() {
+            Ok(mut current) => *current = pool,
+            Err(poisoned) => *poisoned.into_inner() = pool,
+        }
+    }
+
+    /// SQLCipher key for `dwight.db`, while unlocked.
+    pub fn database_key(&self) -> Option<Key> {
+        self.vault().database_key()
+    }
+
+    pub fn storage_status(&self) -> StorageStatus {
+        self.vault().status()
+    }
+
+    pub fn unlock_storage(&self, passphrase: Option<&str>) -> Result<(), String> {
+        let mut vault = self.vault();
+        vault.unlock(passphrase).map_err(|e| format!("Storage error: {}", e))?;
+        self.open_database(&mut vault)
+    }
+
+    /// Closes the database and forgets the keys. Connections already checked
+    /// out finish their work first.
+    pub fn lock_storage(&self) {
+        let mut vault = self.vault();
+        self.set_pool(None);
+        vault.lock();
+    }
+
+    pub fn change_passphrase(&self, current: Option<&str>, new: Option<&str>) -> Result<(), String> {
+        let mut vault = self.vault();
+        let was_unlocked = vault.is_unlocked();
+        vault.change_passphrase(current, new).map_err(|e| format!("Storage error: {}", e))?;
+        if was_unlocked {
+            Ok(())
+        } else {
+            self.open_database(&mut vault)
+        }
+    }
+
+    fn open_database(&self, vault: &mut Vault) -> Result<(), String> {
+        let opened = vault
+            .database_key()
+            .ok_or_else(|| anyhow!("Storage is locked"))
+            .and_then(|key| Database::open_pool(&self.app_config, &key));
+        match opened {
+            Ok(pool) => {
+                self.set_pool(Some(pool));
+                self.request_index_update();
+                Ok(())
+            }
+            Err(e) => {
+                vault.lock();
+                Err(format!("Database error: {}", e))
+            }
+        }
+    }
+
+    /// The current Whisper engine. Callers keep their `Arc` for the duration
+    /// of a transcription even if the configuration changes meanwhile.
+    pub fn whisper(&self) -> Arc<WhisperEngine> {
+        match self.whisper.read() {
+            Ok(engine) => engine.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        }
+    }
+
+    /// Swaps in an engine for a new configuration, dropping any loaded model.
+    pub fn set_whisper_config(&self, config: WhisperConfig) {
+        let engine = Arc::new(WhisperEngine::with_config(config));
+        match self.whisper.write() {
+            Ok(mut current) => *current = engine,
+            Err(poisoned) => *poisoned.into_inner() = engine,
+        }
+    }
+
+    /// Asks the background indexer to embed new or changed transcripts and
+    /// memories. Requests made while it is busy are folded into one more run.
+    pub fn request_index_update(&self) {
+        self.index_requested.notify_one();
+    }
+
+    pub async fn index_update_requested(&self) {
+        self.index_requested.notified().await;
+    }
+
+    /// The sentence embedding model, loading it on first use.
+    pub fn embedder(&self) -> Result<Arc<Embedder>> {
+        let mut embedder = match self.embedder.lock() {
+            Ok(embedder) => embedder,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        if let Some(loaded) = embedder.as_ref() {
+            return Ok(loaded.clone());
+        }
+        let model_dir = embeddings::default_model_dir(&self.app_config);
+        if !embeddings::model_available(&model_dir) {
+            return Err(anyhow!(
+                "No embedding model in {} (need config.json, tokenizer.json and model.safetensors, e.g. from sentence-transformers/all-MiniLM-L6-v2)",
+                model_dir.display()
+            ));
+        }
+        let loaded = Arc::new(Embedder::load(&model_dir)?);
+        *embedder = Some(loaded.clone());
+        Ok(loaded)
+    }
+}
@@ -0,0 +1,458 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Mutex;
+use anyhow::{anyhow, Result};
+use crate::audio_capture;
+use crate::audio_decode;
+use crate::resample::WHISPER_SAMPLE_RATE;
+use crate::vad;
+use crate::whisper::{TranscriptionResult, TranscriptionSegment, WhisperConfig};
+use crate::whisper_candle::{self, CandleWhisper};
+
+/// The transcription engines Dwight can run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackendKind {
+    /// whisper.cpp command line binary
+    WhisperCppCli,
+    /// whisper.cpp `server` example over HTTP
+    WhisperCppServer,
+    /// In-process Whisper on candle
+    Candle,
+    /// Deterministic canned output for development and tests
+    Mock,
+}
+
+impl BackendKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BackendKind::WhisperCppCli => "whisper_cpp_cli",
+            BackendKind::WhisperCppServer => "whisper_cpp_server",
+            BackendKind::Candle => "candle",
+            BackendKind::Mock => "mock",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "whisper_cpp_cli" | "cli" | "cpp" => Some(BackendKind::WhisperCppCli),
+            "whisper_cpp_server" | "server" | "http" => Some(BackendKind::WhisperCppServer),
+            "candle" | "native" => Some(BackendKind::Candle),
+            "mock" => Some(BackendKind::Mock),
+            _ => None,
+        }
+    }
+}
+
+/// A speech-to-text engine. Implementations are blocking and are driven from
+/// `spawn_blocking` so they may shell out, do HTTP or run inference inline.
+pub trait TranscriptionBackend: Send + Sync {
+    fn kind(&self) -> BackendKind;
+
+    fn transcribe(&self, file_path: &str, config: &WhisperConfig) -> Result<TranscriptionResult>;
+
+    /// Cheap check used by `get_whisper_status`; must not run a transcription.
+    fn is_available(&self, config: &WhisperConfig) -> bool;
+}
+
+pub fn create_backend(kind: BackendKind) -> Box<dyn TranscriptionBackend> {
+    match kind {
+        BackendKind::WhisperCppCli => Box::new(WhisperCppCliBackend),
+        BackendKind::WhisperCppServer => Box::new(WhisperCppServerBackend),
+        BackendKind::Candle => Box::new(CandleBackend::default()),
+        BackendKind::Mock => Box::new(MockBackend),
+    }
+}
+
+/// Speech covering at least this share of a file is transcribed as-is.
+const MOSTLY_SPEECH_RATIO: f64 = 0.9;
+/// Context kept around each speech region when silence is cut out.
+const SPEECH_PADDING_SECONDS: f64 = 0.2;
+
+/// Runs `backend` on only the speech in `file_path`, mapping segment times
+/// back onto the original recording. If the VAD finds no speech at all the
+/// whole file is transcribed, since quiet or distant speech can fall below
+/// its fixed threshold.
+pub fn transcribe_speech_only(
+    backend: &dyn TranscriptionBackend,
+    file_path: &str,
+    config: &WhisperConfig,
+) -> Result<TranscriptionResult> {
+    let start_time = std::time::Instant::now();
+    let decoded = audio_decode::decode_file(file_path, Some(WHISPER_SAMPLE_RATE))?;
+    let regions = vad::detect_speech(&decoded.samples, decoded.sample_rate);
+
+    if regions.is_empty() {
+        return backend.transcribe(file_path, config);
+    }
+
+    let speech_seconds: f64 = regions.iter().map(|r| r.end - r.start).sum();
+    if speech_seconds >= decoded.duration_seconds() * MOSTLY_SPEECH_RATIO {
+        return backend.transcribe(file_path, config);
+    }
+
+    let (compact, map) = vad::compact_speech(&decoded.samples, decoded.sample_rate, &regions, SPEECH_PADDING_SECONDS);
+    let speech_path = std::env::temp_dir().join(format!(
+        "dwight_speech_{}_{}.wav",
+        std::process::id(),
+        chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default()
+    ));
+    audio_capture::write_wav(&speech_path, &compact, decoded.sample_rate)?;
+    let result = backend.transcribe(&speech_path.to_string_lossy(), config);
+    let _ = std::fs::remove_file(&speech_path);
+
+    let mut result = result?;
+    for segment in &mut result.segments {
+        segment.start = map.to_source(segment.start);
+        segment.end = map.to_source(segment.end).max(segment.start);
+    }
+    result.processing_time_ms = start_time.elapsed().as_millis() as u64;
+    Ok(result)
+}
+
+fn build_result(
+    kind: BackendKind,
+    segments: Vec<TranscriptionSegment>,
+    text: Option<String>,
+    language: String,
+    confidence: f32,
+    start_time: std::time::Instant,
+) -> TranscriptionResult {
+    let text = text.unwrap_or_else(|| {
+        segments
+            .iter()
+            .map(|segment| segment.text.trim())
+            .collect::<Vec<_>>()
+            .join(" ")
+    });
+
+    TranscriptionResult {
+        text,
+        segments,
+        language,
+        processing_time_ms: start_time.elapsed().as_millis() as u64,
+        confidence,
+        backend: kind.as_str().to_string(),
+        simulated: kind == BackendKind::Mock,
+    }
+}
+
+/// Parses both whisper.cpp CLI `--output-json` files and the server's
+/// `verbose_json` responses into segments plus detected language.
+fn parse_whisper_cpp_json(value: &serde_json::Value) -> (Vec<TranscriptionSegment>, Option<String>, Option<String>) {
+    let mut segments = Vec::new();
+
+    // CLI format: {"result": {"language"}, "transcription": [{"offsets": {"from", "to"}, "text"}]}
+    if let Some(items) = value["transcription"].as_array() {
+        for item in items {
+            segments.push(TranscriptionSegment {
+                start: item["offsets"]["from"].as_f64().unwrap_or(0.0) / 1000.0,
+                end: item["offsets"]["to"].as_f64().unwrap_or(0.0) / 1000.0,
+                text: item["text"].as_str().unwrap_or("").trim().to_string(),
+                confidence: item["confidence"].as_f64().unwrap_or(0.0) as f32,
+            });
+        }
+    }
+
+    // Server verbose_json format: {"text", "language", "segments": [{"start", "end", "text", "avg_logprob"}]}
+    if let Some(items) = value["segments"].as_array() {
+        for item in items {
+            let confidence = item["avg_logprob"]
+                .as_f64()
+                .map(f64::exp)
+                .or_else(|| item["confidence"].as_f64())
+                .unwrap_or(0.0);
+            segments.push(TranscriptionSegment {
+                start: item["start"].as_f64().unwrap_or(0.0),
+                end: item["end"].as_f64().unwrap_or(0.0),
+                text: item["text"].as_str().unwrap_or("").trim().to_string(),
+                confidence: confidence as f32,
+            });
+        }
+    }
+
+    let language = value["result"]["language"]
+        .as_str()
+        .or_else(|| value["language"].as_str())
+        .map(|s| s.to_string());
+    let text = value["text"].as_str().map(|s| s.trim().to_string());
+
+    (segments, text, language)
+}
+
+fn mean_confidence(segments: &[TranscriptionSegment]) -> f32 {
+    if segments.is_empty() {
+        return 0.0;
+    }
+    segments.iter().map(|s| s.confidence).sum::<f32>() / segments.len() as f32
+}
+
+/// whisper.cpp only reads 16 kHz WAV; anything else is transcoded to a
+/// temporary file that is removed when this is dropped.
+struct WhisperCppInput {
+    path: PathBuf,
+    temporary: bool,
+}
+
+impl WhisperCppInput {
+    fn prepare(file_path: &str) -> Result<Self> {
+        let is_16k_wav = hound::WavReader::open(file_path)
+            .map(|reader| reader.spec().sample_rate == 16000)
+            .unwrap_or(false);
+        if is_16k_wav {
+            return Ok(Self { path: PathBuf::from(file_path), temporary: false });
+        }
+
+        let decoded = audio_decode::decode_file(file_path, Some(16000))?;
+        let path = std::env::temp_dir().join(format!(
+            "dwight_whisper_input_{}_{}.wav",
+            std::process::id(),
+            chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default()
+        ));
+        audio_capture::write_wav(&path, &decoded.samples, decoded.sample_rate)?;
+        Ok(Self { path, temporary: true })
+    }
+}
+
+impl Drop for WhisperCppInput {
+    fn drop(&mut self) {
+        if self.temporary {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+pub struct WhisperCppCliBackend;
+
+impl TranscriptionBackend for WhisperCppCliBackend {
+    fn kind(&self) -> BackendKind {
+        BackendKind::WhisperCppCli
+    }
+
+    fn transcribe(&self, file_path: &str, config: &WhisperConfig) -> Result<TranscriptionResult> {
+        let start_time = std::time::Instant::now();
+        let input = WhisperCppInput::prepare(file_path)?;
+        let output_base = std::env::temp_dir().join(format!(
+            "dwight_whisper_{}_{}",
+            std::process::id(),
+            chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default()
+        ));
+
+        let mut cmd = Command::new(&config.cli_path);
+        cmd.arg("-m").arg(format!("{}/ggml-{}.bin", config.model_path, config.model_size))
+           .arg("-f").arg(&input.path)
+           .arg("--output-json")
+           .arg("--output-file").arg(&output_base);
+
+        if let Some(lang) = &config.language {
+            cmd.arg("-l").arg(lang);
+        }
+
+        if !config.use_gpu {
+            cmd.arg("--no-gpu");
+        }
+
+        let output = cmd.output()
+            .map_err(|e| anyhow!("Failed to run {}: {}", config.cli_path, e))?;
+        if !output.status.success() {
+            return Err(anyhow!(
+                "whisper.cpp exited with {}: {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+
+        let json_path = output_base.with_extension("json");
+        let json_content = std::fs::read_to_string(&json_path)?;
+        let _ = std::fs::remove_file(&json_path);
+        let value: serde_json::Value = serde_json::from_str(&json_content)?;
+
+        let (segments, text, language) = parse_whisper_cpp_json(&value);
+        let confidence = mean_confidence(&segments);
+        let language = language
+            .or_else(|| config.language.clone())
+            .unwrap_or_else(|| "en".to_string());
+
+        Ok(build_result(self.kind(), segments, text, language, confidence, start_time))
+    }
+
+    fn is_available(&self, config: &WhisperConfig) -> bool {
+        Command::new(&config.cli_path).arg("--help").output().is_ok()
+    }
+}
+
+pub struct WhisperCppServerBackend;
+
+impl WhisperCppServerBackend {
+    fn base_url(config: &WhisperConfig) -> Result<String> {
+        config
+            .server_url
+            .as_ref()
+            .map(|url| url.trim_end_matches('/').to_string())
+            .ok_or_else(|| anyhow!("No whisper.cpp server URL configured"))
+    }
+}
+
+impl TranscriptionBackend for WhisperCppServerBackend {
+    fn kind(&self) -> BackendKind {
+        BackendKind::WhisperCppServer
+    }
+
+    fn transcribe(&self, file_path: &str, config: &WhisperConfig) -> Result<TranscriptionResult> {
+        let start_time = std::time::Instant::now();
+        let url = format!("{}/inference", Self::base_url(config)?);
+        let input = WhisperCppInput::prepare(file_path)?;
+
+        let mut form = reqwest::blocking::multipart::Form::new()
+            .text("response_format", "verbose_json")
+            .text("temperature", "0.0")
+            .file("file", &input.path)?;
+        if let Some(lang) = &config.language {
+            form = form.text("language", lang.clone());
+        }
+
+        let response = reqwest::blocking::Client::new()
+            .post(&url)
+            .multipart(form)
+            .send()?;
+        if !response.status().is_success() {
+            return Err(anyhow!("whisper.cpp server returned {}", response.status()));
+        }
+
+        let value: serde_json::Value = response.json()?;
+        let (segments, text, language) = parse_whisper_cpp_json(&value);
+        let confidence = mean_confidence(&segments);
+        let language = language
+            .or_else(|| config.language.clone())
+            .unwrap_or_else(|| "en".to_string());
+
+        Ok(build_result(self.kind(), segments, text, language, confidence, start_time))
+    }
+
+    fn is_available(&self, config: &WhisperConfig) -> bool {
+        let Ok(base_url) = Self::base_url(config) else {
+            return false;
+        };
+        reqwest::blocking::Client::builder()
+            .timeout(std::time::Duration::from_secs(2))
+            .build()
+            .and_then(|client| client.get(&base_url).send())
+            .is_ok()
+    }
+}
+
+/// Keeps the loaded model around so repeated transcriptions skip weight loading.
+#[derive(Default)]
+pub struct CandleBackend {
+    loaded: Mutex<Option<(String, CandleWhisper)>>,
+}
+
+impl TranscriptionBackend for CandleBackend {
+    fn kind(&self) -> BackendKind {
+        BackendKind::Candle
+    }
+
+    fn transcribe(&self, file_path: &str, config: &WhisperConfig) -> Result<TranscriptionResult> {
+        let start_time = std::time::Instant::now();
+        let model_dir = CandleWhisper::resolve_model_dir(&config.model_path, &config.model_size);
+        let model_key = format!("{}|{}", model_dir.display(), config.use_gpu);
+        let samples = whisper_candle::load_pcm_16k(file_path)?;
+
+        let mut loaded = self.loaded.lock().map_err(|_| anyhow!("Whisper model lock poisoned"))?;
+        if loaded.as_ref().map(|(key, _)| key != &model_key).unwrap_or(true) {
+            *loaded = Some((model_key, CandleWhisper::load(&model_dir, config.use_gpu)?));
+        }
+        let (_, model) = loaded.as_mut().expect("model loaded above");
+
+        let (segments, language, confidence) = model.transcribe(&samples, config.language.as_deref())?;
+        Ok(build_result(self.kind(), segments, None, language, confidence, start_time))
+    }
+
+    fn is_available(&self, config: &WhisperConfig) -> bool {
+        whisper_candle::model_available(&config.model_path, &config.model_size)
+    }
+}
+
+/// Canned transcripts keyed off the file name. Results are always flagged as
+/// simulated so they can't be mistaken for real output.
+pub struct MockBackend;
+
+impl TranscriptionBackend for MockBackend {
+    fn kind(&self) -> BackendKind {
+        BackendKind::Mock
+    }
+
+    fn transcribe(&self, file_path: &str, _config: &WhisperConfig) -> Result<TranscriptionResult> {
+        let start_time = std::time::Instant::now();
+        let file_name = Path::new(file_path)
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("unknown");
+
+        let (text, segments) = if file_name.to_lowercase().contains("conversation") {
+            let text = "Speaker 1: Hello, how are you today?\nSpeaker 2: I'm doing well, thanks for asking. How about you?\nSpeaker 1: Pretty good, just working on some audio analysis projects.\nSpeaker 2: That sounds interesting. What kind of analysis are you doing?";
+            let segments = vec![
+                TranscriptionSegment {
+                    start: 0.0,
+                    end: 2.5,
+                    text: "Hello, how are you today?".to_string(),
+                    confidence: 0.92,
+                },
+                TranscriptionSegment {
+                    start: 3.0,
+                    end: 6.8,
+                    text: "I'm doing well, thanks for asking. How about you?".to_string(),
+                    confidence: 0.88,
+                },
+                TranscriptionSegment {
+                    start: 7.2,
+                    end: 11.1,
+                    text: "Pretty good, just working on some audio analysis projects.".to_string(),
+                    confidence: 0.90,
+                },
+                TranscriptionSegment {
+                    start: 11.5,
+                    end: 14.8,
+                    text: "That sounds interesting. What kind of analysis are you doing?".to_string(),
+                    confidence: 0.87,
+                },
+            ];
+            (text.to_string(), segments)
+        } else if file_name.to_lowercase().contains("security") {
+            let text = "Radio chatter detected. Multiple voices discussing checkpoint procedures. Keywords: security, perimeter, all clear, proceed with caution.";
+            let segments = vec![
+                TranscriptionSegment {
+                    start: 0.0,
+                    end: 5.2,
+                    text: "Radio chatter detected. Multiple voices discussing checkpoint procedures.".to_string(),
+                    confidence: 0.79,
+                },
+                TranscriptionSegment {
+                    start: 5.5,
+                    end: 9.8,
+                    text: "Keywords: security, perimeter, all clear, proceed with caution.".to_string(),
+                    confidence: 0.82,
+                },
+            ];
+            (text.to_string(), segments)
+        } else {
+            let text = format!("[Simulated transcript] Mock transcription of audio file: {}", file_name);
+            let segments = vec![
+                TranscriptionSegment {
+                    start: 0.0,
+                    end: 3.0,
+                    text: text.clone(),
+                    confidence: 0.85,
+                },
+            ];
+            (text, segments)
+        };
+
+        Ok(build_result(self.kind(), segments, Some(text), "en".to_string(), 0.82, start_time))
+    }
+
+    fn is_available(&self, _config: &WhisperConfig) -> bool {
+        true
+    }
+}
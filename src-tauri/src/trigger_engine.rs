@@ -0,0 +1,794 @@
+use tauri::{command, AppHandle, Manager, State};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use anyhow::{anyhow, Result};
+use crate::audio_analysis::{self, FrameFeatures, FRAME_SECONDS, HOP_SECONDS};
+use crate::audio_capture::{self, AudioCapture};
+use crate::database::{AudioRecord, SoundTrigger};
+use crate::resample::{self, WHISPER_SAMPLE_RATE};
+use crate::state::AppState;
+use crate::transcription::BackendKind;
+use crate::vad::{StreamingVad, VadEvent};
+
+const TICK: Duration = Duration::from_millis(250);
+/// How often active triggers are re-read so edits apply without a restart.
+const RELOAD_INTERVAL: Duration = Duration::from_secs(5);
+/// Audio kept for sound classification; long enough for a stable noise floor.
+const ANALYSIS_WINDOW_SECONDS: f64 = 10.0;
+/// Events this close to the end of the window may still be in progress.
+const SETTLE_SECONDS: f64 = 0.1;
+/// A trigger won't fire again within this long of its last match.
+const COOLDOWN: Duration = Duration::from_secs(5);
+/// Longest utterance sent for keyword transcription.
+const MAX_UTTERANCE_SECONDS: f64 = 30.0;
+
+const DEFAULT_PRE_ROLL_SECONDS: f64 = 10.0;
+const DEFAULT_POST_ROLL_SECONDS: f64 = 5.0;
+
+/// Tauri event sent the moment a trigger matches.
+pub const TRIGGER_FIRED_EVENT: &str = "trigger-fired";
+/// Tauri event sent once a trigger's clip has been saved.
+pub const TRIGGER_CLIP_EVENT: &str = "trigger-clip-saved";
+/// Tauri event carrying each utterance transcribed for keyword matching.
+pub const LIVE_TRANSCRIPT_EVENT: &str = "live-transcript";
+
+/// Everyday names mapped onto the classes produced by
+/// `audio_analysis::classify_sound` (plus `speech` from the VAD).
+const SOUND_ALIASES: [(&str, &str); 20] = [
+    ("knock", "impulse"),
+    ("bang", "impulse"),
+    ("slam", "impulse"),
+    ("clap", "impulse"),
+    ("crash", "impulse"),
+    ("gunshot", "impulse"),
+    ("alarm", "tonal"),
+    ("beep", "tonal"),
+    ("siren", "tonal"),
+    ("whistle", "tonal"),
+    ("ring", "tonal"),
+    ("doorbell", "tonal"),
+    ("hiss", "noise"),
+    ("static", "noise"),
+    ("wind", "noise"),
+    ("water", "noise"),
+    ("rain", "noise"),
+    ("voice", "speech"),
+    ("talking", "speech"),
+    ("speaking", "speech"),
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TriggerFired {
+    pub trigger_id: Option<i32>,
+    pub trigger_type: String,
+    pub trigger_value: String,
+    /// Sound class or transcript that matched
+    pub matched: String,
+    pub confidence: f32,
+    pub detected_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TriggerClipSaved {
+    pub record: AudioRecord,
+    pub fired: Vec<TriggerFired>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TriggerEngineStatus {
+    pub is_running: bool,
+    pub active_triggers: usize,
+    pub pre_roll_seconds: f64,
+    pub post_roll_seconds: f64,
+    /// Whether spoken keywords can be matched with the current Whisper setup
+    pub keyword_transcription: bool,
+    pub fired_count: u64,
+    pub last_fired: Option<TriggerFired>,
+    pub last_error: Option<String>,
+}
+
+impl TriggerEngineStatus {
+    fn stopped() -> Self {
+        TriggerEngineStatus {
+            is_running: false,
+            active_triggers: 0,
+            pre_roll_seconds: 0.0,
+            post_roll_seconds: 0.0,
+            keyword_transcription: false,
+            fired_count: 0,
+            last_fired: None,
+            last_error: None,
+        }
+    }
+}
+
+struct EngineSession {
+    status: Arc<Mutex<TriggerEngineStatus>>,
+    stop_tx: Sender<()>,
+    thread: JoinHandle<()>,
+}
+
+/// Background evaluation of the stored `SoundTrigger` rules against live
+/// capture. Managed as Tauri state alongside [`AudioCapture`], whose buffer it
+/// reads; capture has to be running for anything to fire.
+#[derive(Default)]
+pub struct TriggerEngine {
+    session: Mutex<Option<EngineSession>>,
+}
+
+impl TriggerEngine {
+    pub fn start(&self, app_handle: AppHandle, pre_roll_seconds: f64, post_roll_seconds: f64) -> Result<TriggerEngineStatus> {
+        let mut session = self.session.lock().map_err(|_| anyhow!("Trigger engine state poisoned"))?;
+        if session.is_some() {
+            return Err(anyhow!("Trigger engine is already running"));
+        }
+
+        let status = Arc::new(Mutex::new(TriggerEngineStatus {
+            is_running: true,
+            pre_roll_seconds,
+            post_roll_seconds,
+            ..TriggerEngineStatus::stopped()
+        }));
+        let (stop_tx, stop_rx) = mpsc::channel::<()>();
+
+        let thread_status = status.clone();
+        let thread = std::thread::Builder::new()
+            .name("dwight-trigger-engine".to_string())
+            .spawn(move || {
+                let mut worker = EngineWorker::new(app_handle, thread_status, pre_roll_seconds, post_roll_seconds);
+                while let Err(RecvTimeoutError::Timeout) = stop_rx.recv_timeout(TICK) {
+                    worker.tick();
+                }
+                worker.shutdown();
+            })?;
+
+        let current = status.lock().map(|s| s.clone()).unwrap_or_else(|_| TriggerEngineStatus::stopped());
+        *session = Some(EngineSession { status, stop_tx, thread });
+        Ok(current)
+    }
+
+    pub fn stop(&self) -> Result<TriggerEngineStatus> {
+        let session = self.session.lock().map_err(|_| anyhow!("Trigger engine state poisoned"))?.take();
+        if let Some(session) = session {
+            let _ = session.stop_tx.send(());
+            let _ = session.thread.join();
+        }
+        Ok(TriggerEngineStatus::stopped())
+    }
+
+    pub fn status(&self) -> Result<TriggerEngineStatus> {
+        let session = self.session.lock().map_err(|_| anyhow!("Trigger engine state poisoned"))?;
+        Ok(match session.as_ref() {
+            Some(session) => session.status.lock().map(|s| s.clone()).unwrap_or_else(|_| TriggerEngineStatus::stopped()),
+            None => TriggerEngineStatus::stopped(),
+        })
+    }
+}
+
+/// Whether a `sound` trigger value names the detected class.
+pub fn sound_matches(trigger_value: &str, class: &str) -> bool {
+    let value = trigger_value.trim().to_lowercase();
+    value == "any"
+        || value == "*"
+        || value == class
+        || SOUND_ALIASES.iter().any(|(alias, target)| *alias == value && *target == class)
+}
+
+fn words(text: &str) -> Vec<String> {
+    text.to_lowercase()
+        .split(|c: char| !(c.is_alphanumeric() || c == '\''))
+        .filter(|w| !w.is_empty())
+        .map(|w| w.to_string())
+        .collect()
+}
+
+/// Whether a `speech` trigger's keyword or phrase occurs as whole words.
+pub fn keyword_matches(keyword: &str, transcript: &str) -> bool {
+    let phrase = words(keyword);
+    if phrase.is_empty() {
+        return false;
+    }
+    words(transcript).windows(phrase.len()).any(|window| window == phrase.as_slice())
+}
+
+/// Frame features for the last [`ANALYSIS_WINDOW_SECONDS`] of capture. Only
+/// newly arrived samples are analysed; frame times are seconds from the start
+/// of the capture session.
+struct FrameWindow {
+    sample_rate: u32,
+    frames: Vec<FrameFeatures>,
+    /// Samples not yet covered by a frame hop, starting at `unframed_start`
+    unframed: Vec<f32>,
+    unframed_start: u64,
+}
+
+impl FrameWindow {
+    fn new(sample_rate: u32, start: u64) -> Self {
+        FrameWindow {
+            sample_rate: sample_rate.max(1),
+            frames: Vec::new(),
+            unframed: Vec::new(),
+            unframed_start: start,
+        }
+    }
+
+    fn push(&mut self, samples: &[f32], start: u64) {
+        // The buffer overwrote audio we hadn't read yet; restart the window there
+        if start != self.unframed_start + self.unframed.len() as u64 {
+            self.frames.clear();
+            self.unframed.clear();
+            self.unframed_start = start;
+        }
+        self.unframed.extend_from_slice(samples);
+
+        // Keep the overlap needed by the next frame
+        let frames = audio_analysis::frame_features(&self.unframed, self.sample_rate);
+        let hop = ((self.sample_rate as f64 * HOP_SECONDS) as usize).max(1);
+        let offset = self.unframed_start as f64 / self.sample_rate as f64;
+        let drained = (frames.len() * hop).min(self.unframed.len());
+        self.unframed.drain(..drained);
+        self.unframed_start += drained as u64;
+        self.frames.extend(frames.into_iter().map(|frame| FrameFeatures {
+            start: offset + frame.start,
+            ..frame
+        }));
+
+        let max_frames = (ANALYSIS_WINDOW_SECONDS / HOP_SECONDS) as usize;
+        if self.frames.len() > max_frames {
+            let excess = self.frames.len() - max_frames;
+            self.frames.drain(..excess);
+        }
+    }
+}
+
+struct Utterance {
+    samples: Vec<f32>,
+    start: u64,
+    end: u64,
+}
+
+struct HeardUtterance {
+    text: String,
+    start: u64,
+    end: u64,
+}
+
+struct PendingClip {
+    start: u64,
+    end: u64,
+    fired: Vec<TriggerFired>,
+}
+
+/// State owned by the engine thread.
+struct EngineWorker {
+    app_handle: AppHandle,
+    status: Arc<Mutex<TriggerEngineStatus>>,
+    pre_roll_seconds: f64,
+    post_roll_seconds: f64,
+    triggers: Vec<SoundTrigger>,
+    last_reload: Option<Instant>,
+    /// Capture session the positions below belong to
+    capture_session: Option<String>,
+    sample_rate: u32,
+    cursor: u64,
+    window: FrameWindow,
+    last_sound_end: u64,
+    vad: StreamingVad,
+    vad_origin: u64,
+    cooldowns: HashMap<i32, Instant>,
+    pending: Vec<PendingClip>,
+    transcriber: Option<TranscriberHandle>,
+}
+
+struct TranscriberHandle {
+    tx: Sender<Utterance>,
+    rx: Receiver<HeardUtterance>,
+}
+
+impl EngineWorker {
+    fn new(app_handle: AppHandle, status: Arc<Mutex<TriggerEngineStatus>>, pre_roll_seconds: f64, post_roll_seconds: f64) -> Self {
+        EngineWorker {
+            app_handle,
+            status,
+            pre_roll_seconds,
+            post_roll_seconds,
+            triggers: Vec::new(),
+            last_reload: None,
+            capture_session: None,
+            sample_rate: 0,
+            cursor: 0,
+            window: FrameWindow::new(1, 0),
+            last_sound_end: 0,
+            vad: StreamingVad::new(1),
+            vad_origin: 0,
+            cooldowns: HashMap::new(),
+            pending: Vec::new(),
+            transcriber: None,
+        }
+    }
+
+    fn set_error(&self, error: Option<String>) {
+        if let Ok(mut status) = self.status.lock() {
+            status.last_error = error;
+        }
+    }
+
+    fn tick(&mut self) {
+        if self.last_reload.is_none_or(|t| t.elapsed() >= RELOAD_INTERVAL) {
+            self.reload_triggers();
+        }
+
+        let chunk = match self.app_handle.state::<AudioCapture>().read_since(self.cursor) {
+            Ok(chunk) => chunk,
+            // Capture isn't running; wait for it
+            Err(_) => return,
+        };
+
+        if self.capture_session.as_deref() != Some(chunk.started_at.as_str()) {
+            self.reset_stream(&chunk);
+            return;
+        }
+
+        let end = chunk.end();
+        self.cursor = end;
+        if !chunk.samples.is_empty() {
+            self.process_audio(&chunk.samples, chunk.start);
+        }
+        self.collect_transcripts();
+        self.save_due_clips(end);
+    }
+
+    fn reload_triggers(&mut self) {
+        self.last_reload = Some(Instant::now());
+        let loaded = self
+            .app_handle
+            .state::<AppState>()
+            .db()
+            .and_then(|db| db.get_active_triggers().map_err(|e| format!("Database error: {}", e)));
+        match loaded {
+            Ok(triggers) => {
+                self.triggers = triggers;
+                self.set_error(None);
+            }
+            Err(e) => self.set_error(Some(format!("Failed to load triggers: {}", e))),
+        }
+
+        let wants_keywords = self.triggers.iter().any(|t| t.trigger_type == "speech");
+        if wants_keywords && self.transcriber.is_none() {
+            self.transcriber = spawn_transcriber(&self.app_handle);
+        } else if !wants_keywords {
+            self.transcriber = None;
+        }
+
+        if let Ok(mut status) = self.status.lock() {
+            status.active_triggers = self.triggers.len();
+            status.keyword_transcription = self.transcriber.is_some();
+        }
+    }
+
+    /// Starts tracking a new capture session from its current position, so
+    /// audio captured before the engine noticed it isn't replayed.
+    fn reset_stream(&mut self, chunk: &audio_capture::CapturedAudio) {
+        // Clips pending on the previous session can no longer be read back
+        self.pending.clear();
+        self.capture_session = Some(chunk.started_at.clone());
+        self.sample_rate = chunk.sample_rate;
+        self.cursor = chunk.end();
+        self.window = FrameWindow::new(chunk.sample_rate, self.cursor);
+        self.last_sound_end = self.cursor;
+        self.vad = StreamingVad::new(chunk.sample_rate);
+        self.vad_origin = self.cursor;
+    }
+
+    fn seconds_to_samples(&self, seconds: f64) -> u64 {
+        (seconds.max(0.0) * self.sample_rate as f64).round() as u64
+    }
+
+    fn process_audio(&mut self, samples: &[f32], start: u64) {
+        self.window.push(samples, start);
+
+        if self.triggers.iter().any(|t| t.trigger_type == "sound") {
+            self.detect_sounds();
+        }
+
+        for event in self.vad.push(samples) {
+            self.handle_vad(event);
+        }
+    }
+
+    fn detect_sounds(&mut self) {
+        let Some(window_end) = self.window.frames.last().map(|f| f.start + FRAME_SECONDS) else {
+            return;
+        };
+
+        for event in audio_analysis::non_speech_events(&self.window.frames) {
+            if event.end > window_end - SETTLE_SECONDS {
+                continue;
+            }
+            let event_start = self.seconds_to_samples(event.start);
+            let event_end = self.seconds_to_samples(event.end);
+            if event_end <= self.last_sound_end {
+                continue;
+            }
+            self.last_sound_end = event_end;
+
+            let matching: Vec<SoundTrigger> = self
+                .triggers
+                .iter()
+                .filter(|t| t.trigger_type == "sound" && sound_matches(&t.trigger_value, &event.event_type))
+                .cloned()
+                .collect();
+            for trigger in matching {
+                self.fire(&trigger, event.event_type.clone(), event.confidence, event_start, event_end);
+            }
+        }
+    }
+
+    fn handle_vad(&mut self, event: VadEvent) {
+        match event {
+            VadEvent::SpeechStart { start } => {
+                let at = self.vad_origin + self.seconds_to_samples(start);
+                let matching: Vec<SoundTrigger> = self
+                    .triggers
+                    .iter()
+                    .filter(|t| t.trigger_type == "sound" && sound_matches(&t.trigger_value, "speech"))
+                    .cloned()
+                    .collect();
+                for trigger in matching {
+                    self.fire(&trigger, "speech".to_string(), 1.0, at, at);
+                }
+            }
+            VadEvent::SpeechEnd { start, end, .. } => {
+                let Some(transcriber) = &self.transcriber else {
+                    return;
+                };
+                let start = self.vad_origin + self.seconds_to_samples(start.max(end - MAX_UTTERANCE_SECONDS));
+                let end = self.vad_origin + self.seconds_to_samples(end);
+
+                let capture = self.app_handle.state::<AudioCapture>();
+                if let Ok(audio) = capture.read_since(start) {
+                    let len = end.saturating_sub(audio.start) as usize;
+                    let samples = audio.samples[..len.min(audio.samples.len())].to_vec();
+                    let utterance = match resample::resample(&samples, audio.sample_rate, WHISPER_SAMPLE_RATE) {
+                        Ok(samples) => Utterance { samples, start: audio.start, end },
+                        Err(_) => return,
+                    };
+                    let _ = transcriber.tx.send(utterance);
+                }
+            }
+        }
+    }
+
+    fn collect_transcripts(&mut self) {
+        let heard: Vec<HeardUtterance> = match &self.transcriber {
+            Some(transcriber) => transcriber.rx.try_iter().collect(),
+            None => return,
+        };
+
+        for utterance in heard {
+            let _ = self.app_handle.emit_all(LIVE_TRANSCRIPT_EVENT, utterance.text.clone());
+
+            let matching: Vec<SoundTrigger> = self
+                .triggers
+                .iter()
+                .filter(|t| t.trigger_type == "speech" && keyword_matches(&t.trigger_value, &utterance.text))
+                .cloned()
+                .collect();
+            for trigger in matching {
+                self.fire(&trigger, utterance.text.clone(), 1.0, utterance.start, utterance.end);
+            }
+        }
+    }
+
+    fn fire(&mut self, trigger: &SoundTrigger, matched: String, confidence: f32, start: u64, end: u64) {
+        if let Some(id) = trigger.id {
+            if self.cooldowns.get(&id).is_some_and(|last| last.elapsed() < COOLDOWN) {
+                return;
+            }
+            self.cooldowns.insert(id, Instant::now());
+        }
+
+        let fired = TriggerFired {
+            trigger_id: trigger.id,
+            trigger_type: trigger.trigger_type.clone(),
+            trigger_value: trigger.trigger_value.clone(),
+            matched,
+            confidence,
+            detected_at: chrono::Utc::now().to_rfc3339(),
+        };
+        if let Err(e) = self.app_handle.emit_all(TRIGGER_FIRED_EVENT, fired.clone()) {
+            eprintln!("Failed to emit trigger event: {}", e);
+        }
+        if let Ok(mut status) = self.status.lock() {
+            status.fired_count += 1;
+            status.last_fired = Some(fired.clone());
+        }
+
+        // Overlapping detections share one clip
+        let clip_start = start.saturating_sub(self.seconds_to_samples(self.pre_roll_seconds));
+        let clip_end = end + self.seconds_to_samples(self.post_roll_seconds);
+        match self.pending.iter_mut().find(|clip| clip_start <= clip.end) {
+            Some(clip) => {
+                clip.start = clip.start.min(clip_start);
+                clip.end = clip.end.max(clip_end);
+                clip.fired.push(fired);
+            }
+            None => self.pending.push(PendingClip {
+                start: clip_start,
+                end: clip_end,
+                fired: vec![fired],
+            }),
+        }
+    }
+
+    fn save_due_clips(&mut self, position: u64) {
+        let (due, waiting): (Vec<PendingClip>, Vec<PendingClip>) =
+            self.pending.drain(..).partition(|clip| clip.end <= position);
+        self.pending = waiting;
+
+        for clip in due {
+            if let Err(e) = self.save_clip(clip) {
+                self.set_error(Some(format!("Failed to save trigger clip: {}", e)));
+            }
+        }
+    }
+
+    fn save_clip(&self, clip: PendingClip) -> Result<()> {
+        let audio = self.app_handle.state::<AudioCapture>().read_since(clip.start)?;
+
+        let len = (clip.end.saturating_sub(audio.start) as usize).min(audio.samples.len());
+        let samples = &audio.samples[..len];
+        if samples.is_empty() {
+            return Err(anyhow!("Clip audio is no longer buffered"));
+        }
+
+        let config = self.app_handle.config();
+        let dir = audio_capture::recordings_dir(&config);
+        std::fs::create_dir_all(&dir)?;
+        let now = chrono::Local::now();
+        let file_path = dir.join(format!("trigger_{}.wav", now.format("%Y%m%d_%H%M%S_%3f")));
+        let sha256 = audio_capture::save_wav(&file_path, samples, audio.sample_rate)?;
+        let rate = audio.sample_rate.max(1) as f64;
+        let captured_at = chrono::DateTime::parse_from_rfc3339(&audio.started_at)
+            .ok()
+            .map(|started| (started + chrono::Duration::milliseconds((audio.start as f64 / rate * 1000.0) as i64)).to_rfc3339());
+
+        let values: Vec<&str> = clip.fired.iter().map(|f| f.trigger_value.as_str()).collect();
+        let mut record = AudioRecord {
+            id: None,
+            title: format!("Trigger: {} ({})", values.join(", "), now.format("%Y-%m-%d %H:%M:%S")),
+            file_path: file_path.to_string_lossy().to_string(),
+            transcript: None,
+            duration: samples.len() as f64 / rate,
+            created_at: String::new(),
+            triggers: Some(serde_json::to_string(&clip.fired)?),
+            tags: Vec::new(),
+            starred: false,
+            sha256: Some(sha256),
+            capture_device: Some(audio.device_name.clone()),
+            captured_at,
+        };
+        let db = self.app_handle.state::<AppState>().db().map_err(|e| anyhow!(e))?;
+        record.id = Some(db.save_audio_record(&record)? as i32);
+
+        let saved = TriggerClipSaved { record, fired: clip.fired };
+        if let Err(e) = self.app_handle.emit_all(TRIGGER_CLIP_EVENT, saved) {
+            eprintln!("Failed to emit trigger clip event: {}", e);
+        }
+        Ok(())
+    }
+
+    /// Saves whatever is buffered for clips still waiting on post-roll.
+    fn shutdown(&mut self) {
+        let position = self.cursor;
+        for clip in self.pending.iter_mut() {
+            clip.end = clip.end.min(position);
+        }
+        self.save_due_clips(position);
+    }
+}
+
+/// Starts a thread that transcribes utterances for keyword triggers with the
+/// shared Whisper engine. Returns `None` when Whisper isn't usable; the mock
+/// backend is refused because its canned text would fire keywords that were
+/// never spoken.
+fn spawn_transcriber(app_handle: &AppHandle) -> Option<TranscriberHandle> {
+    let engine = app_handle.state::<AppState>().whisper();
+    let config = engine.config();
+    if config.backend == BackendKind::Mock || !config.validate().is_empty() {
+        return None;
+    }
+
+    let (utterance_tx, utterance_rx) = mpsc::channel::<Utterance>();
+    let (heard_tx, heard_rx) = mpsc::channel::<HeardUtterance>();
+    std::thread::Builder::new()
+        .name("dwight-trigger-transcriber".to_string())
+        .spawn(move || {
+            // Ends when the engine drops its sender
+            for utterance in utterance_rx {
+                let path = std::env::temp_dir().join(format!(
+                    "dwight_utterance_{}_{}.wav",
+                    std::process::id(),
+                    chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default()
+                ));
+                let result = audio_capture::write_wav(&path, &utterance.samples, WHISPER_SAMPLE_RATE)
+                    .and_then(|_| engine.transcribe_blocking(&path.to_string_lossy()));
+                let _ = std::fs::remove_file(&path);
+
+                match result {
+                    Ok(result) if !result.text.trim().is_empty() => {
+                        let heard = HeardUtterance {
+                            text: result.text.trim().to_string(),
+                            start: utterance.start,
+                            end: utterance.end,
+                        };
+                        if heard_tx.send(heard).is_err() {
+                            break;
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(e) => eprintln!("Keyword transcription failed: {}", e),
+                }
+            }
+        })
+        .ok()?;
+
+    Some(TranscriberHandle {
+        tx: utterance_tx,
+        rx: heard_rx,
+    })
+}
+
+#[command]
+pub async fn start_trigger_engine(
+    pre_roll_seconds: Option<f64>,
+    post_roll_seconds: Option<f64>,
+    engine: State<'_, TriggerEngine>,
+    app_handle: AppHandle,
+) -> Result<TriggerEngineStatus, String> {
+    let pre_roll = pre_roll_seconds.unwrap_or(DEFAULT_PRE_ROLL_SECONDS).max(0.0);
+    let post_roll = post_roll_seconds.unwrap_or(DEFAULT_POST_ROLL_SECONDS).max(0.0);
+    engine.start(app_handle, pre_roll, post_roll)
+        .map_err(|e| format!("Trigger engine error: {}", e))
+}
+
+#[command]
+pub async fn stop_trigger_engine(engine: State<'_, TriggerEngine>) -> Result<TriggerEngineStatus, String> {
+    engine.stop().map_err(|e| format!("Trigger engine error: {}", e))
+}
+
+#[command]
+pub async fn get_trigger_engine_status(engine: State<'_, TriggerEngine>) -> Result<TriggerEngineStatus, String> {
+    engine.status().map_err(|e| format!("Trigger engine error: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sound_values_match_classes_and_aliases() {
+        let cases = [
+            ("impulse", "impulse", true),
+            ("knock", "impulse", true),
+            ("  Knock ", "impulse", true),
+            ("GUNSHOT", "impulse", true),
+            ("knock", "tonal", false),
+            ("alarm", "tonal", true),
+            ("doorbell", "tonal", true),
+            ("rain", "noise", true),
+            ("voice", "speech", true),
+            ("speech", "speech", true),
+            ("any", "noise", true),
+            ("*", "speech", true),
+            ("knocking", "impulse", false),
+            ("", "impulse", false),
+            ("dog", "sound", false),
+        ];
+        for (value, class, expected) in cases {
+            assert_eq!(sound_matches(value, class), expected, "{:?} vs {:?}", value, class);
+        }
+    }
+
+    #[test]
+    fn every_alias_targets_a_class_the_engine_produces() {
+        for (alias, class) in SOUND_ALIASES {
+            assert!(["impulse", "tonal", "noise", "speech"].contains(&class), "{} -> {}", alias, class);
+            assert!(sound_matches(alias, class));
+            assert_eq!(SOUND_ALIASES.iter().filter(|(a, _)| *a == alias).count(), 1, "{} listed twice", alias);
+        }
+    }
+
+    #[test]
+    fn keywords_match_whole_words_in_any_case() {
+        let cases = [
+            ("help", "Somebody help me!", true),
+            ("HELP", "somebody help me", true),
+            ("help", "that was helpful", false),
+            ("help", "unhelpful", false),
+            ("call the police", "Please, call the police now.", true),
+            ("call the police", "call the, police", true),
+            ("call the police", "call police", false),
+            ("call the police", "the police will call", false),
+            ("don't", "I don't know", true),
+            ("don't", "dont", false),
+            ("fire", "FIRE", true),
+            ("", "anything", false),
+            ("  ", "anything", false),
+            ("help", "", false),
+        ];
+        for (keyword, transcript, expected) in cases {
+            assert_eq!(keyword_matches(keyword, transcript), expected, "{:?} in {:?}", keyword, transcript);
+        }
+    }
+
+    /// A quiet hum with two knocks, so the window has a noise floor and events.
+    fn hum_with_knocks(rate: u32, seconds: f64) -> Vec<f32> {
+        let len = (seconds * rate as f64) as usize;
+        let mut samples: Vec<f32> = (0..len)
+            .map(|i| 0.001 * (2.0 * std::f32::consts::PI * 60.0 * i as f32 / rate as f32).sin())
+            .collect();
+        for at in [1.0, 2.5] {
+            let start = (at * rate as f64) as usize;
+            let mut state = 12_345u32;
+            for (i, sample) in samples[start..start + rate as usize / 10].iter_mut().enumerate() {
+                state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                let decay = (-(i as f32) / (rate as f32 * 0.02)).exp();
+                *sample += 0.5 * decay * ((state >> 8) as f32 / (1u32 << 24) as f32 * 2.0 - 1.0);
+            }
+        }
+        samples
+    }
+
+    #[test]
+    fn frame_window_matches_analysing_the_whole_buffer() {
+        for rate in [16_000, 22_050, 44_100] {
+            let samples = hum_with_knocks(rate, 4.0);
+            let mut window = FrameWindow::new(rate, 0);
+            // Uneven chunks so frames straddle chunk boundaries
+            let mut start = 0;
+            for chunk in samples.chunks(rate as usize / 4 + 7) {
+                window.push(chunk, start);
+                start += chunk.len() as u64;
+            }
+
+            let whole = audio_analysis::frame_features(&samples, rate);
+            assert_eq!(window.frames.len(), whole.len());
+            for (incremental, expected) in window.frames.iter().zip(&whole) {
+                assert!((incremental.start - expected.start).abs() < 1e-9);
+                assert!((incremental.db - expected.db).abs() < 1e-3);
+            }
+
+            let expected = audio_analysis::analyze_samples(&samples, rate, 1).non_speech_events;
+            let events = audio_analysis::non_speech_events(&window.frames);
+            assert_eq!(events.len(), 2, "{:?}", events);
+            assert_eq!(events.len(), expected.len());
+            for (event, expected) in events.iter().zip(&expected) {
+                assert_eq!(event.event_type, expected.event_type);
+                assert!((event.start - expected.start).abs() < 1e-9);
+                assert!((event.end - expected.end).abs() < 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn frame_window_keeps_only_the_analysis_window() {
+        let rate = 16_000;
+        let mut window = FrameWindow::new(rate, 0);
+        let chunk = vec![0.1; rate as usize];
+        for second in 0..15u64 {
+            window.push(&chunk, second * rate as u64);
+        }
+        assert_eq!(window.frames.len(), (ANALYSIS_WINDOW_SECONDS / HOP_SECONDS) as usize);
+        let last = window.frames.last().unwrap();
+        assert!(last.start + FRAME_SECONDS <= 15.0 && last.start > 14.9);
+
+        // A gap in positions means the buffer overwrote unread audio
+        window.push(&chunk, 100 * rate as u64);
+        assert!(window.frames.iter().all(|f| f.start >= 100.0));
+    }
+}
@@ -0,0 +1,389 @@
+use serde::{Deserialize, Serialize};
+use crate::audio_analysis::{self, FrameFeatures, FRAME_SECONDS, HOP_SECONDS};
+
+/// Shortest run of speech frames reported as a region.
+pub const MIN_SPEECH_SECONDS: f64 = 0.25;
+/// Pauses up to this long stay inside one region.
+pub const MAX_GAP_SECONDS: f64 = 0.30;
+
+/// Floor for the streaming noise estimate, so digital silence doesn't make
+/// every faint sound look like speech.
+const MIN_NOISE_DB: f32 = -80.0;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpeechRegion {
+    pub start: f64,
+    pub end: f64,
+    /// Fraction of frames inside the region that were classed as speech
+    pub confidence: f32,
+}
+
+/// Emitted by [`StreamingVad`] as speech begins and ends. Times are seconds
+/// since the first sample pushed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum VadEvent {
+    SpeechStart { start: f64 },
+    SpeechEnd { start: f64, end: f64, confidence: f32 },
+}
+
+/// Energy plus spectral-shape speech decision for a single frame.
+pub fn is_speech_frame(frame: &FrameFeatures, noise_floor_db: f32) -> bool {
+    frame.db > noise_floor_db + 6.0
+        && frame.db > -55.0
+        && frame.speech_band_ratio > 0.45
+        && frame.spectral_flatness < 0.45
+        && frame.zero_crossing_rate < 0.35
+}
+
+/// Per-frame speech flags and the `(start_frame, end_frame)` runs they form.
+pub fn speech_runs(frames: &[FrameFeatures], noise_floor_db: f32) -> (Vec<bool>, Vec<(usize, usize)>) {
+    let flags: Vec<bool> = frames.iter().map(|f| is_speech_frame(f, noise_floor_db)).collect();
+    let runs = audio_analysis::group_runs(&flags, MIN_SPEECH_SECONDS, MAX_GAP_SECONDS);
+    (flags, runs)
+}
+
+/// Offline VAD over a whole recording.
+pub fn detect_speech(samples: &[f32], sample_rate: u32) -> Vec<SpeechRegion> {
+    let frames = audio_analysis::frame_features(samples, sample_rate);
+    let noise_db = audio_analysis::noise_floor_db(&frames);
+    let (flags, runs) = speech_runs(&frames, noise_db);
+
+    runs.into_iter()
+        .map(|(start, end)| {
+            let voiced = flags[start..=end].iter().filter(|&&f| f).count();
+            SpeechRegion {
+                start: frames[start].start,
+                end: frames[end].start + FRAME_SECONDS,
+                confidence: voiced as f32 / (end + 1 - start) as f32,
+            }
+        })
+        .collect()
+}
+
+struct ActiveSpeech {
+    start: f64,
+    last_voiced: f64,
+    voiced: usize,
+    frames: usize,
+}
+
+/// Incremental VAD for live capture. Feed it samples as they arrive and it
+/// reports speech onsets once a region has lasted [`MIN_SPEECH_SECONDS`], and
+/// ends once the speaker has paused for longer than [`MAX_GAP_SECONDS`].
+///
+/// The noise floor adapts as it goes: it drops quickly to quieter frames and
+/// creeps up slowly during non-speech, so a steady fan or hum is absorbed.
+pub struct StreamingVad {
+    sample_rate: u32,
+    pending: Vec<f32>,
+    consumed: u64,
+    noise_db: Option<f32>,
+    candidate: Option<ActiveSpeech>,
+    active: Option<ActiveSpeech>,
+}
+
+impl StreamingVad {
+    pub fn new(sample_rate: u32) -> Self {
+        StreamingVad {
+            sample_rate: sample_rate.max(1),
+            pending: Vec::new(),
+            consumed: 0,
+            noise_db: None,
+            candidate: None,
+            active: None,
+        }
+    }
+
+    pub fn push(&mut self, samples: &[f32]) -> Vec<VadEvent> {
+        self.pending.extend_from_slice(samples);
+
+        let frames = audio_analysis::frame_features(&self.pending, self.sample_rate);
+        if frames.is_empty() {
+            return Vec::new();
+        }
+
+        // Keep the overlap needed by the next frame
+        let hop = ((self.sample_rate as f64 * HOP_SECONDS) as usize).max(1);
+        let offset = self.consumed as f64 / self.sample_rate as f64;
+        let drained = (frames.len() * hop).min(self.pending.len());
+        self.pending.drain(..drained);
+        self.consumed += drained as u64;
+
+        let mut events = Vec::new();
+        for frame in &frames {
+            if let Some(event) = self.process_frame(frame, offset + frame.start) {
+                events.push(event);
+            }
+        }
+        events
+    }
+
+    /// Closes any open region, e.g. when capture stops.
+    pub fn finish(&mut self) -> Option<VadEvent> {
+        self.candidate = None;
+        self.active.take().map(Self::end_event)
+    }
+
+    fn process_frame(&mut self, frame: &FrameFeatures, time: f64) -> Option<VadEvent> {
+        let noise_db = *self.noise_db.get_or_insert(frame.db.max(MIN_NOISE_DB));
+        let speech = is_speech_frame(frame, noise_db);
+
+        if !speech {
+            let updated = if frame.db < noise_db {
+                noise_db + 0.2 * (frame.db - noise_db)
+            } else {
+                noise_db + 0.005 * (frame.db - noise_db)
+            };
+            self.noise_db = Some(updated.max(MIN_NOISE_DB));
+        }
+
+        if let Some(active) = &mut self.active {
+            active.frames += 1;
+            if speech {
+                active.voiced += 1;
+                active.last_voiced = time;
+            } else if time - active.last_voiced > MAX_GAP_SECONDS {
+                return self.active.take().map(Self::end_event);
+            }
+            return None;
+        }
+
+        match &mut self.candidate {
+            Some(candidate) => {
+                candidate.frames += 1;
+                if speech {
+                    candidate.voiced += 1;
+                    candidate.last_voiced = time;
+                } else if time - candidate.last_voiced > MAX_GAP_SECONDS {
+                    self.candidate = None;
+                    return None;
+                }
+
+                if candidate.last_voiced + FRAME_SECONDS - candidate.start >= MIN_SPEECH_SECONDS {
+                    let start = candidate.start;
+                    self.active = self.candidate.take();
+                    return Some(VadEvent::SpeechStart { start });
+                }
+            }
+            None if speech => {
+                self.candidate = Some(ActiveSpeech {
+                    start: time,
+                    last_voiced: time,
+                    voiced: 1,
+                    frames: 1,
+                });
+            }
+            None => {}
+        }
+        None
+    }
+
+    fn end_event(active: ActiveSpeech) -> VadEvent {
+        VadEvent::SpeechEnd {
+            start: active.start,
+            end: active.last_voiced + FRAME_SECONDS,
+            confidence: active.voiced as f32 / active.frames.max(1) as f32,
+        }
+    }
+}
+
+/// Maps times in a speech-only copy of a recording back to the original.
+pub struct SpeechMap {
+    /// `(compact_start, source_start, length)` in seconds
+    spans: Vec<(f64, f64, f64)>,
+}
+
+impl SpeechMap {
+    pub fn to_source(&self, time: f64) -> f64 {
+        let span = self
+            .spans
+            .iter()
+            .rev()
+            .find(|(compact_start, _, _)| *compact_start <= time)
+            .or(self.spans.first());
+        match span {
+            Some(&(compact_start, source_start, length)) => {
+                source_start + (time - compact_start).clamp(0.0, length)
+            }
+            None => time,
+        }
+    }
+}
+
+/// Cuts the recording down to its speech regions, each widened by `padding`
+/// seconds, so silence isn't sent to the transcriber.
+pub fn compact_speech(samples: &[f32], sample_rate: u32, regions: &[SpeechRegion], padding: f64) -> (Vec<f32>, SpeechMap) {
+    let rate = sample_rate.max(1) as f64;
+    let duration = samples.len() as f64 / rate;
+
+    // Merge regions whose padding overlaps
+    let mut merged: Vec<(f64, f64)> = Vec::new();
+    for region in regions {
+        let start = (region.start - padding).max(0.0);
+        let end = (region.end + padding).min(duration);
+        match merged.last_mut() {
+            Some((_, last_end)) if start <= *last_end => *last_end = last_end.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+
+    let mut compact = Vec::new();
+    let mut spans = Vec::with_capacity(merged.len());
+    for (start, end) in merged {
+        let from = (start * rate) as usize;
+        let to = ((end * rate) as usize).min(samples.len());
+        if from >= to {
+            continue;
+        }
+        spans.push((compact.len() as f64 / rate, from as f64 / rate, (to - from) as f64 / rate));
+        compact.extend_from_slice(&samples[from..to]);
+    }
+
+    (compact, SpeechMap { spans })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: u32 = 16_000;
+
+    fn tone(seconds: f64, amplitude: f32) -> Vec<f32> {
+        (0..(seconds * RATE as f64) as usize)
+            .map(|i| amplitude * (2.0 * std::f32::consts::PI * 440.0 * i as f32 / RATE as f32).sin())
+            .collect()
+    }
+
+    /// White noise from a fixed-seed LCG, so runs are repeatable.
+    fn noise(seconds: f64, amplitude: f32, seed: u32) -> Vec<f32> {
+        let mut state = seed;
+        (0..(seconds * RATE as f64) as usize)
+            .map(|_| {
+                state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                amplitude * ((state >> 8) as f32 / (1u32 << 24) as f32 * 2.0 - 1.0)
+            })
+            .collect()
+    }
+
+    /// One second of near-silence, one of tone, one of near-silence.
+    fn quiet_tone_quiet() -> Vec<f32> {
+        let mut samples = noise(1.0, 0.0005, 1);
+        samples.extend(tone(1.0, 0.3));
+        samples.extend(noise(1.0, 0.0005, 2));
+        samples
+    }
+
+    fn region(start: f64, end: f64) -> SpeechRegion {
+        SpeechRegion { start, end, confidence: 1.0 }
+    }
+
+    #[test]
+    fn frames_tell_silence_tone_and_noise_apart() {
+        let silence = audio_analysis::frame_features(&vec![0.0; RATE as usize], RATE);
+        assert!(silence.iter().all(|f| f.db < -90.0));
+        assert!(silence.iter().all(|f| !is_speech_frame(f, -80.0)));
+
+        let tone = audio_analysis::frame_features(&tone(1.0, 0.3), RATE);
+        assert!(!tone.is_empty());
+        for frame in &tone {
+            assert!(frame.speech_band_ratio > 0.9, "{:?}", frame.speech_band_ratio);
+            assert!(frame.spectral_flatness < 0.1, "{:?}", frame.spectral_flatness);
+            assert!(is_speech_frame(frame, -80.0));
+        }
+
+        // Loud white noise is broadband and flat, so it isn't speech however loud
+        let noise = audio_analysis::frame_features(&noise(1.0, 0.3, 3), RATE);
+        for frame in &noise {
+            assert!(frame.spectral_flatness > 0.45, "{:?}", frame.spectral_flatness);
+            assert!(!is_speech_frame(frame, -80.0));
+        }
+    }
+
+    #[test]
+    fn detect_speech_finds_the_tone() {
+        let regions = detect_speech(&quiet_tone_quiet(), RATE);
+        assert_eq!(regions.len(), 1, "{:?}", regions);
+        assert!((regions[0].start - 1.0).abs() < 0.05, "{:?}", regions[0]);
+        assert!((regions[0].end - 2.0).abs() < 0.05, "{:?}", regions[0]);
+        assert!(regions[0].confidence > 0.9);
+
+        assert!(detect_speech(&noise(2.0, 0.3, 4), RATE).is_empty());
+        assert!(detect_speech(&vec![0.0; 2 * RATE as usize], RATE).is_empty());
+    }
+
+    #[test]
+    fn streaming_vad_reports_start_and_end_of_the_tone() {
+        let mut vad = StreamingVad::new(RATE);
+        let mut events = Vec::new();
+        for chunk in quiet_tone_quiet().chunks(RATE as usize / 10) {
+            events.extend(vad.push(chunk));
+        }
+        assert!(vad.finish().is_none());
+
+        assert_eq!(events.len(), 2, "{:?}", events);
+        match events[0] {
+            VadEvent::SpeechStart { start } => assert!((start - 1.0).abs() < 0.05, "{}", start),
+            ref other => panic!("expected a start, got {:?}", other),
+        }
+        match events[1] {
+            VadEvent::SpeechEnd { start, end, confidence } => {
+                assert!((start - 1.0).abs() < 0.05, "{}", start);
+                assert!((end - 2.0).abs() < 0.05, "{}", end);
+                assert!(confidence > 0.5, "{}", confidence);
+            }
+            ref other => panic!("expected an end, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn streaming_vad_ignores_noise_and_closes_open_speech_on_finish() {
+        let mut vad = StreamingVad::new(RATE);
+        for chunk in noise(2.0, 0.3, 5).chunks(RATE as usize / 10) {
+            assert!(vad.push(chunk).is_empty());
+        }
+        assert!(vad.finish().is_none());
+
+        let mut vad = StreamingVad::new(RATE);
+        let mut samples = noise(0.5, 0.0005, 6);
+        samples.extend(tone(1.0, 0.3));
+        let events = vad.push(&samples);
+        assert!(matches!(events.as_slice(), [VadEvent::SpeechStart { .. }]), "{:?}", events);
+        assert!(matches!(vad.finish(), Some(VadEvent::SpeechEnd { .. })));
+    }
+
+    #[test]
+    fn compact_speech_merges_regions_whose_padding_overlaps() {
+        // 10 s of samples whose value is their own time, so cuts are easy to check
+        let samples: Vec<f32> = (0..10 * RATE).map(|i| i as f32 / RATE as f32).collect();
+        let regions = [region(1.0, 2.0), region(2.3, 3.0), region(6.0, 7.0)];
+
+        let (compact, map) = compact_speech(&samples, RATE, &regions, 0.2);
+
+        // 0.8-3.2 (merged) and 5.8-7.2
+        assert_eq!(map.spans.len(), 2);
+        assert_eq!(compact.len(), ((2.4 + 1.4) * RATE as f64).round() as usize);
+        assert!((compact[0] - 0.8).abs() < 1e-3);
+        let second = (2.4 * RATE as f64) as usize;
+        assert!((compact[second] - 5.8).abs() < 1e-3);
+
+        assert!((map.to_source(0.0) - 0.8).abs() < 1e-6);
+        assert!((map.to_source(1.0) - 1.8).abs() < 1e-6);
+        assert!((map.to_source(2.4) - 5.8).abs() < 1e-6);
+        assert!((map.to_source(3.0) - 6.4).abs() < 1e-6);
+        // Past the end clamps to the end of the last span
+        assert!((map.to_source(100.0) - 7.2).abs() < 1e-6);
+    }
+
+    #[test]
+    fn compact_speech_clamps_padding_to_the_recording() {
+        let samples = vec![0.5; RATE as usize];
+        let (compact, map) = compact_speech(&samples, RATE, &[region(0.1, 0.9)], 0.5);
+        assert_eq!(compact.len(), samples.len());
+        assert!((map.to_source(0.25) - 0.25).abs() < 1e-6);
+
+        let (compact, map) = compact_speech(&samples, RATE, &[], 0.5);
+        assert!(compact.is_empty());
+        assert_eq!(map.to_source(0.4), 0.4);
+    }
+}
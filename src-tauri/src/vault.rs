@@ -0,0 +1,652 @@
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use anyhow::{anyhow, Context, Result};
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, KeyInit, OsRng, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use hkdf::Hkdf;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use tauri::{command, AppHandle, Manager};
+use zeroize::Zeroizing;
+use crate::state::AppState;
+
+/// Describes how the master key is protected. Lives next to `dwight.db`.
+const KEY_FILE: &str = "dwight.key";
+const KEY_FILE_VERSION: u32 = 1;
+
+const KEYRING_SERVICE: &str = "dwight";
+const KEYRING_USER: &str = "storage-key";
+
+/// Marks an audio file encrypted by [`write_file`].
+const FILE_MAGIC: &[u8; 8] = b"DWENC\x01\0\0";
+const NONCE_LEN: usize = 24;
+
+// Argon2id cost for new passphrases; stored per key file so it can be raised later
+const ARGON2_MEMORY_KIB: u32 = 64 * 1024;
+const ARGON2_ITERATIONS: u32 = 3;
+const ARGON2_LANES: u32 = 1;
+const SALT_LEN: usize = 16;
+
+pub type Key = Zeroizing<[u8; 32]>;
+
+/// Key for audio files while storage is unlocked. Global because audio is read
+/// from deep inside the decoders, which don't otherwise need app state.
+static FILE_KEY: RwLock<Option<Key>> = RwLock::new(None);
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyStorage {
+    Keyring,
+    Passphrase,
+}
+
+/// Argon2id parameters for a passphrase-derived key, kept alongside
+/// whatever the key protects.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KdfParams {
+    salt: String,
+    memory_kib: u32,
+    iterations: u32,
+    lanes: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct KeyFile {
+    version: u32,
+    storage: KeyStorage,
+    /// Hex prefix of a key-derived value, to recognise the right master key
+    check: String,
+    #[serde(default)]
+    kdf: Option<KdfParams>,
+    /// Hex of nonce followed by the master key sealed with the passphrase key
+    #[serde(default)]
+    wrapped_key: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StorageStatus {
+    /// False until a master key exists, i.e. the keyring was unavailable on
+    /// first run and no passphrase has been set yet
+    pub initialized: bool,
+    pub locked: bool,
+    pub key_storage: Option<KeyStorage>,
+    pub error: Option<String>,
+}
+
+/// Owns the master key from which the database and audio file keys are
+/// derived. The master key itself is random; a passphrase (via Argon2id) or
+/// the OS keyring only protects it, so changing the passphrase never
+/// re-encrypts any data.
+pub struct Vault {
+    data_dir: PathBuf,
+    master: Option<Key>,
+    last_error: Option<String>,
+}
+
+impl Vault {
+    /// Loads the key from the keyring if that's where it lives. On first run
+    /// a new key is created and stored in the keyring; if there is no keyring
+    /// the vault stays uninitialized until a passphrase is set.
+    pub fn open(data_dir: &Path) -> Self {
+        let mut vault = Vault {
+            data_dir: data_dir.to_path_buf(),
+            master: None,
+            last_error: None,
+        };
+
+        let result = match vault.read_key_file() {
+            Ok(Some(key_file)) if key_file.storage == KeyStorage::Keyring => vault.unlock(None),
+            Ok(Some(_)) => Ok(()),
+            Ok(None) => vault.initialize(None),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            eprintln!("Storage is locked: {}", e);
+            vault.last_error = Some(e.to_string());
+        }
+        vault
+    }
+
+    pub fn status(&self) -> StorageStatus {
+        let key_storage = self.read_key_file().ok().flatten().map(|k| k.storage);
+        StorageStatus {
+            initialized: key_storage.is_some(),
+            locked: self.master.is_none(),
+            key_storage,
+            error: self.last_error.clone(),
+        }
+    }
+
+    pub fn is_unlocked(&self) -> bool {
+        self.master.is_some()
+    }
+
+    /// Key for SQLCipher, while unlocked.
+    pub fn database_key(&self) -> Option<Key> {
+        self.master.as_ref().map(|master| subkey(master, b"dwight database"))
+    }
+
+    /// Recovers the master key from the keyring, or from `passphrase` when
+    /// the key is passphrase-protected.
+    pub fn unlock(&mut self, passphrase: Option<&str>) -> Result<()> {
+        let key_file = self
+            .read_key_file()?
+            .ok_or_else(|| anyhow!("Storage has not been set up yet; set a passphrase first"))?;
+
+        let master = match key_file.storage {
+            KeyStorage::Keyring => keyring_load()?,
+            KeyStorage::Passphrase => {
+                let passphrase = passphrase.ok_or_else(|| anyhow!("A passphrase is required to unlock storage"))?;
+                unwrap_with_passphrase(&key_file, passphrase)?
+            }
+        };
+        if key_check(&master) != key_file.check {
+            return Err(anyhow!("The stored key does not match this database"));
+        }
+
+        self.set_master(master);
+        Ok(())
+    }
+
+    /// Forgets every key. The database has to be closed by the caller.
+    pub fn lock(&mut self) {
+        self.master = None;
+        set_file_key(None);
+    }
+
+    /// Moves the master key between passphrase and keyring protection, or
+    /// sets it up if storage was never initialized. `new` of `None` means
+    /// the OS keyring. The current passphrase is required whenever one is set.
+    pub fn change_passphrase(&mut self, current: Option<&str>, new: Option<&str>) -> Result<()> {
+        if let Some(new) = new {
+            if new.chars().count() < 8 {
+                return Err(anyhow!("Passphrase must be at least 8 characters"));
+            }
+        }
+
+        let Some(key_file) = self.read_key_file()? else {
+            return self.initialize(new);
+        };
+
+        let master = match key_file.storage {
+            KeyStorage::Passphrase => {
+                let current = current.ok_or_else(|| anyhow!("The current passphrase is required"))?;
+                unwrap_with_passphrase(&key_file, current)?
+            }
+            KeyStorage::Keyring => match &self.master {
+                Some(master) => master.clone(),
+                None => keyring_load()?,
+            },
+        };
+
+        match new {
+            Some(passphrase) => {
+                self.write_key_file(&wrap_with_passphrase(&master, passphrase)?)?;
+                if key_file.storage == KeyStorage::Keyring {
+                    if let Err(e) = keyring_delete() {
+                        eprintln!("Failed to remove storage key from the OS keyring: {}", e);
+                    }
+                }
+            }
+            None if key_file.storage == KeyStorage::Keyring => {
+                return Err(anyhow!("Storage key is already kept in the OS keyring"));
+            }
+            None => {
+                keyring_store(&master)?;
+                self.write_key_file(&KeyFile {
+                    version: KEY_FILE_VERSION,
+                    storage: KeyStorage::Keyring,
+                    check: key_check(&master),
+                    kdf: None,
+                    wrapped_key: None,
+                })?;
+            }
+        }
+
+        self.set_master(master);
+        Ok(())
+    }
+
+    fn initialize(&mut self, passphrase: Option<&str>) -> Result<()> {
+        let mut master: Key = Zeroizing::new([0u8; 32]);
+        OsRng.fill_bytes(master.as_mut());
+
+        let key_file = match passphrase {
+            Some(passphrase) => wrap_with_passphrase(&master, passphrase)?,
+            None => {
+                keyring_store(&master).context("OS keyring is unavailable; set a passphrase to enable storage")?;
+                KeyFile {
+                    version: KEY_FILE_VERSION,
+                    storage: KeyStorage::Keyring,
+                    check: key_check(&master),
+                    kdf: None,
+                    wrapped_key: None,
+                }
+            }
+        };
+        self.write_key_file(&key_file)?;
+        self.set_master(master);
+        Ok(())
+    }
+
+    fn set_master(&mut self, master: Key) {
+        set_file_key(Some(subkey(&master, b"dwight audio files")));
+        self.master = Some(master);
+        self.last_error = None;
+    }
+
+    fn key_file_path(&self) -> PathBuf {
+        self.data_dir.join(KEY_FILE)
+    }
+
+    fn read_key_file(&self) -> Result<Option<KeyFile>> {
+        let path = self.key_file_path();
+        if !path.exists() {
+            return Ok(None);
+        }
+        let contents = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let key_file: KeyFile = serde_json::from_str(&contents)
+            .with_context(|| format!("{} is corrupt", path.display()))?;
+        if key_file.version > KEY_FILE_VERSION {
+            return Err(anyhow!("{} was written by a newer version of Dwight", path.display()));
+        }
+        Ok(Some(key_file))
+    }
+
+    fn write_key_file(&self, key_file: &KeyFile) -> Result<()> {
+        std::fs::create_dir_all(&self.data_dir)?;
+        let path = self.key_file_path();
+        let tmp_path = path.with_extension("key.tmp");
+        std::fs::write(&tmp_path, serde_json::to_string_pretty(key_file)?)?;
+        std::fs::rename(&tmp_path, &path)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+fn subkey(master: &Key, info: &[u8]) -> Key {
+    let mut key: Key = Zeroizing::new([0u8; 32]);
+    Hkdf::<Sha256>::new(None, master.as_ref())
+        .expand(info, key.as_mut())
+        .expect("32 bytes is a valid HKDF-SHA256 output length");
+    key
+}
+
+/// Short public fingerprint of a key, to tell a wrong key or passphrase
+/// apart from corrupt data.
+pub fn key_check(master: &Key) -> String {
+    hex::encode(&subkey(master, b"dwight key check")[..16])
+}
+
+pub fn passphrase_key(passphrase: &str, kdf: &KdfParams) -> Result<Key> {
+    let salt = hex::decode(&kdf.salt).context("Key file has an invalid salt")?;
+    let params = Params::new(kdf.memory_kib, kdf.iterations, kdf.lanes, Some(32))
+        .map_err(|e| anyhow!("Invalid key derivation parameters: {}", e))?;
+    let mut key: Key = Zeroizing::new([0u8; 32]);
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), &salt, key.as_mut())
+        .map_err(|e| anyhow!("Key derivation failed: {}", e))?;
+    Ok(key)
+}
+
+/// Derives a key from a passphrase with a fresh salt and the current cost.
+pub fn new_passphrase_key(passphrase: &str) -> Result<(Key, KdfParams)> {
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let kdf = KdfParams {
+        salt: hex::encode(salt),
+        memory_kib: ARGON2_MEMORY_KIB,
+        iterations: ARGON2_ITERATIONS,
+        lanes: ARGON2_LANES,
+    };
+    Ok((passphrase_key(passphrase, &kdf)?, kdf))
+}
+
+fn wrap_with_passphrase(master: &Key, passphrase: &str) -> Result<KeyFile> {
+    let (wrapping_key, kdf) = new_passphrase_key(passphrase)?;
+    let sealed = seal(&wrapping_key, master.as_ref(), b"dwight master key")?;
+
+    Ok(KeyFile {
+        version: KEY_FILE_VERSION,
+        storage: KeyStorage::Passphrase,
+        check: key_check(master),
+        kdf: Some(kdf),
+        wrapped_key: Some(hex::encode(sealed)),
+    })
+}
+
+fn unwrap_with_passphrase(key_file: &KeyFile, passphrase: &str) -> Result<Key> {
+    let (Some(kdf), Some(wrapped)) = (&key_file.kdf, &key_file.wrapped_key) else {
+        return Err(anyhow!("Key file is missing its passphrase-protected key"));
+    };
+    let wrapping_key = passphrase_key(passphrase, kdf)?;
+    let sealed = hex::decode(wrapped).context("Key file has an invalid wrapped key")?;
+    let opened = Zeroizing::new(
+        open(&wrapping_key, &sealed, b"dwight master key").map_err(|_| anyhow!("Incorrect passphrase"))?,
+    );
+
+    let mut master: Key = Zeroizing::new([0u8; 32]);
+    if opened.len() != master.len() {
+        return Err(anyhow!("Key file has an invalid wrapped key"));
+    }
+    master.copy_from_slice(&opened);
+    Ok(master)
+}
+
+fn keyring_entry(user: &str) -> Result<keyring::Entry> {
+    keyring::Entry::new(KEYRING_SERVICE, user).map_err(|e| anyhow!("OS keyring error: {}", e))
+}
+
+fn keyring_store(master: &Key) -> Result<()> {
+    let encoded = Zeroizing::new(hex::encode(master.as_ref()));
+    keyring_entry(KEYRING_USER)?
+        .set_password(&encoded)
+        .map_err(|e| anyhow!("OS keyring error: {}", e))
+}
+
+fn keyring_load() -> Result<Key> {
+    let encoded = Zeroizing::new(
+        keyring_entry(KEYRING_USER)?
+            .get_password()
+            .map_err(|e| anyhow!("Could not read the storage key from the OS keyring: {}", e))?,
+    );
+    let decoded = Zeroizing::new(hex::decode(encoded.as_str()).context("OS keyring holds an invalid storage key")?);
+
+    let mut master: Key = Zeroizing::new([0u8; 32]);
+    if decoded.len() != master.len() {
+        return Err(anyhow!("OS keyring holds an invalid storage key"));
+    }
+    master.copy_from_slice(&decoded);
+    Ok(master)
+}
+
+fn keyring_delete() -> Result<()> {
+    delete_secret(KEYRING_USER)
+}
+
+/// Keeps a secret other than the storage key, such as an API key, in the OS
+/// keyring. Unlike the library, it is readable while storage is locked.
+pub fn store_secret(name: &str, secret: &str) -> Result<()> {
+    keyring_entry(name)?
+        .set_password(secret)
+        .map_err(|e| anyhow!("OS keyring error: {}", e))
+}
+
+/// A secret saved with [`store_secret`], or `None` if there isn't one.
+pub fn load_secret(name: &str) -> Result<Option<String>> {
+    match keyring_entry(name)?.get_password() {
+        Ok(secret) => Ok(Some(secret)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(anyhow!("OS keyring error: {}", e)),
+    }
+}
+
+pub fn delete_secret(name: &str) -> Result<()> {
+    match keyring_entry(name)?.delete_credential() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(anyhow!("OS keyring error: {}", e)),
+    }
+}
+
+/// XChaCha20-Poly1305 with a random nonce, returned as `nonce || ciphertext`.
+fn seal(key: &Key, plaintext: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+    let mut nonce = [0u8; NONCE_LEN];
+    OsRng.fill_bytes(&mut nonce);
+    let ciphertext = XChaCha20Poly1305::new(key.as_ref().into())
+        .encrypt(XNonce::from_slice(&nonce), Payload { msg: plaintext, aad })
+        .map_err(|_| anyhow!("Encryption failed"))?;
+
+    let mut sealed = Vec::with_capacity(NONCE_LEN + ciphertext.len());
+    sealed.extend_from_slice(&nonce);
+    sealed.extend_from_slice(&ciphertext);
+    Ok(sealed)
+}
+
+fn open(key: &Key, sealed: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+    if sealed.len() < NONCE_LEN {
+        return Err(anyhow!("Encrypted data is truncated"));
+    }
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+    XChaCha20Poly1305::new(key.as_ref().into())
+        .decrypt(XNonce::from_slice(nonce), Payload { msg: ciphertext, aad })
+        .map_err(|_| anyhow!("Decryption failed: wrong key or the data was modified"))
+}
+
+fn set_file_key(key: Option<Key>) {
+    match FILE_KEY.write() {
+        Ok(mut current) => *current = key,
+        Err(poisoned) => *poisoned.into_inner() = key,
+    }
+}
+
+fn file_key() -> Result<Key> {
+    let current = match FILE_KEY.read() {
+        Ok(key) => key.clone(),
+        Err(poisoned) => poisoned.into_inner().clone(),
+    };
+    current.ok_or_else(|| anyhow!("Storage is locked"))
+}
+
+pub fn is_encrypted(bytes: &[u8]) -> bool {
+    bytes.starts_with(FILE_MAGIC)
+}
+
+/// Encrypts file contents under `key` in the format [`read_file`] expects.
+pub fn seal_file_bytes(key: &Key, plaintext: &[u8]) -> Result<Vec<u8>> {
+    let sealed = seal(key, plaintext, FILE_MAGIC)?;
+    let mut contents = Vec::with_capacity(FILE_MAGIC.len() + sealed.len());
+    contents.extend_from_slice(FILE_MAGIC);
+    contents.extend_from_slice(&sealed);
+    Ok(contents)
+}
+
+pub fn open_file_bytes(key: &Key, contents: &[u8]) -> Result<Vec<u8>> {
+    if !is_encrypted(contents) {
+        return Err(anyhow!("Not an encrypted Dwight file"));
+    }
+    open(key, &contents[FILE_MAGIC.len()..], FILE_MAGIC)
+}
+
+/// Reads a file, decrypting it if it was written by [`write_file`]. Files
+/// from elsewhere are returned as they are.
+pub fn read_file(path: &Path) -> Result<Vec<u8>> {
+    let bytes = std::fs::read(path)?;
+    if !is_encrypted(&bytes) {
+        return Ok(bytes);
+    }
+    open_file_bytes(&file_key()?, &bytes)
+        .with_context(|| format!("Failed to decrypt {}", path.display()))
+}
+
+/// Encrypts and writes a file. Refuses to write anything while locked, so
+/// recordings never reach disk in the clear.
+pub fn write_file(path: &Path, plaintext: &[u8]) -> Result<()> {
+    let contents = seal_file_bytes(&file_key()?, plaintext)?;
+    std::fs::write(path, contents).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Encrypts a plaintext file where it stands. Returns false if it was
+/// already encrypted.
+pub fn encrypt_in_place(path: &Path) -> Result<bool> {
+    let bytes = std::fs::read(path)?;
+    if is_encrypted(&bytes) {
+        return Ok(false);
+    }
+    let tmp_path = path.with_extension("enc.tmp");
+    write_file(&tmp_path, &bytes)?;
+    std::fs::rename(&tmp_path, path)?;
+    Ok(true)
+}
+
+#[command]
+pub async fn get_storage_status(state: tauri::State<'_, AppState>) -> Result<StorageStatus, String> {
+    Ok(state.storage_status())
+}
+
+/// Unlocks storage and opens the database. `passphrase` may be left out when
+/// the key is kept in the OS keyring.
+#[command]
+pub async fn unlock_storage(passphrase: Option<String>, app_handle: AppHandle) -> Result<StorageStatus, String> {
+    // Argon2 takes a moment and a fair amount of memory; keep it off the async workers
+    tokio::task::spawn_blocking(move || {
+        let passphrase = passphrase.map(Zeroizing::new);
+        let state = app_handle.state::<AppState>();
+        state.unlock_storage(passphrase.as_deref().map(|p| p.as_str()))?;
+        Ok(state.storage_status())
+    })
+    .await
+    .map_err(|e| format!("Storage error: {}", e))?
+}
+
+#[command]
+pub async fn lock_storage(state: tauri::State<'_, AppState>) -> Result<StorageStatus, String> {
+    state.lock_storage();
+    Ok(state.storage_status())
+}
+
+/// Sets, changes or removes the storage passphrase. Without a new passphrase
+/// the key moves to the OS keyring.
+#[command]
+pub async fn change_passphrase(
+    current_passphrase: Option<String>,
+    new_passphrase: Option<String>,
+    app_handle: AppHandle,
+) -> Result<StorageStatus, String> {
+    tokio::task::spawn_blocking(move || {
+        let current = current_passphrase.map(Zeroizing::new);
+        let new = new_passphrase.map(Zeroizing::new);
+        let state = app_handle.state::<AppState>();
+        state.change_passphrase(
+            current.as_deref().map(|p| p.as_str()),
+            new.as_deref().map(|p| p.as_str()),
+        )?;
+        Ok(state.storage_status())
+    })
+    .await
+    .map_err(|e| format!("Storage error: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Tests that unlock a vault or touch files share the global file key.
+    static FILE_KEY_LOCK: Mutex<()> = Mutex::new(());
+
+    fn file_key_guard() -> std::sync::MutexGuard<'static, ()> {
+        FILE_KEY_LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn random_key() -> Key {
+        let mut key: Key = Zeroizing::new([0u8; 32]);
+        OsRng.fill_bytes(key.as_mut());
+        key
+    }
+
+    /// A scratch data directory, removed when dropped.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!("dwight-vault-{}-{}", std::process::id(), name));
+            let _ = std::fs::remove_dir_all(&dir);
+            std::fs::create_dir_all(&dir).unwrap();
+            TempDir(dir)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    /// A vault in `dir` that never touches the OS keyring, unlike [`Vault::open`].
+    fn passphrase_vault(dir: &Path, passphrase: &str) -> Vault {
+        let mut vault = Vault { data_dir: dir.to_path_buf(), master: None, last_error: None };
+        vault.change_passphrase(None, Some(passphrase)).unwrap();
+        vault
+    }
+
+    #[test]
+    fn seal_open_round_trip() {
+        let key = random_key();
+        let sealed = seal(&key, b"kitchen recording", b"aad").unwrap();
+        assert_ne!(&sealed[NONCE_LEN..], b"kitchen recording");
+        assert_eq!(open(&key, &sealed, b"aad").unwrap(), b"kitchen recording");
+    }
+
+    #[test]
+    fn open_rejects_wrong_key_tampering_and_aad() {
+        let key = random_key();
+        let sealed = seal(&key, b"kitchen recording", b"aad").unwrap();
+        assert!(open(&random_key(), &sealed, b"aad").is_err());
+        assert!(open(&key, &sealed, b"other").is_err());
+        let mut tampered = sealed.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(open(&key, &tampered, b"aad").is_err());
+        assert!(open(&key, &sealed[..NONCE_LEN - 1], b"aad").is_err());
+    }
+
+    #[test]
+    fn wrong_passphrase_does_not_unlock() {
+        let _guard = file_key_guard();
+        let dir = TempDir::new("wrong-passphrase");
+        passphrase_vault(&dir.0, "correct horse battery");
+
+        let mut vault = Vault { data_dir: dir.0.clone(), master: None, last_error: None };
+        assert!(vault.unlock(Some("incorrect horse battery")).is_err());
+        assert!(vault.unlock(None).is_err());
+        assert!(!vault.is_unlocked());
+        vault.unlock(Some("correct horse battery")).unwrap();
+        assert!(vault.is_unlocked());
+    }
+
+    #[test]
+    fn change_passphrase_keeps_master_key() {
+        let _guard = file_key_guard();
+        let dir = TempDir::new("change-passphrase");
+        let mut vault = passphrase_vault(&dir.0, "first passphrase");
+        let database_key = vault.database_key().unwrap();
+
+        assert!(vault.change_passphrase(Some("not the passphrase"), Some("second passphrase")).is_err());
+        assert!(vault.change_passphrase(Some("first passphrase"), Some("short")).is_err());
+        vault.change_passphrase(Some("first passphrase"), Some("second passphrase")).unwrap();
+        assert_eq!(*vault.database_key().unwrap(), *database_key);
+
+        let mut reopened = Vault { data_dir: dir.0.clone(), master: None, last_error: None };
+        assert!(reopened.unlock(Some("first passphrase")).is_err());
+        reopened.unlock(Some("second passphrase")).unwrap();
+        assert_eq!(*reopened.database_key().unwrap(), *database_key);
+    }
+
+    #[test]
+    fn read_file_handles_plaintext_and_encrypted_files() {
+        let _guard = file_key_guard();
+        let dir = TempDir::new("read-file");
+        let mut vault = passphrase_vault(&dir.0, "file passphrase");
+
+        let plain_path = dir.0.join("plain.wav");
+        std::fs::write(&plain_path, b"RIFF plain audio").unwrap();
+        assert_eq!(read_file(&plain_path).unwrap(), b"RIFF plain audio");
+
+        let encrypted_path = dir.0.join("encrypted.wav");
+        write_file(&encrypted_path, b"RIFF secret audio").unwrap();
+        let on_disk = std::fs::read(&encrypted_path).unwrap();
+        assert!(is_encrypted(&on_disk));
+        assert!(!on_disk.windows(b"secret".len()).any(|w| w == b"secret"));
+        assert_eq!(read_file(&encrypted_path).unwrap(), b"RIFF secret audio");
+
+        assert!(encrypt_in_place(&plain_path).unwrap());
+        assert!(!encrypt_in_place(&plain_path).unwrap());
+        assert_eq!(read_file(&plain_path).unwrap(), b"RIFF plain audio");
+
+        // Locked: plaintext still reads, encrypted files and writes are refused
+        vault.lock();
+        assert!(read_file(&encrypted_path).is_err());
+        assert!(write_file(&dir.0.join("new.wav"), b"audio").is_err());
+    }
+}
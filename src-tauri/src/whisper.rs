@@ -1,16 +1,137 @@
-use tauri::command;
+use tauri::{command, State};
 use std::path::Path;
-use std::process::Command;
 use serde::{Deserialize, Serialize};
-use anyhow::Result;
+use std::sync::{Arc, Mutex};
+use anyhow::{anyhow, Result};
+use crate::transcription::{self, BackendKind, TranscriptionBackend};
+use crate::audio_analysis;
+use crate::audio_decode;
+use crate::database;
+use crate::state::AppState;
+use crate::whisper_candle::{self, CandleWhisper};
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct WhisperConfig {
     pub model_path: String,
     pub model_size: String, // tiny, base, small, medium, large
     pub language: Option<String>,
-    pub use_cpp: bool,
+    pub backend: BackendKind,
+    pub cli_path: String,
+    pub server_url: Option<String>,
     pub use_gpu: bool,
+    /// Cut silence out with VAD before handing audio to the backend. The
+    /// threshold follows each recording's own noise floor, but speech quieter
+    /// than -55 dBFS is treated as silence; a recording where nothing is
+    /// found is transcribed whole
+    pub skip_silence: bool,
+}
+
+/// Model sizes published for Whisper, as used in weight file names.
+pub const MODEL_SIZES: [&str; 12] = [
+    "tiny", "tiny.en", "base", "base.en", "small", "small.en",
+    "medium", "medium.en", "large", "large-v2", "large-v3", "large-v3-turbo",
+];
+
+/// Language codes understood by multilingual Whisper checkpoints.
+pub const SUPPORTED_LANGUAGES: [&str; 100] = [
+    "en", "zh", "de", "es", "ru", "ko", "fr", "ja", "pt", "tr", "pl", "ca", "nl", "ar", "sv",
+    "it", "id", "hi", "fi", "vi", "he", "uk", "el", "ms", "cs", "ro", "da", "hu", "ta", "no",
+    "th", "ur", "hr", "bg", "lt", "la", "mi", "ml", "cy", "sk", "te", "fa", "lv", "bn", "sr",
+    "az", "sl", "kn", "et", "mk", "br", "eu", "is", "hy", "ne", "mn", "bs", "kk", "sq", "sw",
+    "gl", "mr", "pa", "si", "km", "sn", "yo", "so", "af", "oc", "ka", "be", "tg", "sd", "gu",
+    "am", "yi", "lo", "uz", "fo", "ht", "ps", "tk", "nn", "mt", "sa", "lb", "my", "bo", "tl",
+    "mg", "as", "tt", "haw", "ln", "ha", "ba", "jw", "su", "yue",
+];
+
+const CONFIG_FILE: &str = "whisper_config.json";
+
+impl WhisperConfig {
+    /// Loads the saved configuration from the app data directory, falling
+    /// back to defaults (with models under the app data dir) when none exists.
+    pub fn load(app_config: &tauri::Config) -> Self {
+        let data_dir = database::app_data_path(app_config);
+        
+        match std::fs::read_to_string(data_dir.join(CONFIG_FILE)) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+                eprintln!("Ignoring unreadable {}: {}", CONFIG_FILE, e);
+                Self::default_for(&data_dir)
+            }),
+            Err(_) => Self::default_for(&data_dir),
+        }
+    }
+    
+    fn default_for(data_dir: &Path) -> Self {
+        WhisperConfig {
+            model_path: data_dir.join("models").join("whisper").to_string_lossy().to_string(),
+            ..Default::default()
+        }
+    }
+    
+    pub fn save(&self, app_config: &tauri::Config) -> Result<()> {
+        let data_dir = database::app_data_path(app_config);
+        std::fs::create_dir_all(&data_dir)?;
+        std::fs::write(data_dir.join(CONFIG_FILE), serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+    
+    /// Returns every problem that would stop the configured backend from running.
+    pub fn validate(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        
+        if !MODEL_SIZES.contains(&self.model_size.as_str()) {
+            errors.push(format!("Unknown model size '{}' (expected one of {})", self.model_size, MODEL_SIZES.join(", ")));
+        }
+        
+        if let Some(lang) = &self.language {
+            if !SUPPORTED_LANGUAGES.contains(&lang.as_str()) {
+                errors.push(format!("Unsupported language code '{}'", lang));
+            } else if self.model_size.ends_with(".en") && lang != "en" {
+                errors.push(format!("Model '{}' is English-only but language is '{}'", self.model_size, lang));
+            }
+        }
+        
+        match self.backend {
+            BackendKind::WhisperCppCli => {
+                let model_file = Path::new(&self.model_path).join(format!("ggml-{}.bin", self.model_size));
+                if !model_file.exists() {
+                    errors.push(format!("Model file not found: {}", model_file.display()));
+                }
+            }
+            BackendKind::WhisperCppServer => match &self.server_url {
+                Some(url) if url.starts_with("http://") || url.starts_with("https://") => {}
+                Some(url) => errors.push(format!("Server URL must start with http:// or https://: {}", url)),
+                None => errors.push("No whisper.cpp server URL configured".to_string()),
+            },
+            BackendKind::Candle => {
+                if !whisper_candle::model_available(&self.model_path, &self.model_size) {
+                    let dir = CandleWhisper::resolve_model_dir(&self.model_path, &self.model_size);
+                    errors.push(format!(
+                        "No candle Whisper weights in {} (need config.json, tokenizer.json and model.safetensors or .gguf)",
+                        dir.display()
+                    ));
+                }
+            }
+            BackendKind::Mock => {}
+        }
+        
+        errors
+    }
+}
+
+impl Default for WhisperConfig {
+    fn default() -> Self {
+        WhisperConfig {
+            model_path: "./models/whisper".to_string(),
+            model_size: "base".to_string(),
+            language: None,
+            backend: BackendKind::WhisperCppCli,
+            cli_path: "whisper".to_string(),
+            server_url: None,
+            use_gpu: false,
+            skip_silence: true,
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -20,8 +141,22 @@ pub struct TranscriptionResult {
     pub language: String,
     pub processing_time_ms: u64,
     pub confidence: f32,
+    pub backend: String,
+    pub simulated: bool,
+}
+
+/// Which backend produced the most recent transcription, for `get_whisper_status`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackendRun {
+    pub backend: String,
+    pub simulated: bool,
+    pub success: bool,
+    pub error: Option<String>,
+    pub finished_at: String,
 }
 
+static LAST_RUN: Mutex<Option<BackendRun>> = Mutex::new(None);
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TranscriptionSegment {
     pub start: f64,
@@ -75,341 +210,258 @@ pub struct EmotionalTone {
 
 pub struct WhisperEngine {
     config: WhisperConfig,
+    backend: Arc<dyn TranscriptionBackend>,
 }
 
 impl WhisperEngine {
-    pub fn new() -> Self {
-        WhisperEngine {
-            config: WhisperConfig {
-                model_path: "./models/whisper".to_string(),
-                model_size: "base".to_string(),
-                language: None,
-                use_cpp: true,
-                use_gpu: false,
-            }
-        }
+    pub fn with_config(config: WhisperConfig) -> Self {
+        let backend = Arc::from(transcription::create_backend(config.backend));
+        WhisperEngine { config, backend }
     }
     
-    pub async fn transcribe_with_whisper_cpp(&self, file_path: &str) -> Result<TranscriptionResult> {
-        let start_time = std::time::Instant::now();
-        
-        // Check if whisper.cpp is available
-        let whisper_cpp_path = "whisper"; // Assumes whisper.cpp is in PATH
-        
-        let mut cmd = Command::new(whisper_cpp_path);
-        cmd.arg("-m").arg(format!("{}/ggml-{}.bin", self.config.model_path, self.config.model_size))
-           .arg("-f").arg(file_path)
-           .arg("--output-json")
-           .arg("--output-file").arg("/tmp/whisper_output");
-        
-        if let Some(lang) = &self.config.language {
-            cmd.arg("-l").arg(lang);
-        }
-        
-        if self.config.use_gpu {
-            cmd.arg("-ngl").arg("1");
-        }
-        
-        match cmd.output() {
-            Ok(output) => {
-                if output.status.success() {
-                    // Parse whisper.cpp JSON output
-                    if let Ok(json_content) = std::fs::read_to_string("/tmp/whisper_output.json") {
-                        if let Ok(whisper_result) = serde_json::from_str::<serde_json::Value>(&json_content) {
-                            return self.parse_whisper_output(whisper_result, start_time);
-                        }
-                    }
-                }
-            }
-            Err(_) => {
-                // Fallback to simulated transcription if whisper.cpp is not available
-                return self.simulate_transcription(file_path, start_time).await;
-            }
-        }
-        
-        // Fallback
-        self.simulate_transcription(file_path, start_time).await
+    pub fn config(&self) -> &WhisperConfig {
+        &self.config
     }
     
-    fn parse_whisper_output(&self, whisper_result: serde_json::Value, start_time: std::time::Instant) -> Result<TranscriptionResult> {
-        let transcription = whisper_result["transcription"]
-            .as_str()
-            .unwrap_or("Unable to transcribe audio")
-            .to_string();
-        
-        let mut segments = Vec::new();
-        
-        if let Some(segments_array) = whisper_result["segments"].as_array() {
-            for segment in segments_array {
-                segments.push(TranscriptionSegment {
-                    start: segment["start"].as_f64().unwrap_or(0.0),
-                    end: segment["end"].as_f64().unwrap_or(0.0),
-                    text: segment["text"].as_str().unwrap_or("").to_string(),
-                    confidence: segment["confidence"].as_f64().unwrap_or(0.8) as f32,
-                });
-            }
-        }
-        
-        Ok(TranscriptionResult {
-            text: transcription,
-            segments,
-            language: whisper_result["language"].as_str().unwrap_or("en").to_string(),
-            processing_time_ms: start_time.elapsed().as_millis() as u64,
-            confidence: 0.85,
-        })
+    /// Runs the backend on the calling thread, for callers already off the async runtime.
+    pub fn transcribe_blocking(&self, file_path: &str) -> Result<TranscriptionResult> {
+        self.backend.transcribe(file_path, &self.config)
     }
     
-    async fn simulate_transcription(&self, file_path: &str, start_time: std::time::Instant) -> Result<TranscriptionResult> {
-        let file_name = Path::new(file_path)
-            .file_name()
-            .and_then(|n| n.to_str())
-            .unwrap_or("unknown");
+    /// Transcribes a file with the configured backend. Failures are returned
+    /// rather than papered over with simulated output.
+    pub async fn transcribe(&self, file_path: &str) -> Result<TranscriptionResult> {
+        let backend = self.backend.clone();
+        let config = self.config.clone();
+        let path = file_path.to_string();
         
-        // Simulate processing time
-        tokio::time::sleep(tokio::time::Duration::from_millis(1500)).await;
+        let result = tokio::task::spawn_blocking(move || {
+            if config.skip_silence && backend.kind() != BackendKind::Mock {
+                transcription::transcribe_speech_only(backend.as_ref(), &path, &config)
+            } else {
+                backend.transcribe(&path, &config)
+            }
+        })
+            .await
+            .map_err(|e| anyhow!("Transcription task failed: {}", e))
+            .and_then(|result| result);
         
-        // Create realistic simulated transcription based on filename
-        let (text, segments) = if file_name.to_lowercase().contains("conversation") {
-            let text = "Speaker 1: Hello, how are you today?\nSpeaker 2: I'm doing well, thanks for asking. How about you?\nSpeaker 1: Pretty good, just working on some audio analysis projects.\nSpeaker 2: That sounds interesting. What kind of analysis are you doing?";
-            let segments = vec![
-                TranscriptionSegment {
-                    start: 0.0,
-                    end: 2.5,
-                    text: "Hello, how are you today?".to_string(),
-                    confidence: 0.92,
-                },
-                TranscriptionSegment {
-                    start: 3.0,
-                    end: 6.8,
-                    text: "I'm doing well, thanks for asking. How about you?".to_string(),
-                    confidence: 0.88,
-                },
-                TranscriptionSegment {
-                    start: 7.2,
-                    end: 11.1,
-                    text: "Pretty good, just working on some audio analysis projects.".to_string(),
-                    confidence: 0.90,
-                },
-                TranscriptionSegment {
-                    start: 11.5,
-                    end: 14.8,
-                    text: "That sounds interesting. What kind of analysis are you doing?".to_string(),
-                    confidence: 0.87,
-                },
-            ];
-            (text.to_string(), segments)
-        } else if file_name.to_lowercase().contains("security") {
-            let text = "Radio chatter detected. Multiple voices discussing checkpoint procedures. Keywords: security, perimeter, all clear, proceed with caution.";
-            let segments = vec![
-                TranscriptionSegment {
-                    start: 0.0,
-                    end: 5.2,
-                    text: "Radio chatter detected. Multiple voices discussing checkpoint procedures.".to_string(),
-                    confidence: 0.79,
-                },
-                TranscriptionSegment {
-                    start: 5.5,
-                    end: 9.8,
-                    text: "Keywords: security, perimeter, all clear, proceed with caution.".to_string(),
-                    confidence: 0.82,
-                },
-            ];
-            (text.to_string(), segments)
-        } else {
-            let text = format!("Transcription of audio file: {}\n\nThis is a simulated transcription demonstrating Dwight's advanced audio processing capabilities. Real implementation uses Whisper C++ for accurate speech recognition.", file_name);
-            let segments = vec![
-                TranscriptionSegment {
-                    start: 0.0,
-                    end: 3.0,
-                    text: "Transcription of audio file".to_string(),
-                    confidence: 0.85,
-                },
-            ];
-            (text, segments)
+        let run = BackendRun {
+            backend: self.backend.kind().as_str().to_string(),
+            simulated: self.backend.kind() == BackendKind::Mock,
+            success: result.is_ok(),
+            error: result.as_ref().err().map(|e| e.to_string()),
+            finished_at: chrono::Utc::now().to_rfc3339(),
         };
+        if let Ok(mut last_run) = LAST_RUN.lock() {
+            *last_run = Some(run);
+        }
         
-        Ok(TranscriptionResult {
-            text,
-            segments,
-            language: "en".to_string(),
-            processing_time_ms: start_time.elapsed().as_millis() as u64,
-            confidence: 0.82,
-        })
+        result
     }
     
+    /// Decodes the file and measures it: duration, format, quality and speech regions.
     pub async fn analyze_audio_advanced(&self, file_path: &str) -> Result<AudioAnalysis> {
-        // Simulate advanced audio analysis
-        tokio::time::sleep(tokio::time::Duration::from_millis(2000)).await;
-        
-        let file_name = Path::new(file_path)
-            .file_name()
-            .and_then(|n| n.to_str())
-            .unwrap_or("unknown");
+        let file_path = file_path.to_string();
         
-        // Create context-appropriate analysis
-        let analysis = if file_name.to_lowercase().contains("security") {
-            AudioAnalysis {
-                duration_seconds: 125.6,
-                sample_rate: 44100,
-                channels: 2,
-                speech_segments: vec![
-                    SpeechSegment {
-                        start: 0.0,
-                        end: 45.2,
-                        confidence: 0.94,
-                        speaker: "Security Officer 1".to_string(),
-                        language: "en".to_string(),
-                    },
-                    SpeechSegment {
-                        start: 45.5,
-                        end: 89.1,
-                        confidence: 0.87,
-                        speaker: "Security Officer 2".to_string(),
-                        language: "en".to_string(),
-                    },
-                ],
-                non_speech_events: vec![
-                    NonSpeechEvent {
-                        start: 14.2,
-                        end: 16.8,
-                        event_type: "footsteps".to_string(),
-                        confidence: 0.78,
-                    },
-                    NonSpeechEvent {
-                        start: 22.1,
-                        end: 24.3,
-                        event_type: "door_slam".to_string(),
-                        confidence: 0.92,
-                    },
-                    NonSpeechEvent {
-                        start: 49.7,
-                        end: 52.1,
-                        event_type: "vehicle_engine".to_string(),
-                        confidence: 0.85,
-                    },
-                ],
-                audio_quality: AudioQuality {
-                    snr_db: 28.4,
-                    clarity_score: 0.82,
-                    background_noise_level: 0.15,
-                },
-                keywords_detected: vec!["security".to_string(), "checkpoint".to_string(), "perimeter".to_string(), "all clear".to_string()],
-                emotional_tone: EmotionalTone {
-                    urgency: 0.23,
-                    stress: 0.31,
-                    confidence: 0.76,
-                },
-            }
-        } else {
-            AudioAnalysis {
-                duration_seconds: 67.3,
-                sample_rate: 44100,
-                channels: 2,
-                speech_segments: vec![
-                    SpeechSegment {
-                        start: 0.0,
-                        end: 30.5,
-                        confidence: 0.89,
-                        speaker: "Speaker 1".to_string(),
-                        language: "en".to_string(),
-                    },
-                ],
-                non_speech_events: vec![
-                    NonSpeechEvent {
-                        start: 5.2,
-                        end: 6.1,
-                        event_type: "background_music".to_string(),
-                        confidence: 0.65,
-                    },
-                ],
-                audio_quality: AudioQuality {
-                    snr_db: 32.1,
-                    clarity_score: 0.91,
-                    background_noise_level: 0.08,
-                },
-                keywords_detected: vec!["analysis".to_string(), "audio".to_string(), "processing".to_string()],
-                emotional_tone: EmotionalTone {
-                    urgency: 0.12,
-                    stress: 0.08,
-                    confidence: 0.88,
-                },
-            }
-        };
-        
-        Ok(analysis)
+        tokio::task::spawn_blocking(move || {
+            let decoded = audio_decode::decode_file(&file_path, None)?;
+            Ok(audio_analysis::analyze_samples(&decoded.samples, decoded.sample_rate, decoded.channels))
+        })
+        .await?
+    }
+}
+
+/// The shared engine, refusing to run when its configuration is invalid.
+fn configured_engine(state: &AppState) -> Result<Arc<WhisperEngine>, String> {
+    let engine = state.whisper();
+    let errors = engine.config.validate();
+    if !errors.is_empty() {
+        return Err(format!("Whisper is not configured correctly: {}", errors.join("; ")));
+    }
+    Ok(engine)
+}
+
+/// Runs `store` against the audio record for `file_path`, if there is one.
+/// Failures are logged rather than failing the command that produced the data.
+fn store_for_record<F>(state: &AppState, file_path: &str, what: &str, store: F)
+where
+    F: FnOnce(&database::Database, i64) -> rusqlite::Result<()>,
+{
+    let stored = state.db().and_then(|db| {
+        match db.find_record_by_path(file_path).map_err(|e| e.to_string())? {
+            Some(id) => store(&db, id).map_err(|e| e.to_string()),
+            None => Ok(()),
+        }
+    });
+    if let Err(e) = stored {
+        eprintln!("Failed to store {} for {}: {}", what, file_path, e);
+    }
+}
+
+/// Stores the transcript and segments on the record so they show up in
+/// transcript search. Simulated output is never stored.
+fn store_transcript(state: &AppState, file_path: &str, result: &TranscriptionResult) {
+    if result.simulated {
+        return;
     }
+    store_for_record(state, file_path, "transcript", |db, id| {
+        db.save_transcription(id, &result.text, &result.segments)
+    });
+    state.request_index_update();
 }
 
 #[command]
-pub async fn transcribe_audio(file_path: String) -> Result<String, String> {
-    let engine = WhisperEngine::new();
-    
+pub async fn transcribe_audio(file_path: String, state: State<'_, AppState>) -> Result<String, String> {
     // Validate file exists
     if !Path::new(&file_path).exists() {
         return Err(format!("Audio file not found: {}", file_path));
     }
     
-    match engine.transcribe_with_whisper_cpp(&file_path).await {
-        Ok(result) => Ok(result.text),
+    let engine = configured_engine(&state)?;
+    
+    match engine.transcribe(&file_path).await {
+        Ok(result) => {
+            store_transcript(&state, &file_path, &result);
+            Ok(result.text)
+        }
         Err(e) => Err(format!("Transcription failed: {}", e)),
     }
 }
 
 #[command]
-pub async fn transcribe_audio_detailed(file_path: String) -> Result<TranscriptionResult, String> {
-    let engine = WhisperEngine::new();
-    
+pub async fn transcribe_audio_detailed(file_path: String, state: State<'_, AppState>) -> Result<TranscriptionResult, String> {
     if !Path::new(&file_path).exists() {
         return Err(format!("Audio file not found: {}", file_path));
     }
     
-    engine.transcribe_with_whisper_cpp(&file_path)
+    let engine = configured_engine(&state)?;
+    
+    let result = engine.transcribe(&file_path)
         .await
-        .map_err(|e| format!("Detailed transcription failed: {}", e))
+        .map_err(|e| format!("Detailed transcription failed: {}", e))?;
+    store_transcript(&state, &file_path, &result);
+    Ok(result)
 }
 
 #[command]
-pub async fn analyze_audio_features(file_path: String) -> Result<AudioAnalysis, String> {
-    let engine = WhisperEngine::new();
+pub async fn analyze_audio_features(file_path: String, state: State<'_, AppState>) -> Result<AudioAnalysis, String> {
+    let engine = state.whisper();
     
     if !Path::new(&file_path).exists() {
         return Err(format!("Audio file not found: {}", file_path));
     }
     
-    engine.analyze_audio_advanced(&file_path)
+    let analysis = engine.analyze_audio_advanced(&file_path)
         .await
-        .map_err(|e| format!("Audio analysis failed: {}", e))
+        .map_err(|e| format!("Audio analysis failed: {}", e))?;
+    store_for_record(&state, &file_path, "audio events", |db, id| {
+        db.save_audio_events(id, &analysis.non_speech_events)
+    });
+    Ok(analysis)
 }
 
+/// Updates and persists the Whisper configuration. Fields left out keep their
+/// saved values; nothing is written if the result fails validation.
 #[command]
+#[allow(clippy::too_many_arguments)]
 pub async fn configure_whisper(
-    model_size: String,
+    model_size: Option<String>,
     language: Option<String>,
-    use_cpp: bool,
-    use_gpu: bool,
-) -> Result<String, String> {
-    // In a real implementation, this would update the configuration
-    Ok(format!("Whisper configured: model={}, lang={:?}, cpp={}, gpu={}", 
-               model_size, language, use_cpp, use_gpu))
+    backend: Option<String>,
+    use_cpp: Option<bool>,
+    use_gpu: Option<bool>,
+    model_path: Option<String>,
+    cli_path: Option<String>,
+    server_url: Option<String>,
+    skip_silence: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<WhisperConfig, String> {
+    let mut config = state.whisper().config.clone();
+    
+    if let Some(name) = backend {
+        config.backend = BackendKind::parse(&name).ok_or_else(|| format!("Unknown transcription backend: {}", name))?;
+    } else if let Some(use_cpp) = use_cpp {
+        config.backend = if use_cpp { BackendKind::WhisperCppCli } else { BackendKind::Candle };
+    }
+    if let Some(model_size) = model_size {
+        config.model_size = model_size;
+    }
+    if let Some(model_path) = model_path {
+        config.model_path = model_path;
+    }
+    if let Some(cli_path) = cli_path {
+        config.cli_path = cli_path;
+    }
+    if let Some(server_url) = server_url {
+        config.server_url = Some(server_url).filter(|url| !url.trim().is_empty());
+    }
+    if let Some(use_gpu) = use_gpu {
+        config.use_gpu = use_gpu;
+    }
+    if let Some(skip_silence) = skip_silence {
+        config.skip_silence = skip_silence;
+    }
+    // An empty language string means auto-detect
+    config.language = match language {
+        Some(lang) if lang.trim().is_empty() => None,
+        Some(lang) => Some(lang),
+        None => config.language,
+    };
+    
+    let errors = config.validate();
+    if !errors.is_empty() {
+        return Err(format!("Invalid Whisper configuration: {}", errors.join("; ")));
+    }
+    
+    config.save(state.app_config()).map_err(|e| format!("Failed to save Whisper configuration: {}", e))?;
+    state.set_whisper_config(config.clone());
+    Ok(config)
 }
 
 #[command]
-pub async fn get_whisper_status() -> Result<serde_json::Value, String> {
-    let engine = WhisperEngine::new();
+pub async fn get_whisper_status(state: State<'_, AppState>) -> Result<serde_json::Value, String> {
+    let engine = state.whisper();
+    let config = engine.config.clone();
+    let validation_errors = config.validate();
+    
+    let availability = tokio::task::spawn_blocking(move || {
+        [BackendKind::WhisperCppCli, BackendKind::WhisperCppServer, BackendKind::Candle, BackendKind::Mock]
+            .into_iter()
+            .map(|kind| (kind.as_str().to_string(), transcription::create_backend(kind).is_available(&config).into()))
+            .collect::<serde_json::Map<String, serde_json::Value>>()
+    })
+    .await
+    .map_err(|e| format!("Status check failed: {}", e))?;
     
-    // Check if whisper.cpp is available
-    let whisper_available = Command::new("whisper")
-        .arg("--help")
-        .output()
-        .is_ok();
+    let configured_available = availability
+        .get(engine.config.backend.as_str())
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    let last_run = LAST_RUN.lock().ok().and_then(|run| run.clone());
     
     Ok(serde_json::json!({
-        "whisper_cpp_available": whisper_available,
+        "config": engine.config,
+        "validation_errors": validation_errors,
+        "backend": engine.config.backend.as_str(),
+        "backend_available": configured_available,
+        "backends": availability,
+        "simulated": engine.config.backend == BackendKind::Mock,
+        "last_run": last_run,
         "model_path": engine.config.model_path,
         "model_size": engine.config.model_size,
-        "use_cpp": engine.config.use_cpp,
+        "language": engine.config.language,
         "use_gpu": engine.config.use_gpu,
-        "supported_languages": ["en", "es", "fr", "de", "it", "pt", "ru", "ja", "ko", "zh"],
-        "status": if whisper_available { "ready" } else { "fallback_mode" }
+        "supported_model_sizes": MODEL_SIZES,
+        "supported_languages": SUPPORTED_LANGUAGES.as_slice(),
+        "status": if !validation_errors.is_empty() {
+            "misconfigured"
+        } else if !configured_available {
+            "unavailable"
+        } else if engine.config.backend == BackendKind::Mock {
+            "simulated"
+        } else {
+            "ready"
+        }
     }))
-}
\ No newline at end of file
+}
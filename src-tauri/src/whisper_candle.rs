@@ -0,0 +1,451 @@
+use candle_core::{Device, IndexOp, Tensor, D};
+use candle_nn::VarBuilder;
+use candle_transformers::models::whisper::{self as m, audio, Config};
+use std::path::{Path, PathBuf};
+use anyhow::{anyhow, Context, Result};
+use tokenizers::Tokenizer;
+use crate::audio_decode;
+use crate::whisper::TranscriptionSegment;
+
+// Whisper timestamp tokens advance in 20 ms steps
+const TIMESTAMP_STEP_SECONDS: f64 = 0.02;
+
+/// Languages we try during automatic language detection.
+pub const DETECTABLE_LANGUAGES: [&str; 10] = ["en", "es", "fr", "de", "it", "pt", "ru", "ja", "ko", "zh"];
+
+enum WhisperModel {
+    Normal(m::model::Whisper),
+    Quantized(m::quantized_model::Whisper),
+}
+
+impl WhisperModel {
+    fn config(&self) -> &Config {
+        match self {
+            WhisperModel::Normal(model) => &model.config,
+            WhisperModel::Quantized(model) => &model.config,
+        }
+    }
+
+    fn encoder_forward(&mut self, x: &Tensor, flush: bool) -> candle_core::Result<Tensor> {
+        match self {
+            WhisperModel::Normal(model) => model.encoder.forward(x, flush),
+            WhisperModel::Quantized(model) => model.encoder.forward(x, flush),
+        }
+    }
+
+    fn decoder_forward(&mut self, x: &Tensor, xa: &Tensor, flush: bool) -> candle_core::Result<Tensor> {
+        match self {
+            WhisperModel::Normal(model) => model.decoder.forward(x, xa, flush),
+            WhisperModel::Quantized(model) => model.decoder.forward(x, xa, flush),
+        }
+    }
+
+    fn decoder_final_linear(&self, x: &Tensor) -> candle_core::Result<Tensor> {
+        match self {
+            WhisperModel::Normal(model) => model.decoder.final_linear(x),
+            WhisperModel::Quantized(model) => model.decoder.final_linear(x),
+        }
+    }
+}
+
+/// Output of decoding one 30 second window.
+struct WindowResult {
+    segments: Vec<TranscriptionSegment>,
+    avg_logprob: f64,
+    no_speech_prob: f64,
+}
+
+/// In-process Whisper running on candle, loading weights from a local model directory.
+///
+/// The directory must contain `config.json`, `tokenizer.json` and either
+/// `model.safetensors` or a single `*.gguf` file for quantized weights.
+pub struct CandleWhisper {
+    model: WhisperModel,
+    tokenizer: Tokenizer,
+    mel_filters: Vec<f32>,
+    device: Device,
+    suppress_tokens: Tensor,
+    sot_token: u32,
+    transcribe_token: u32,
+    eot_token: u32,
+    no_timestamps_token: u32,
+    no_speech_token: Option<u32>,
+}
+
+impl CandleWhisper {
+    /// Resolves the directory holding the weights for `model_size`, preferring
+    /// a `<model_path>/<model_size>` subdirectory when one exists.
+    pub fn resolve_model_dir(model_path: &str, model_size: &str) -> PathBuf {
+        let sized = Path::new(model_path).join(model_size);
+        if sized.join("config.json").exists() {
+            sized
+        } else {
+            PathBuf::from(model_path)
+        }
+    }
+
+    pub fn load(model_dir: &Path, use_gpu: bool) -> Result<Self> {
+        let device = if use_gpu { Device::cuda_if_available(0)? } else { Device::Cpu };
+
+        let config_path = model_dir.join("config.json");
+        let config: Config = serde_json::from_str(
+            &std::fs::read_to_string(&config_path)
+                .with_context(|| format!("Missing Whisper config at {}", config_path.display()))?,
+        )?;
+
+        let tokenizer_path = model_dir.join("tokenizer.json");
+        let tokenizer = Tokenizer::from_file(&tokenizer_path)
+            .map_err(|e| anyhow!("Failed to load tokenizer {}: {}", tokenizer_path.display(), e))?;
+
+        let model = match find_weights(model_dir)? {
+            Weights::Safetensors(path) => {
+                // Safety: the weights file is memory-mapped read-only and not modified while loaded
+                let vb = unsafe { VarBuilder::from_mmaped_safetensors(&[path], m::DTYPE, &device)? };
+                WhisperModel::Normal(m::model::Whisper::load(&vb, config.clone())?)
+            }
+            Weights::Gguf(path) => {
+                let vb = candle_transformers::quantized_var_builder::VarBuilder::from_gguf(path, &device)?;
+                WhisperModel::Quantized(m::quantized_model::Whisper::load(&vb, config.clone())?)
+            }
+        };
+
+        let mel_filters = mel_filters(config.num_mel_bins, m::N_FFT, m::SAMPLE_RATE);
+
+        let sot_token = token_id(&tokenizer, m::SOT_TOKEN)?;
+        let transcribe_token = token_id(&tokenizer, m::TRANSCRIBE_TOKEN)?;
+        let eot_token = token_id(&tokenizer, m::EOT_TOKEN)?;
+        let no_timestamps_token = token_id(&tokenizer, m::NO_TIMESTAMPS_TOKEN)?;
+        let no_speech_token = m::NO_SPEECH_TOKENS
+            .iter()
+            .find_map(|token| tokenizer.token_to_id(token));
+
+        // Mask tokens the model config asks us to never emit
+        let suppress: Vec<f32> = (0..config.vocab_size as u32)
+            .map(|i| {
+                if config.suppress_tokens.contains(&i) || i == no_timestamps_token {
+                    f32::NEG_INFINITY
+                } else {
+                    0.0
+                }
+            })
+            .collect();
+        let suppress_tokens = Tensor::new(suppress.as_slice(), &device)?;
+
+        Ok(CandleWhisper {
+            model,
+            tokenizer,
+            mel_filters,
+            device,
+            suppress_tokens,
+            sot_token,
+            transcribe_token,
+            eot_token,
+            no_timestamps_token,
+            no_speech_token,
+        })
+    }
+
+    /// Transcribes 16 kHz mono samples, returning timed segments, the language
+    /// used and an overall confidence derived from token log-probabilities.
+    pub fn transcribe(&mut self, samples: &[f32], language: Option<&str>) -> Result<(Vec<TranscriptionSegment>, String, f32)> {
+        let config = self.model.config().clone();
+        let mel = audio::pcm_to_mel(&config, samples, &self.mel_filters);
+        let mel_len = mel.len();
+        let mel = Tensor::from_vec(mel, (1, config.num_mel_bins, mel_len / config.num_mel_bins), &self.device)?;
+        let (_, _, content_frames) = mel.dims3()?;
+
+        // pcm_to_mel pads with 30 s of silence. Windows are always cut at full
+        // length from the padded mel, as the encoder expects, but the loop
+        // only runs over frames that hold real audio
+        let audio_frames = samples.len().div_ceil(m::HOP_LENGTH).min(content_frames);
+
+        let language = match language {
+            Some(lang) => lang.to_string(),
+            None => self.detect_language(&mel)?,
+        };
+        let language_token = self.tokenizer.token_to_id(&format!("<|{}|>", language));
+
+        let mut segments = Vec::new();
+        let mut logprob_sum = 0.0;
+        let mut windows = 0;
+        let mut seek = 0;
+
+        while seek < audio_frames {
+            let window = mel.narrow(2, seek, usize::min(content_frames - seek, m::N_FRAMES))?;
+            let window_frames = usize::min(audio_frames - seek, m::N_FRAMES);
+            let offset = (seek * m::HOP_LENGTH) as f64 / m::SAMPLE_RATE as f64;
+            let window_seconds = (window_frames * m::HOP_LENGTH) as f64 / m::SAMPLE_RATE as f64;
+
+            let result = self.decode_window(&window, language_token, offset, window_seconds)?;
+            seek += window_frames;
+
+            if result.no_speech_prob > m::NO_SPEECH_THRESHOLD && result.avg_logprob < m::LOGPROB_THRESHOLD {
+                continue;
+            }
+
+            logprob_sum += result.avg_logprob;
+            windows += 1;
+            segments.extend(result.segments);
+        }
+
+        let confidence = if windows > 0 {
+            (logprob_sum / windows as f64).exp() as f32
+        } else {
+            0.0
+        };
+
+        Ok((segments, language, confidence))
+    }
+
+    fn detect_language(&mut self, mel: &Tensor) -> Result<String> {
+        let candidates: Vec<(&str, u32)> = DETECTABLE_LANGUAGES
+            .iter()
+            .filter_map(|lang| self.tokenizer.token_to_id(&format!("<|{}|>", lang)).map(|id| (*lang, id)))
+            .collect();
+
+        // English-only checkpoints have no language tokens at all
+        if candidates.is_empty() {
+            return Ok("en".to_string());
+        }
+
+        // The first full window of the padded mel, even for short clips
+        let (_, _, content_frames) = mel.dims3()?;
+        let window = mel.narrow(2, 0, usize::min(content_frames, m::N_FRAMES))?;
+        let audio_features = self.model.encoder_forward(&window, true)?;
+
+        let tokens = Tensor::new(&[[self.sot_token]], &self.device)?;
+        let ys = self.model.decoder_forward(&tokens, &audio_features, true)?;
+        let logits = self.model.decoder_final_linear(&ys.i(..1)?)?.i(0)?.i(0)?;
+
+        let ids: Vec<u32> = candidates.iter().map(|(_, id)| *id).collect();
+        let ids = Tensor::new(ids.as_slice(), &self.device)?;
+        let scores = logits.index_select(&ids, 0)?.to_vec1::<f32>()?;
+
+        let best = scores
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .map(|(i, _)| candidates[i].0)
+            .unwrap_or("en");
+        Ok(best.to_string())
+    }
+
+    fn decode_window(
+        &mut self,
+        mel: &Tensor,
+        language_token: Option<u32>,
+        offset: f64,
+        window_seconds: f64,
+    ) -> Result<WindowResult> {
+        let audio_features = self.model.encoder_forward(mel, true)?;
+        let max_tokens = self.model.config().max_target_positions / 2;
+
+        let mut tokens = vec![self.sot_token];
+        if let Some(language_token) = language_token {
+            tokens.push(language_token);
+        }
+        tokens.push(self.transcribe_token);
+        let prompt_len = tokens.len();
+
+        let mut sum_logprob = 0.0;
+        let mut no_speech_prob = 0.0;
+
+        for i in 0..max_tokens {
+            let tokens_t = Tensor::new(tokens.as_slice(), &self.device)?.unsqueeze(0)?;
+            let ys = self.model.decoder_forward(&tokens_t, &audio_features, i == 0)?;
+
+            if i == 0 {
+                if let Some(no_speech_token) = self.no_speech_token {
+                    let logits = self.model.decoder_final_linear(&ys.i(..1)?)?.i(0)?.i(0)?;
+                    no_speech_prob = candle_nn::ops::softmax(&logits, 0)?
+                        .i(no_speech_token as usize)?
+                        .to_scalar::<f32>()? as f64;
+                }
+            }
+
+            let (_, seq_len, _) = ys.dims3()?;
+            let logits = self.model
+                .decoder_final_linear(&ys.i((..1, seq_len - 1..))?)?
+                .i(0)?
+                .i(0)?
+                .broadcast_add(&self.suppress_tokens)?;
+
+            let next_token = logits.argmax(D::Minus1)?.to_scalar::<u32>()?;
+            let prob = candle_nn::ops::softmax(&logits, D::Minus1)?
+                .i(next_token as usize)?
+                .to_scalar::<f32>()? as f64;
+
+            if next_token == self.eot_token || tokens.len() > self.model.config().max_target_positions {
+                break;
+            }
+            tokens.push(next_token);
+            sum_logprob += prob.max(f64::MIN_POSITIVE).ln();
+        }
+
+        let generated = &tokens[prompt_len..];
+        let avg_logprob = sum_logprob / generated.len().max(1) as f64;
+        let confidence = avg_logprob.exp() as f32;
+        let segments = self.split_segments(generated, offset, window_seconds, confidence)?;
+
+        Ok(WindowResult {
+            segments,
+            avg_logprob,
+            no_speech_prob,
+        })
+    }
+
+    /// Splits a decoded token stream on timestamp tokens into timed segments.
+    fn split_segments(
+        &self,
+        tokens: &[u32],
+        offset: f64,
+        window_seconds: f64,
+        confidence: f32,
+    ) -> Result<Vec<TranscriptionSegment>> {
+        let mut segments = Vec::new();
+        let mut text_tokens: Vec<u32> = Vec::new();
+        let mut segment_start: Option<f64> = None;
+
+        for &token in tokens {
+            if token > self.no_timestamps_token {
+                let timestamp = (token - self.no_timestamps_token - 1) as f64 * TIMESTAMP_STEP_SECONDS;
+                match segment_start {
+                    Some(start) if !text_tokens.is_empty() => {
+                        let text = self.decode_text(&text_tokens)?;
+                        if !text.is_empty() {
+                            segments.push(TranscriptionSegment {
+                                start: offset + start,
+                                end: offset + timestamp,
+                                text,
+                                confidence,
+                            });
+                        }
+                        text_tokens.clear();
+                        segment_start = None;
+                    }
+                    _ => segment_start = Some(timestamp),
+                }
+            } else {
+                text_tokens.push(token);
+            }
+        }
+
+        // Trailing text without a closing timestamp runs to the end of the window
+        if !text_tokens.is_empty() {
+            let text = self.decode_text(&text_tokens)?;
+            if !text.is_empty() {
+                segments.push(TranscriptionSegment {
+                    start: offset + segment_start.unwrap_or(0.0),
+                    end: offset + window_seconds,
+                    text,
+                    confidence,
+                });
+            }
+        }
+
+        Ok(segments)
+    }
+
+    fn decode_text(&self, tokens: &[u32]) -> Result<String> {
+        let text = self.tokenizer
+            .decode(tokens, true)
+            .map_err(|e| anyhow!("Failed to decode tokens: {}", e))?;
+        Ok(text.trim().to_string())
+    }
+}
+
+enum Weights {
+    Safetensors(PathBuf),
+    Gguf(PathBuf),
+}
+
+fn find_weights(model_dir: &Path) -> Result<Weights> {
+    let safetensors = model_dir.join("model.safetensors");
+    if safetensors.exists() {
+        return Ok(Weights::Safetensors(safetensors));
+    }
+
+    let entries = std::fs::read_dir(model_dir)
+        .with_context(|| format!("Whisper model directory not found: {}", model_dir.display()))?;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().and_then(|e| e.to_str()) == Some("gguf") {
+            return Ok(Weights::Gguf(path));
+        }
+    }
+
+    Err(anyhow!("No model.safetensors or .gguf weights found in {}", model_dir.display()))
+}
+
+fn token_id(tokenizer: &Tokenizer, token: &str) -> Result<u32> {
+    tokenizer
+        .token_to_id(token)
+        .ok_or_else(|| anyhow!("Tokenizer is missing special token {}", token))
+}
+
+fn hz_to_mel(hz: f64) -> f64 {
+    // Slaney scale, matching librosa's default used by OpenAI Whisper
+    let f_sp = 200.0 / 3.0;
+    let min_log_hz = 1000.0;
+    let min_log_mel = min_log_hz / f_sp;
+    let logstep = 6.4f64.ln() / 27.0;
+    if hz >= min_log_hz {
+        min_log_mel + (hz / min_log_hz).ln() / logstep
+    } else {
+        hz / f_sp
+    }
+}
+
+fn mel_to_hz(mel: f64) -> f64 {
+    let f_sp = 200.0 / 3.0;
+    let min_log_hz = 1000.0;
+    let min_log_mel = min_log_hz / f_sp;
+    let logstep = 6.4f64.ln() / 27.0;
+    if mel >= min_log_mel {
+        min_log_hz * (logstep * (mel - min_log_mel)).exp()
+    } else {
+        mel * f_sp
+    }
+}
+
+/// Builds the Slaney-normalised mel filterbank (`n_mels` x `n_fft / 2 + 1`) that
+/// Whisper was trained with, equivalent to `librosa.filters.mel`.
+pub fn mel_filters(n_mels: usize, n_fft: usize, sample_rate: usize) -> Vec<f32> {
+    let n_freqs = n_fft / 2 + 1;
+    let nyquist = sample_rate as f64 / 2.0;
+
+    let fft_freqs: Vec<f64> = (0..n_freqs)
+        .map(|i| i as f64 * nyquist / (n_freqs - 1) as f64)
+        .collect();
+
+    let max_mel = hz_to_mel(nyquist);
+    let mel_points: Vec<f64> = (0..n_mels + 2)
+        .map(|i| mel_to_hz(max_mel * i as f64 / (n_mels + 1) as f64))
+        .collect();
+
+    let mut filters = vec![0.0f32; n_mels * n_freqs];
+    for m in 0..n_mels {
+        let (lower, center, upper) = (mel_points[m], mel_points[m + 1], mel_points[m + 2]);
+        let norm = 2.0 / (upper - lower);
+
+        for (k, &freq) in fft_freqs.iter().enumerate() {
+            let rising = (freq - lower) / (center - lower);
+            let falling = (upper - freq) / (upper - center);
+            let weight = rising.min(falling).max(0.0);
+            filters[m * n_freqs + k] = (weight * norm) as f32;
+        }
+    }
+
+    filters
+}
+
+/// Decodes any supported audio file to 16 kHz mono samples for Whisper.
+pub fn load_pcm_16k(file_path: &str) -> Result<Vec<f32>> {
+    Ok(audio_decode::decode_file(file_path, Some(m::SAMPLE_RATE as u32))?.samples)
+}
+
+/// Checks whether usable candle weights are present without loading them.
+pub fn model_available(model_path: &str, model_size: &str) -> bool {
+    let dir = CandleWhisper::resolve_model_dir(model_path, model_size);
+    dir.join("config.json").exists() && dir.join("tokenizer.json").exists() && find_weights(&dir).is_ok()
+}
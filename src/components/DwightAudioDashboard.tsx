@@ -789,20 +789,19 @@ export default function DwightAudioDashboard() {
       
       try {
         // Try enhanced AI chat first, fall back to regular chat
-        let response: DwightResponse;
+        let response: Pick<DwightResponse, 'message'> & { confidence?: number };
         try {
           const enhancedResponse = await enhancedDwightChat(
             dwightInput.trim(), 
             true, // Use advanced model
             [] // No context documents for now - keep it local
           );
+          if (enhancedResponse.simulated) {
+            throw new Error("No language model is configured");
+          }
           
-          response = {
-            message: enhancedResponse.text,
-            confidence: enhancedResponse.confidence,
-            context_used: false,
-            suggestions: []
-          };
+          // Models don't report a confidence, so none is shown
+          response = { message: enhancedResponse.text };
         } catch (enhancedError) {
           console.log("Enhanced AI unavailable, falling back to regular chat:", enhancedError);
           response = await chatWithDwight(dwightInput.trim());
@@ -827,7 +826,7 @@ export default function DwightAudioDashboard() {
           }
           
           // Add confidence indicator if low confidence
-          if (response.confidence < 0.7) {
+          if (response.confidence !== undefined && response.confidence < 0.7) {
             enhancedMessage += " (Though I must admit, I'm not entirely certain about this one, Sir.)";
           }
           
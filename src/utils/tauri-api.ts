@@ -1,4 +1,5 @@
 import { invoke } from '@tauri-apps/api/tauri';
+import { listen, UnlistenFn } from '@tauri-apps/api/event';
 
 export interface AudioRecord {
   id?: number;
@@ -8,6 +9,29 @@ export interface AudioRecord {
   duration: number;
   created_at: string;
   triggers?: string;
+  tags?: string[];
+  starred?: boolean;
+  sha256?: string;
+  capture_device?: string;
+  captured_at?: string;
+}
+
+export interface RecordFilter {
+  offset?: number;
+  limit?: number;
+  created_after?: string;
+  created_before?: string;
+  min_duration?: number;
+  max_duration?: number;
+  has_transcript?: boolean;
+  tag?: string;
+}
+
+export interface RecordPage {
+  records: AudioRecord[];
+  total: number;
+  offset: number;
+  limit: number;
 }
 
 export interface DwightResponse {
@@ -26,19 +50,107 @@ export interface SoundTrigger {
 }
 
 // Enhanced AI interfaces
+export interface LlmUsage {
+  prompt_tokens?: number;
+  completion_tokens?: number;
+  load_ms?: number;
+  prompt_eval_ms?: number;
+  eval_ms?: number;
+}
+
 export interface LlamaResponse {
   text: string;
-  tokens_used: number;
+  // Prompt plus answer tokens, when the server reports them
+  tokens_used?: number;
   processing_time_ms: number;
-  confidence: number;
+  // A canned stand-in because no model is configured, not a model's answer
+  simulated: boolean;
+  usage?: LlmUsage;
+  tokens_per_second?: number;
+}
+
+// Per-request overrides of a model's defaults
+export interface GenerationOptions {
+  temperature?: number;
+  top_p?: number;
+  max_tokens?: number;
+  context_length?: number;
+  system_prompt?: string;
+  seed?: number;
+  stop?: string[];
+}
+
+export interface LlmToken {
+  request_id: string;
+  token: string;
+}
+
+export interface LlmStreamEnd {
+  request_id: string;
+  response?: LlamaResponse;
+  cancelled: boolean;
+  error?: string;
+}
+
+export interface RagHit {
+  score: number;
+  source: { type: 'recording' | 'memory'; id: number };
+  text: string;
+  record_title?: string;
+  start?: number;
+  end?: number;
+  created_at: string;
+  citation: string;
+}
+
+export interface RagSearchResult {
+  query: string;
+  hits: RagHit[];
+  answer?: LlamaResponse;
+}
+
+export interface RagIndexReport {
+  model: string;
+  sources_indexed: number;
+  chunks_embedded: number;
+  up_to_date: number;
+  errors: string[];
+}
+
+export interface RagStatus {
+  model_dir: string;
+  model_available: boolean;
+  indexed_chunks: number;
+  indexed_sources: number;
+}
+
+export interface ModelDefaults {
+  temperature: number;
+  top_p: number;
+  max_tokens: number;
+  context_length?: number;
+  system_prompt?: string;
 }
 
 export interface ModelConfig {
+  id: string;
   name: string;
   model_type: string;
   api_endpoint?: string;
   local_path?: string;
   enabled: boolean;
+  provider?: 'ollama_generate' | 'ollama_chat' | 'openai_chat' | 'stub';
+  api_key?: string;
+  defaults?: ModelDefaults;
+}
+
+export interface ModelProbe {
+  id: string;
+  endpoint?: string;
+  reachable: boolean;
+  installed_models: string[];
+  model_installed?: boolean;
+  error?: string;
 }
 
 export interface TranscriptionResult {
@@ -47,6 +159,19 @@ export interface TranscriptionResult {
   language: string;
   processing_time_ms: number;
   confidence: number;
+  backend: string;
+  simulated: boolean;
+}
+
+export interface WhisperConfig {
+  model_path: string;
+  model_size: string;
+  language?: string;
+  backend: string;
+  cli_path: string;
+  server_url?: string;
+  use_gpu: boolean;
+  skip_silence: boolean;
 }
 
 export interface TranscriptionSegment {
@@ -56,6 +181,175 @@ export interface TranscriptionSegment {
   confidence: number;
 }
 
+export interface CaptureStatus {
+  is_capturing: boolean;
+  device_name?: string;
+  sample_rate: number;
+  buffer_capacity_seconds: number;
+  buffered_seconds: number;
+  started_at?: string;
+  speech_active: boolean;
+}
+
+// Voice activity during capture; times are seconds since capture started
+export type VadEvent =
+  | { type: 'speech_start'; start: number }
+  | { type: 'speech_end'; start: number; end: number; confidence: number };
+
+export interface TriggerFired {
+  trigger_id?: number;
+  trigger_type: string;
+  trigger_value: string;
+  matched: string;
+  confidence: number;
+  detected_at: string;
+}
+
+export interface TriggerClipSaved {
+  record: AudioRecord;
+  fired: TriggerFired[];
+}
+
+export interface TriggerEngineStatus {
+  is_running: boolean;
+  active_triggers: number;
+  pre_roll_seconds: number;
+  post_roll_seconds: number;
+  keyword_transcription: boolean;
+  fired_count: number;
+  last_fired?: TriggerFired;
+  last_error?: string;
+}
+
+export interface StoredSegment {
+  id: number;
+  record_id: number;
+  start: number;
+  end: number;
+  text: string;
+  confidence: number;
+}
+
+export interface AudioEvent {
+  id: number;
+  record_id: number;
+  start: number;
+  end: number;
+  event_type: string;
+  confidence: number;
+}
+
+export interface AuditEntry {
+  id: number;
+  record_id?: number;
+  action: string;
+  details: string;
+  created_at: string;
+  prev_hash: string;
+  hash: string;
+}
+
+export interface IntegrityReport {
+  record_id: number;
+  file_path: string;
+  status: 'intact' | 'tampered' | 'missing' | 'unhashed';
+  expected_sha256?: string;
+  actual_sha256?: string;
+  chain_valid: boolean;
+  problems: string[];
+  history: AuditEntry[];
+  checked_at: string;
+}
+
+export interface EvidenceExportReport {
+  path: string;
+  records: number;
+  files: number;
+  missing_files: string[];
+  public_key: string;
+  size_bytes: number;
+}
+
+export interface BundleVerification {
+  valid: boolean;
+  consistent: boolean;
+  signature_valid: boolean;
+  public_key?: string;
+  trusted_key?: boolean;
+  created_at?: string;
+  files_checked: number;
+  audio_matching_capture: number;
+  audio_unhashed: number;
+  errors: string[];
+}
+
+export interface SegmentHit {
+  segment_id: number;
+  start: number;
+  end: number;
+  snippet: string;
+}
+
+export interface SearchHit {
+  record_id: number;
+  title: string;
+  file_path: string;
+  created_at: string;
+  snippet?: string;
+  score: number;
+  segments: SegmentHit[];
+}
+
+export interface StorageStatus {
+  initialized: boolean;
+  locked: boolean;
+  key_storage?: 'keyring' | 'passphrase';
+  error?: string;
+}
+
+export interface RetentionPolicy {
+  enabled: boolean;
+  max_age_days?: number;
+  max_total_mb?: number;
+  keep_triggered: boolean;
+  keep_starred: boolean;
+  interval_minutes: number;
+}
+
+export interface RetentionCandidate {
+  record_id: number;
+  title: string;
+  file_path: string;
+  created_at: string;
+  size_bytes: number;
+  reason: 'max_age' | 'max_total_size';
+}
+
+export interface RetentionReport {
+  dry_run: boolean;
+  removed: RetentionCandidate[];
+  freed_bytes: number;
+  remaining_bytes: number;
+  errors: string[];
+}
+
+export interface ExportReport {
+  path: string;
+  records: number;
+  files: number;
+  missing_files: string[];
+  size_bytes: number;
+}
+
+export interface ImportReport {
+  records_imported: number;
+  duplicates_skipped: string[];
+  triggers_imported: number;
+  memories_imported: number;
+  missing_files: string[];
+  errors: string[];
+}
+
 export interface PythonResult {
   success: boolean;
   result: any;
@@ -115,13 +409,15 @@ export async function chatWithDwight(userInput: string): Promise<DwightResponse>
 export async function enhancedDwightChat(
   userInput: string,
   useAdvancedModel?: boolean,
-  contextDocuments?: string[]
+  contextDocuments?: string[],
+  options?: GenerationOptions
 ): Promise<LlamaResponse> {
   try {
     return await invoke('enhanced_dwight_chat', { 
       userInput, 
       useAdvancedModel,
-      contextDocuments 
+      contextDocuments,
+      options
     });
   } catch (error) {
     console.error('Enhanced Dwight chat error:', error);
@@ -132,39 +428,148 @@ export async function enhancedDwightChat(
 // Chat with Llama models
 export async function chatWithLlama(
   prompt: string,
-  model?: string
+  model?: string,
+  options?: GenerationOptions
 ): Promise<LlamaResponse> {
   try {
-    return await invoke('chat_with_llama', { prompt, model });
+    return await invoke('chat_with_llama', { prompt, model, options });
   } catch (error) {
     console.error('Llama chat error:', error);
     throw error;
   }
 }
 
-// RAG search
+// Streaming chat: tokens and the end of each stream arrive as events keyed by request id
+export async function streamLlama(
+  prompt: string,
+  model?: string,
+  requestId?: string,
+  options?: GenerationOptions
+): Promise<string> {
+  try {
+    return await invoke('stream_llama', { prompt, model, requestId, options });
+  } catch (error) {
+    console.error('Stream Llama error:', error);
+    throw error;
+  }
+}
+
+export async function cancelLlmStream(requestId: string): Promise<boolean> {
+  try {
+    return await invoke('cancel_llm_stream', { requestId });
+  } catch (error) {
+    console.error('Cancel LLM stream error:', error);
+    throw error;
+  }
+}
+
+export async function onLlmToken(handler: (token: LlmToken) => void): Promise<UnlistenFn> {
+  return await listen<LlmToken>('llm-token', (event) => handler(event.payload));
+}
+
+export async function onLlmStreamEnd(handler: (end: LlmStreamEnd) => void): Promise<UnlistenFn> {
+  return await listen<LlmStreamEnd>('llm-stream-end', (event) => handler(event.payload));
+}
+
+// RAG search over recordings and past conversations
 export async function ragSearch(
   query: string,
-  contextDocuments: string[]
-): Promise<LlamaResponse> {
+  topK?: number,
+  generate?: boolean,
+  model?: string,
+  options?: GenerationOptions
+): Promise<RagSearchResult> {
   try {
-    return await invoke('rag_search', { query, contextDocuments });
+    return await invoke('rag_search', { query, topK, generate, model, options });
   } catch (error) {
     console.error('RAG search error:', error);
     throw error;
   }
 }
 
+export async function updateRagIndex(): Promise<RagIndexReport> {
+  try {
+    return await invoke('update_rag_index');
+  } catch (error) {
+    console.error('Update RAG index error:', error);
+    throw error;
+  }
+}
+
+export async function getRagStatus(): Promise<RagStatus> {
+  try {
+    return await invoke('get_rag_status');
+  } catch (error) {
+    console.error('Get RAG status error:', error);
+    throw error;
+  }
+}
+
 // Get available AI models
-export async function getAiModels(): Promise<ModelConfig[]> {
+export async function getAiModels(includeDisabled?: boolean): Promise<ModelConfig[]> {
   try {
-    return await invoke('get_ai_models');
+    return await invoke('get_ai_models', { includeDisabled });
   } catch (error) {
     console.error('Get AI models error:', error);
     throw error;
   }
 }
 
+// Model registry
+export async function addAiModel(model: ModelConfig): Promise<ModelConfig> {
+  try {
+    return await invoke('add_ai_model', { model });
+  } catch (error) {
+    console.error('Add AI model error:', error);
+    throw error;
+  }
+}
+
+export async function updateAiModel(model: ModelConfig): Promise<ModelConfig> {
+  try {
+    return await invoke('update_ai_model', { model });
+  } catch (error) {
+    console.error('Update AI model error:', error);
+    throw error;
+  }
+}
+
+export async function removeAiModel(id: string): Promise<boolean> {
+  try {
+    return await invoke('remove_ai_model', { id });
+  } catch (error) {
+    console.error('Remove AI model error:', error);
+    throw error;
+  }
+}
+
+export async function setAiModelEnabled(id: string, enabled: boolean): Promise<ModelConfig> {
+  try {
+    return await invoke('set_ai_model_enabled', { id, enabled });
+  } catch (error) {
+    console.error('Set AI model enabled error:', error);
+    throw error;
+  }
+}
+
+export async function resetAiModels(): Promise<ModelConfig[]> {
+  try {
+    return await invoke('reset_ai_models');
+  } catch (error) {
+    console.error('Reset AI models error:', error);
+    throw error;
+  }
+}
+
+export async function probeAiModels(id?: string): Promise<ModelProbe[]> {
+  try {
+    return await invoke('probe_ai_models', { id });
+  } catch (error) {
+    console.error('Probe AI models error:', error);
+    throw error;
+  }
+}
+
 // AI audio analysis
 export async function aiAudioAnalysis(
   audioFeatures: number[],
@@ -212,6 +617,18 @@ export async function pythonAudioPreprocessing(
   }
 }
 
+export async function nativeAudioPreprocessing(
+  filePath: string,
+  sampleRate?: number
+): Promise<PythonResult> {
+  try {
+    return await invoke('native_audio_preprocessing', { filePath, sampleRate });
+  } catch (error) {
+    console.error('Native audio preprocessing error:', error);
+    throw error;
+  }
+}
+
 export async function pythonMlClassification(
   audioFeatures: any
 ): Promise<PythonResult> {
@@ -225,17 +642,25 @@ export async function pythonMlClassification(
 
 // Whisper configuration
 export async function configureWhisper(
-  modelSize: string,
+  modelSize?: string,
   language?: string,
   useCpp?: boolean,
-  useGpu?: boolean
-): Promise<string> {
+  useGpu?: boolean,
+  backend?: string,
+  paths?: { modelPath?: string; cliPath?: string; serverUrl?: string },
+  skipSilence?: boolean
+): Promise<WhisperConfig> {
   try {
     return await invoke('configure_whisper', { 
       modelSize, 
       language, 
+      backend,
       useCpp, 
-      useGpu 
+      useGpu,
+      modelPath: paths?.modelPath,
+      cliPath: paths?.cliPath,
+      serverUrl: paths?.serverUrl,
+      skipSilence
     });
   } catch (error) {
     console.error('Whisper configuration error:', error);
@@ -252,6 +677,101 @@ export async function getWhisperStatus(): Promise<any> {
   }
 }
 
+// Rolling audio capture (DVR mode)
+export async function startAudioCapture(bufferMinutes?: number): Promise<CaptureStatus> {
+  try {
+    return await invoke('start_audio_capture', { bufferMinutes });
+  } catch (error) {
+    console.error('Start audio capture error:', error);
+    throw error;
+  }
+}
+
+export async function stopAudioCapture(): Promise<CaptureStatus> {
+  try {
+    return await invoke('stop_audio_capture');
+  } catch (error) {
+    console.error('Stop audio capture error:', error);
+    throw error;
+  }
+}
+
+export async function getCaptureStatus(): Promise<CaptureStatus> {
+  try {
+    return await invoke('get_capture_status');
+  } catch (error) {
+    console.error('Get capture status error:', error);
+    throw error;
+  }
+}
+
+export async function saveAudioClip(seconds: number, title?: string): Promise<AudioRecord> {
+  try {
+    return await invoke('save_audio_clip', { seconds, title });
+  } catch (error) {
+    console.error('Save audio clip error:', error);
+    throw error;
+  }
+}
+
+export async function onVadEvent(handler: (event: VadEvent) => void): Promise<UnlistenFn> {
+  return await listen<VadEvent>('vad-event', (event) => handler(event.payload));
+}
+
+// Live trigger engine (requires audio capture to be running)
+export async function startTriggerEngine(
+  preRollSeconds?: number,
+  postRollSeconds?: number
+): Promise<TriggerEngineStatus> {
+  try {
+    return await invoke('start_trigger_engine', { preRollSeconds, postRollSeconds });
+  } catch (error) {
+    console.error('Start trigger engine error:', error);
+    throw error;
+  }
+}
+
+export async function stopTriggerEngine(): Promise<TriggerEngineStatus> {
+  try {
+    return await invoke('stop_trigger_engine');
+  } catch (error) {
+    console.error('Stop trigger engine error:', error);
+    throw error;
+  }
+}
+
+export async function getTriggerEngineStatus(): Promise<TriggerEngineStatus> {
+  try {
+    return await invoke('get_trigger_engine_status');
+  } catch (error) {
+    console.error('Get trigger engine status error:', error);
+    throw error;
+  }
+}
+
+export async function onTriggerFired(handler: (fired: TriggerFired) => void): Promise<UnlistenFn> {
+  return await listen<TriggerFired>('trigger-fired', (event) => handler(event.payload));
+}
+
+export async function onTriggerClipSaved(handler: (saved: TriggerClipSaved) => void): Promise<UnlistenFn> {
+  return await listen<TriggerClipSaved>('trigger-clip-saved', (event) => handler(event.payload));
+}
+
+export async function onLiveTranscript(handler: (text: string) => void): Promise<UnlistenFn> {
+  return await listen<string>('live-transcript', (event) => handler(event.payload));
+}
+
+// Convert any supported recording to a 16 kHz mono WAV for Whisper, saved
+// to the library as a new recording
+export async function convertAudioForWhisper(filePath: string): Promise<AudioRecord> {
+  try {
+    return await invoke('convert_audio_for_whisper', { filePath });
+  } catch (error) {
+    console.error('Audio conversion error:', error);
+    throw error;
+  }
+}
+
 // AI-powered audio intelligence
 export async function analyzeAudioIntelligence(audioFilePath: string): Promise<string[]> {
   try {
@@ -262,6 +782,105 @@ export async function analyzeAudioIntelligence(audioFilePath: string): Promise<s
   }
 }
 
+// Encrypted storage
+export async function getStorageStatus(): Promise<StorageStatus> {
+  try {
+    return await invoke('get_storage_status');
+  } catch (error) {
+    console.error('Get storage status error:', error);
+    throw error;
+  }
+}
+
+export async function unlockStorage(passphrase?: string): Promise<StorageStatus> {
+  try {
+    return await invoke('unlock_storage', { passphrase });
+  } catch (error) {
+    console.error('Unlock storage error:', error);
+    throw error;
+  }
+}
+
+export async function lockStorage(): Promise<StorageStatus> {
+  try {
+    return await invoke('lock_storage');
+  } catch (error) {
+    console.error('Lock storage error:', error);
+    throw error;
+  }
+}
+
+// Leave newPassphrase out to keep the key in the OS keyring instead
+export async function changePassphrase(currentPassphrase?: string, newPassphrase?: string): Promise<StorageStatus> {
+  try {
+    return await invoke('change_passphrase', { currentPassphrase, newPassphrase });
+  } catch (error) {
+    console.error('Change passphrase error:', error);
+    throw error;
+  }
+}
+
+// Retention and storage cleanup
+export async function getRetentionPolicy(): Promise<RetentionPolicy> {
+  try {
+    return await invoke('get_retention_policy');
+  } catch (error) {
+    console.error('Get retention policy error:', error);
+    throw error;
+  }
+}
+
+export async function setRetentionPolicy(policy: RetentionPolicy): Promise<RetentionPolicy> {
+  try {
+    return await invoke('set_retention_policy', { policy });
+  } catch (error) {
+    console.error('Set retention policy error:', error);
+    throw error;
+  }
+}
+
+// Dry run: reports what would be removed without deleting anything
+export async function previewRetention(policy?: RetentionPolicy): Promise<RetentionReport> {
+  try {
+    return await invoke('preview_retention', { policy });
+  } catch (error) {
+    console.error('Preview retention error:', error);
+    throw error;
+  }
+}
+
+export async function runRetentionNow(): Promise<RetentionReport> {
+  try {
+    return await invoke('run_retention_now');
+  } catch (error) {
+    console.error('Run retention error:', error);
+    throw error;
+  }
+}
+
+export async function onRetentionCleanup(handler: (report: RetentionReport) => void): Promise<UnlistenFn> {
+  return await listen<RetentionReport>('retention-cleanup', (event) => handler(event.payload));
+}
+
+// Library backup and transfer
+export async function exportLibrary(destination: string, passphrase: string): Promise<ExportReport> {
+  try {
+    return await invoke('export_library', { destination, passphrase });
+  } catch (error) {
+    console.error('Export library error:', error);
+    throw error;
+  }
+}
+
+export async function importLibrary(archivePath: string, passphrase: string): Promise<ImportReport> {
+  try {
+    return await invoke('import_library', { archivePath, passphrase });
+  } catch (error) {
+    console.error('Import library error:', error);
+    throw error;
+  }
+}
+
 // Database operations
 export async function saveAudioRecord(record: Omit<AudioRecord, 'id' | 'created_at'>): Promise<number> {
   try {
@@ -271,6 +890,7 @@ export async function saveAudioRecord(record: Omit<AudioRecord, 'id' | 'created_
       transcript: record.transcript,
       duration: record.duration,
       triggers: record.triggers,
+      tags: record.tags,
     });
   } catch (error) {
     console.error('Save audio record error:', error);
@@ -287,6 +907,54 @@ export async function getAudioRecords(): Promise<AudioRecord[]> {
   }
 }
 
+export async function listAudioRecords(filter?: RecordFilter): Promise<RecordPage> {
+  try {
+    return await invoke('list_audio_records', { filter });
+  } catch (error) {
+    console.error('List audio records error:', error);
+    throw error;
+  }
+}
+
+export async function getAudioRecord(id: number): Promise<AudioRecord> {
+  try {
+    return await invoke('get_audio_record', { id });
+  } catch (error) {
+    console.error('Get audio record error:', error);
+    throw error;
+  }
+}
+
+export async function updateAudioRecord(
+  id: number,
+  changes: { title?: string; tags?: string[] }
+): Promise<AudioRecord> {
+  try {
+    return await invoke('update_audio_record', { id, title: changes.title, tags: changes.tags });
+  } catch (error) {
+    console.error('Update audio record error:', error);
+    throw error;
+  }
+}
+
+export async function setRecordStarred(id: number, starred: boolean): Promise<void> {
+  try {
+    await invoke('set_record_starred', { id, starred });
+  } catch (error) {
+    console.error('Set record starred error:', error);
+    throw error;
+  }
+}
+
+export async function deleteAudioRecord(id: number): Promise<void> {
+  try {
+    await invoke('delete_audio_record', { id });
+  } catch (error) {
+    console.error('Delete audio record error:', error);
+    throw error;
+  }
+}
+
 export async function saveTrigger(triggerType: string, triggerValue: string): Promise<number> {
   try {
     return await invoke('save_trigger', { triggerType, triggerValue });
@@ -296,11 +964,122 @@ export async function saveTrigger(triggerType: string, triggerValue: string): Pr
   }
 }
 
-export async function getTriggers(): Promise<SoundTrigger[]> {
+export async function getTriggers(includeInactive?: boolean): Promise<SoundTrigger[]> {
   try {
-    return await invoke('get_triggers');
+    return await invoke('get_triggers', { includeInactive });
   } catch (error) {
     console.error('Get triggers error:', error);
     throw error;
   }
-}
\ No newline at end of file
+}
+
+export async function updateTrigger(
+  id: number,
+  changes: { triggerType?: string; triggerValue?: string }
+): Promise<void> {
+  try {
+    await invoke('update_trigger', { id, ...changes });
+  } catch (error) {
+    console.error('Update trigger error:', error);
+    throw error;
+  }
+}
+
+export async function setTriggerActive(id: number, active: boolean): Promise<void> {
+  try {
+    await invoke('set_trigger_active', { id, active });
+  } catch (error) {
+    console.error('Set trigger active error:', error);
+    throw error;
+  }
+}
+
+export async function deleteTrigger(id: number): Promise<void> {
+  try {
+    await invoke('delete_trigger', { id });
+  } catch (error) {
+    console.error('Delete trigger error:', error);
+    throw error;
+  }
+}
+
+// Transcript search
+export async function searchTranscripts(query: string, limit?: number): Promise<SearchHit[]> {
+  try {
+    return await invoke('search_transcripts', { query, limit });
+  } catch (error) {
+    console.error('Search transcripts error:', error);
+    throw error;
+  }
+}
+
+// Stored segments and analysis events
+export async function getSegments(recordId?: number, start?: number, end?: number): Promise<StoredSegment[]> {
+  try {
+    return await invoke('get_segments', { recordId, start, end });
+  } catch (error) {
+    console.error('Get segments error:', error);
+    throw error;
+  }
+}
+
+export async function getAudioEvents(
+  recordId?: number,
+  eventType?: string,
+  start?: number,
+  end?: number
+): Promise<AudioEvent[]> {
+  try {
+    return await invoke('get_audio_events', { recordId, eventType, start, end });
+  } catch (error) {
+    console.error('Get audio events error:', error);
+    throw error;
+  }
+}
+
+// Chain of custody
+export async function verifyRecordIntegrity(recordId: number): Promise<IntegrityReport> {
+  try {
+    return await invoke('verify_record_integrity', { recordId });
+  } catch (error) {
+    console.error('Verify record integrity error:', error);
+    throw error;
+  }
+}
+
+export async function getAuditLog(recordId?: number, limit?: number): Promise<AuditEntry[]> {
+  try {
+    return await invoke('get_audit_log', { recordId, limit });
+  } catch (error) {
+    console.error('Get audit log error:', error);
+    throw error;
+  }
+}
+
+// Signed evidence bundles
+export async function exportEvidenceBundle(recordIds: number[], destination: string): Promise<EvidenceExportReport> {
+  try {
+    return await invoke('export_evidence_bundle', { recordIds, destination });
+  } catch (error) {
+    console.error('Export evidence bundle error:', error);
+    throw error;
+  }
+}
+
+export async function verifyEvidenceBundle(bundlePath: string, trustedKey?: string): Promise<BundleVerification> {
+  try {
+    return await invoke('verify_evidence_bundle', { bundlePath, trustedKey });
+  } catch (error) {
+    console.error('Verify evidence bundle error:', error);
+    throw error;
+  }
+}
+
+export async function getEvidencePublicKey(): Promise<string> {
+  try {
+    return await invoke('get_evidence_public_key');
+  } catch (error) {
+    console.error('Get evidence public key error:', error);
+    throw error;
+  }
+}